@@ -0,0 +1,89 @@
+use crate::layout::Layout;
+use crate::theme::Theme;
+use crate::ui::{self, ACTION_SCALE};
+
+use ggez::{graphics, Context, GameResult};
+
+/// A button below the move list for ending the game other than by checkmate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action
+{
+    Resign,
+    /// Offers a draw, or accepts the one offered.
+    Draw,
+    Decline,
+    /// Claims a draw by threefold repetition or the fifty-move rule.
+    Claim,
+}
+
+const ACTIONS: [Action; 4] = [Action::Resign, Action::Draw, Action::Decline, Action::Claim];
+
+/// The buttons side by side, filling the row.
+fn buttons(layout: &Layout) -> Vec<(Action, graphics::Rect)>
+{
+    ui::button_row(&ACTIONS, layout.actions_rect())
+}
+
+pub fn button_at(layout: &Layout, x: f32, y: f32) -> Option<Action>
+{
+    ui::button_at(&buttons(layout), x, y)
+}
+
+/// Draws the buttons, `label` gives the text of each and whether it can be pressed right now.
+pub fn draw(ctx: &mut Context, layout: &Layout, theme: &Theme, label: impl Fn(Action) -> (String, bool)) -> GameResult
+{
+    for (action, rect) in buttons(layout)
+    {
+        let (content, enabled) = label(action);
+        let background = graphics::Mesh::new_rectangle
+        (
+            ctx,
+            graphics::DrawMode::fill(),
+            rect,
+            if enabled { [0.0, 0.0, 0.0, 0.35].into() } else { [0.0, 0.0, 0.0, 0.15].into() },
+        )?;
+        graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+
+        let text = graphics::Text::new
+        (
+            graphics::TextFragment::from(content)
+                .font(theme.font)
+                .scale(layout.text_scale(ACTION_SCALE)),
+        );
+        let dimensions = text.dimensions(ctx);
+        // labels shrink to fit narrow windows
+        let fit = ((rect.w - 4.0) / dimensions.w).min(1.0);
+        let alpha = if enabled { 1.0 } else { 0.4 };
+        graphics::draw
+        (
+            ctx,
+            &text,
+            graphics::DrawParam::default()
+                .color(graphics::Color::new(theme.text.r, theme.text.g, theme.text.b, alpha))
+                .scale([fit, fit])
+                .dest([rect.x + (rect.w - dimensions.w * fit) / 2.0, rect.y + (rect.h - dimensions.h * fit) / 2.0]),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{button_at, buttons, ACTIONS};
+    use crate::layout::{Layout, SCREEN_SIZE};
+
+    #[test]
+    fn buttons_fit_between_the_move_list_and_the_move_box()
+    {
+        let layout = Layout::new(SCREEN_SIZE.0, SCREEN_SIZE.1);
+        let (list, area) = (layout.move_list_rect(), layout.actions_rect());
+        assert_eq!(area.y, list.y + list.h);
+        assert_eq!(area.y + area.h, layout.input_rect().y);
+
+        let found: Vec<_> = buttons(&layout).iter().map(|(_, rect)| button_at(&layout, rect.x + rect.w / 2.0, rect.y + rect.h / 2.0)).collect();
+        assert_eq!(found, ACTIONS.iter().map(|action| Some(*action)).collect::<Vec<_>>());
+        assert_eq!(button_at(&layout, area.x - 1.0, area.y + 1.0), None);
+    }
+}
@@ -0,0 +1,89 @@
+use super::{piece_char, Game};
+
+impl Game {
+    /// Blows up the square of an Atomic capture: the capturing piece and every piece next to it but the pawns.
+    /// Everything blown up counts as taken. Called by `do_move` once the capture is made.
+    pub(crate) fn explode(&mut self, _file: u32, _rank: u32) {
+        for file in _file.saturating_sub(1)..(_file + 2).min(8) {
+            for rank in _rank.saturating_sub(1)..(_rank + 2).min(8) {
+                let bit_pos = 2_u64.pow(file * 8 + rank);
+                let centre = (file == _file) & (rank == _rank);
+                if !centre & (self.pieces[0] & bit_pos != 0) {
+                    continue;
+                }
+                if let Some(index_of_piece) = (0..6).find(|index| self.pieces[*index] & bit_pos != 0) {
+                    self.captured.push(piece_char(index_of_piece, self.colour_of_piece[0] & bit_pos != 0));
+                    self.pieces[index_of_piece] &= !bit_pos;
+                    self.colour_of_piece[0] &= !bit_pos;
+                    self.colour_of_piece[1] &= !bit_pos;
+                }
+            }
+        }
+    }
+
+    /// True if the two kings stand next to each other. In Atomic neither can then be in check,
+    /// taking one king would blow up the other with it.
+    pub(crate) fn kings_touch(&self) -> bool {
+        let white = self.pieces[5] & self.colour_of_piece[0];
+        let black = self.pieces[5] & self.colour_of_piece[1];
+        if (white == 0) | (black == 0) {
+            return false;
+        }
+        let (white, black) = (white.trailing_zeros(), black.trailing_zeros());
+        ((white / 8).abs_diff(black / 8) <= 1) & ((white % 8).abs_diff(black % 8) <= 1)
+    }
+
+    /// A castling right is lost once its rook or king was blown up, they never moved so `update_castling_rights` misses it.
+    pub(crate) fn clear_exploded_castling(&mut self) {
+        for right in 0..4 {
+            let rook = self.pieces[2] & self.colour_of_piece[right / 2] & 2_u64.pow(self.castling_rook_index(right));
+            if (rook == 0) | !self.has_king(right < 2) {
+                self.castling[right] = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_perft, Ending, Game, GameState, Variant};
+
+    #[test]
+    fn captures_explode() {
+        let mut game = Game::from_variant_fen(Variant::Atomic, "rnbqkbnr/pppp1ppp/8/4p3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        assert!(game.make_move("D4", "E5"));
+        // the pawns next to e5 stay, the two that met are gone
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppp1ppp/8/8/8/8/PPP1PPPP/RNBQKBNR b KQkq - 0 2");
+        assert_eq!(game.get_captured(), &['p', 'P']);
+
+        // the knight takes on f7 and blows up everything around it but the pawns, the king too
+        let mut game = Game::from_variant_fen(Variant::Atomic, "rnbqkb1r/pppppppp/7n/6N1/8/8/PPPPPPPP/RNBQKB1R w KQkq - 0 1").unwrap();
+        assert_eq!(game.move_to_san("G5", "F7").as_deref(), Some("Nxf7"));
+        assert!(game.make_move("G5", "F7"));
+        assert_eq!(game.to_fen(), "rnbq3r/ppppp1pp/7n/8/8/8/PPPPPPPP/RNBQKB1R b KQ - 0 1");
+        assert_eq!(game.get_ending(), Some(Ending::Explosion { white_wins: true }));
+        assert_eq!(game.get_possible_moves("H6"), Some(vec![]));
+    }
+
+    #[test]
+    fn kings_do_not_capture_or_check_each_other() {
+        // standing next to the other king shields the king from the queen, and it may not take that king
+        let mut game = Game::from_variant_fen(Variant::Atomic, "8/8/8/8/8/8/3k4/4K2q w - - 0 1").unwrap();
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        let mut king_moves = game.get_possible_moves("E1").unwrap();
+        king_moves.sort();
+        assert_eq!(king_moves, vec!["D1", "E2", "F2"]);
+
+        // taking next to the own king is not allowed
+        let mut game = Game::from_variant_fen(Variant::Atomic, "4k3/8/8/8/8/8/3p4/3QK3 w - - 0 1").unwrap();
+        assert!(!game.get_possible_moves("D1").unwrap().contains(&"D2".to_string()));
+    }
+
+    #[test]
+    fn perft_matches_known_numbers() {
+        // the kings touch after castling queen side, and the f-pawn can be taken en passant
+        assert_perft(Variant::Atomic, "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1", &[40, 1238, 45237]);
+        assert_perft(Variant::Atomic, "8/8/8/8/8/8/2k5/R3K2R w KQ - 0 1", &[26, 153, 4241]);
+        assert_perft(Variant::Atomic, "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", &[31, 705, 21511]);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::cli::Player;
+use crate::clock::{Clock, TimeControl};
+use crate::config::{Config, ConfigError, Value};
+use crate::layout::Layout;
+use crate::settings::{self, MAX_DEPTH};
+use crate::theme::Theme;
+use crate::ui::{self, OFFER_SCALE};
+
+use chess_gui::{Game, MoveInputError, ParsedMove, Variant};
+use ggez::{graphics, Context, GameResult};
+use std::{fmt, fs, io, path::PathBuf};
+
+const SAVE_FILE: &str = "autosave.toml";
+/// Version of the save format written into every file, files of any other version are not read.
+const FORMAT_VERSION: f64 = 1.0;
+/// Seconds between saves while a game is being played, besides the save when the window closes.
+pub const AUTOSAVE_INTERVAL: f32 = 30.0;
+
+/// Height of one row of the resume offer, in tiles.
+const ROW_HEIGHT: f32 = 0.6;
+
+/// Why a saved game could not be read back.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SaveError
+{
+    /// The file is not in the config format at all.
+    Config(ConfigError),
+    /// The file has no format version, or one this build does not know.
+    Version(Option<f64>),
+    /// A value is missing or out of range, holds the key.
+    Invalid(String),
+    /// A saved move can not be played, holds its ply, counted from 1, and its text.
+    Move(usize, String, MoveInputError),
+}
+
+impl fmt::Display for SaveError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            SaveError::Config(error) => write!(f, "{}", error),
+            SaveError::Version(None) => write!(f, "no format version"),
+            SaveError::Version(Some(version)) => write!(f, "unknown format version {}", version),
+            SaveError::Invalid(key) => write!(f, "missing or invalid `{}`", key),
+            SaveError::Move(ply, san, error) => write!(f, "move {} ({}): {}", ply, san, error),
+        }
+    }
+}
+
+/// A game in progress as it is written to disk: where it started, the moves since, who plays and the clock.
+pub struct SavedGame
+{
+    pub start: Game,
+    pub moves: Vec<ParsedMove>,
+    // The moves as written in the file, kept to write them back the same way.
+    san: Vec<String>,
+    pub players: [Player; 2],
+    pub engine_depth: u32,
+    pub clock: Option<Clock>,
+}
+
+impl SavedGame
+{
+    /// Takes a game and the notation of the moves played since `start`, which have to be legal.
+    pub fn new(start: Game, san: Vec<String>, players: [Player; 2], engine_depth: u32, clock: Option<Clock>) -> Result<SavedGame, SaveError>
+    {
+        let moves = replay(&start, &san)?;
+        Ok(SavedGame { start, moves, san, players, engine_depth, clock })
+    }
+
+    /// The save file next to the settings.
+    pub fn path() -> Option<PathBuf>
+    {
+        settings::config_dir().map(|dir| dir.join(SAVE_FILE))
+    }
+
+    /// Reads the last saved game, None if there is none.
+    pub fn load() -> Result<Option<SavedGame>, String>
+    {
+        let path = match SavedGame::path()
+        {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(None),
+        };
+
+        let text = fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        SavedGame::parse(&text).map(Some).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Writes the game to a temporary file first, so a crash while saving leaves the last save intact.
+    pub fn save(&self) -> io::Result<()>
+    {
+        let path = SavedGame::path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, self.to_config().to_string())?;
+        fs::rename(temporary, path)
+    }
+
+    /// Deletes the saved game, once it is finished or replaced by a new one.
+    pub fn remove() -> io::Result<()>
+    {
+        match SavedGame::path()
+        {
+            Some(path) if path.is_file() => fs::remove_file(path),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<SavedGame, SaveError>
+    {
+        let config = Config::parse(text).map_err(SaveError::Config)?;
+
+        match config.get_number("save", "format")
+        {
+            Some(version) if version == FORMAT_VERSION => (),
+            version => return Err(SaveError::Version(version)),
+        }
+
+        let invalid = |key: &str| SaveError::Invalid(key.to_string());
+
+        // games saved before variants were played by the standard rules
+        let variant = match config.get_str("game", "variant")
+        {
+            Some(key) => Variant::parse(key).ok_or_else(|| invalid("variant"))?,
+            None => Variant::Standard,
+        };
+        let start = config.get_str("game", "start").and_then(|fen| Game::from_variant_fen(variant, fen).ok()).ok_or_else(|| invalid("start"))?;
+        let san: Vec<String> = config.get_str("game", "moves").ok_or_else(|| invalid("moves"))?
+            .split_whitespace().map(str::to_string).collect();
+
+        let player = |key: &str| config.get_str("players", key).and_then(Player::parse).ok_or_else(|| invalid(key));
+        let players = [player("white")?, player("black")?];
+        let engine_depth = config.get_number("players", "depth")
+            .filter(|depth| depth.fract() == 0.0 && *depth >= 1.0 && *depth <= MAX_DEPTH as f64)
+            .ok_or_else(|| invalid("depth"))? as u32;
+
+        let clock = match config.get_str("clock", "time_control")
+        {
+            None => None,
+            Some(text) =>
+            {
+                let control = TimeControl::parse(text).ok_or_else(|| invalid("time_control"))?;
+                let remaining = |key: &str| config.get_number("clock", key)
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                    .ok_or_else(|| invalid(key));
+                Some(Clock::resume(control, [remaining("white_time")? as f32, remaining("black_time")? as f32]))
+            }
+        };
+
+        SavedGame::new(start, san, players, engine_depth, clock)
+    }
+
+    fn to_config(&self) -> Config
+    {
+        let mut config = Config::default();
+
+        config.set("save", "format", Value::Number(FORMAT_VERSION));
+        config.set("game", "variant", Value::String(self.start.get_variant().key().to_string()));
+        config.set("game", "start", Value::String(self.start.to_fen()));
+        config.set("game", "moves", Value::String(self.san.join(" ")));
+        config.set("players", "white", Value::String(self.players[0].name().to_string()));
+        config.set("players", "black", Value::String(self.players[1].name().to_string()));
+        config.set("players", "depth", Value::Number(self.engine_depth as f64));
+        if let Some(clock) = &self.clock
+        {
+            config.set("clock", "time_control", Value::String(clock.control().to_string()));
+            config.set("clock", "white_time", Value::Number(clock.remaining()[0] as f64));
+            config.set("clock", "black_time", Value::Number(clock.remaining()[1] as f64));
+        }
+
+        config
+    }
+
+    /// One line about the game for the resume offer, e.g. "Move 12, black to play".
+    fn summary(&self) -> String
+    {
+        let game = self.moves.iter().fold(self.start.clone(), |mut game, parsed|
+        {
+            game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+            game.make_move(&parsed.from, &parsed.to);
+            game
+        });
+
+        format!("Move {}, {} to play", game.get_fullmove_number(), if game.is_white_turn() { "white" } else { "black" })
+    }
+}
+
+/// Checks every move in turn and reads it into the squares the GUI plays it with.
+fn replay(start: &Game, san: &[String]) -> Result<Vec<ParsedMove>, SaveError>
+{
+    let mut game = start.clone();
+    let mut moves = Vec::with_capacity(san.len());
+
+    for (index, text) in san.iter().enumerate()
+    {
+        let parsed = game.parse_move(text).map_err(|error| SaveError::Move(index + 1, text.clone(), error))?;
+        game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+        game.make_move(&parsed.from, &parsed.to);
+        moves.push(parsed);
+    }
+
+    Ok(moves)
+}
+
+/// A button of the offer to resume the last game, shown in the panel on startup.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResumeButton
+{
+    Resume,
+    NewGame,
+}
+
+/// The buttons below the title and the summary line.
+fn buttons(layout: &Layout) -> Vec<(ResumeButton, graphics::Rect)>
+{
+    let panel = layout.panel_rect();
+    let row_height = ROW_HEIGHT * layout.cell;
+    let row = |index: f32| graphics::Rect::new(panel.x, panel.y + index * row_height, panel.w, row_height);
+
+    [ui::button_row(&[ResumeButton::Resume], row(3.0)), ui::button_row(&[ResumeButton::NewGame], row(4.0))].concat()
+}
+
+pub fn button_at(layout: &Layout, x: f32, y: f32) -> Option<ResumeButton>
+{
+    ui::button_at(&buttons(layout), x, y)
+}
+
+/// Draws the offer to resume a saved game in the side panel.
+pub fn draw_offer(ctx: &mut Context, layout: &Layout, theme: &Theme, saved: &SavedGame) -> GameResult
+{
+    let panel = layout.panel_rect();
+    let row_height = ROW_HEIGHT * layout.cell;
+    let centered = |ctx: &mut Context, content: String, rect: graphics::Rect| -> GameResult
+    {
+        let text = graphics::Text::new
+        (
+            graphics::TextFragment::from(content)
+                .font(theme.font)
+                .scale(layout.text_scale(OFFER_SCALE)),
+        );
+        let dimensions = text.dimensions(ctx);
+        graphics::draw
+        (
+            ctx,
+            &text,
+            graphics::DrawParam::default()
+                .color(theme.text)
+                .dest([rect.x + (rect.w - dimensions.w) / 2.0, rect.y + (rect.h - dimensions.h) / 2.0]),
+        )
+    };
+
+    centered(ctx, "Resume last game?".to_string(), graphics::Rect::new(panel.x, panel.y, panel.w, row_height))?;
+    centered(ctx, saved.summary(), graphics::Rect::new(panel.x, panel.y + row_height, panel.w, row_height))?;
+
+    for (button, rect) in buttons(layout)
+    {
+        let background = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, [0.0, 0.0, 0.0, 0.25].into())?;
+        graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+
+        let label = match button
+        {
+            ResumeButton::Resume => "Resume",
+            ResumeButton::NewGame => "New game",
+        };
+        centered(ctx, label.to_string(), rect)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{SaveError, SavedGame};
+    use crate::cli::Player;
+    use crate::clock::{Clock, TimeControl};
+    use chess_gui::{Game, MoveInputError, Variant};
+
+    fn saved_text() -> String
+    {
+        let clock = Clock::resume(TimeControl { initial: 300.0, increment: 3.0 }, [281.5, 290.0]);
+        let san = ["e4", "e5", "Nf3", "Nc6"].iter().map(|san| san.to_string()).collect();
+        let saved = SavedGame::new(Game::new(), san, [Player::Human, Player::Engine], 4, Some(clock)).unwrap();
+        saved.to_config().to_string()
+    }
+
+    #[test]
+    fn reads_what_it_writes()
+    {
+        let saved = SavedGame::parse(&saved_text()).unwrap();
+        assert_eq!(saved.start.to_fen(), Game::new().to_fen());
+        assert_eq!(saved.moves.len(), 4);
+        assert_eq!(saved.moves[2].from, "G1");
+        assert_eq!(saved.players, [Player::Human, Player::Engine]);
+        assert_eq!(saved.engine_depth, 4);
+        assert_eq!(saved.clock.as_ref().map(|clock| clock.remaining()), Some([281.5, 290.0]));
+        assert_eq!(saved.summary(), "Move 3, white to play");
+    }
+
+    #[test]
+    fn rejects_corrupted_files()
+    {
+        let text = saved_text();
+        let corrupt = |from: &str, to: &str| SavedGame::parse(&text.replacen(from, to, 1)).err();
+
+        assert!(matches!(SavedGame::parse("[game\nmoves = \"e4\""), Err(SaveError::Config(_))));
+        assert!(SavedGame::parse(&text[..text.len() / 2]).is_err());
+        assert_eq!(corrupt("format = 1", "format = 2"), Some(SaveError::Version(Some(2.0))));
+        assert_eq!(corrupt("format = 1", ""), Some(SaveError::Version(None)));
+        assert_eq!(corrupt("rnbqkbnr/", "rnbqkbnr/pp/"), Some(SaveError::Invalid("start".to_string())));
+        assert_eq!(corrupt("Nc6", "Nc3"), Some(SaveError::Move(4, "Nc3".to_string(), MoveInputError::Illegal)));
+        assert_eq!(corrupt("engine", "robot"), Some(SaveError::Invalid("black".to_string())));
+        assert_eq!(corrupt("depth = 4", "depth = 40"), Some(SaveError::Invalid("depth".to_string())));
+        assert_eq!(corrupt("black_time = 290", "black_time = -1"), Some(SaveError::Invalid("black_time".to_string())));
+        assert_eq!(corrupt("\"standard\"", "\"bughouse\""), Some(SaveError::Invalid("variant".to_string())));
+    }
+
+    #[test]
+    fn keeps_the_variant()
+    {
+        let san = ["e4", "f6", "Qh5+"].iter().map(|san| san.to_string()).collect();
+        let saved = SavedGame::new(Game::new_variant(Variant::ThreeCheck), san, [Player::Human; 2], 3, None).unwrap();
+        let read = SavedGame::parse(&saved.to_config().to_string()).unwrap();
+        assert_eq!(read.start.get_variant(), Variant::ThreeCheck);
+        assert_eq!(read.moves.len(), 3);
+
+        // saves from before variants have no key for it
+        let old = saved_text().replace("variant = \"standard\"\n", "");
+        assert_eq!(SavedGame::parse(&old).unwrap().start.get_variant(), Variant::Standard);
+    }
+}
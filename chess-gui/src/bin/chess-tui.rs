@@ -0,0 +1,372 @@
+//! Terminal front end for playing without a window, e.g. over SSH.
+//! It plays through the same `Game` calls as the window, only the drawing and input differ.
+
+use chess_gui::{piece_value, Game, GameState};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, queue, terminal};
+use std::io::{self, Write};
+
+/// Square colours, close to the bundled board theme.
+const LIGHT: Color = Color::Rgb { r: 240, g: 217, b: 181 };
+const DARK: Color = Color::Rgb { r: 181, g: 136, b: 99 };
+const LAST_MOVE: Color = Color::Rgb { r: 205, g: 210, b: 106 };
+const SELECTED: Color = Color::Rgb { r: 130, g: 151, b: 105 };
+const POSSIBLE_MOVE: Color = Color::Rgb { r: 100, g: 160, b: 200 };
+const CHECK: Color = Color::Rgb { r: 220, g: 80, b: 70 };
+const CURSOR: Color = Color::Rgb { r: 90, g: 120, b: 220 };
+const WHITE_PIECE: Color = Color::Rgb { r: 255, g: 255, b: 255 };
+const BLACK_PIECE: Color = Color::Rgb { r: 0, g: 0, b: 0 };
+
+/// Terminal columns taken by one square.
+const SQUARE_WIDTH: u16 = 3;
+/// Column the move list starts in, right of the board and its labels.
+const PANEL_X: u16 = 2 + 8 * SQUARE_WIDTH + 3;
+/// Rows of the move list, as many as the board is high.
+const MOVE_ROWS: usize = 8;
+
+/// Pieces drawn with the filled symbols for both colours, told apart by their colour,
+/// because the outlined white symbols are hard to read on coloured squares.
+fn piece_symbol(piece: char) -> char
+{
+    match piece.to_ascii_lowercase()
+    {
+        'k' => '♚',
+        'q' => '♛',
+        'r' => '♜',
+        'b' => '♝',
+        'n' => '♞',
+        'p' => '♟',
+        _ => ' ',
+    }
+}
+
+/// Converts a square name like "E2" into (file, rank), both counted from 0.
+fn transform_input(input_pos: &str) -> (u32, u32)
+{
+    let mut chars_iter = input_pos.chars();
+    (
+        chars_iter.next().unwrap().to_digit(18).unwrap() - 10,
+        chars_iter.next().unwrap().to_digit(10).unwrap() - 1,
+    )
+}
+
+/// Converts (file, rank) into a square name like "E2", the way `Game` expects squares.
+fn transform_back(file_input: u32, rank_input: u32) -> String
+{
+    ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H'][file_input as usize].to_string() + &(rank_input + 1).to_string()
+}
+
+/// Puts the terminal back the way it was found, also when the program panics.
+struct RawTerminal;
+
+impl RawTerminal
+{
+    fn enter() -> io::Result<RawTerminal>
+    {
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal
+{
+    fn drop(&mut self)
+    {
+        // nothing sensible is left to do if the terminal can not be restored
+        let _ = crossterm::execute!(io::stdout(), ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Terminal counterpart of the window's AppState.
+struct TuiState
+{
+    game: Game,
+    // Moves in SAN, ply n of the game is `moves[n - 1]`.
+    moves: Vec<String>,
+    last_move: Option<((u32, u32), (u32, u32))>,
+
+    // Squares are stored as (file, rank), both counted from 0.
+    cursor: (u32, u32),
+    selected_piece: Option<(u32, u32)>,
+    possible_moves: Vec<(u32, u32)>,
+    flipped: bool,
+
+    // Move being typed, and why the last one was not played.
+    text: String,
+    message: Option<String>,
+}
+
+impl TuiState
+{
+    fn new() -> TuiState
+    {
+        TuiState
+        {
+            game: Game::new(),
+            moves: vec![],
+            last_move: None,
+            cursor: (4, 1),
+            selected_piece: None,
+            possible_moves: vec![],
+            flipped: false,
+            text: String::new(),
+            message: None,
+        }
+    }
+
+    /// The piece character on a square, '*' if it is empty.
+    fn piece_at(&self, square: (u32, u32)) -> char
+    {
+        self.game.get_board().as_bytes()[(square.0 + (7 - square.1) * 9) as usize] as char
+    }
+
+    /// Selects the piece on a square and looks up where it can go.
+    fn select(&mut self, square: (u32, u32))
+    {
+        self.selected_piece = Some(square);
+        self.possible_moves = self.game.get_possible_moves(&transform_back(square.0, square.1))
+            .unwrap_or_default()
+            .iter()
+            .map(|name| transform_input(name))
+            .collect();
+    }
+
+    fn clear_selection(&mut self)
+    {
+        self.selected_piece = None;
+        self.possible_moves.clear();
+    }
+
+    /// Plays a move, every move made in the terminal goes through here. Returns false if the game rejected it.
+    fn apply_move(&mut self, from: (u32, u32), to: (u32, u32)) -> bool
+    {
+        let moved = match self.game.make_move_with_san(&transform_back(from.0, from.1), &transform_back(to.0, to.1))
+        {
+            Some(san) =>
+            {
+                self.moves.push(san);
+                self.last_move = Some((from, to));
+                true
+            }
+            None => false,
+        };
+
+        self.clear_selection();
+        moved
+    }
+
+    /// Picks up the piece under the cursor, or moves the picked up piece to the cursor.
+    fn press_square(&mut self)
+    {
+        let square = self.cursor;
+        if let Some(from) = self.selected_piece
+        {
+            if self.possible_moves.contains(&square)
+            {
+                self.apply_move(from, square);
+                return;
+            }
+        }
+
+        let piece = self.piece_at(square);
+        if piece == '*' || piece.is_uppercase() != self.game.is_white_turn() || self.selected_piece == Some(square)
+        {
+            self.clear_selection();
+            return;
+        }
+        self.select(square);
+    }
+
+    /// Plays the typed move, or says why it can not be played.
+    fn submit_typed_move(&mut self)
+    {
+        match self.game.parse_move(&self.text)
+        {
+            Ok(parsed) =>
+            {
+                if let Some(piece) = parsed.promotion { self.game.set_promotion(&piece.to_string()); }
+                self.apply_move(transform_input(&parsed.from), transform_input(&parsed.to));
+                // pieces moved with the cursor always promote to queens
+                self.game.set_promotion("Q");
+
+                self.text.clear();
+                self.message = None;
+            }
+            Err(error) => self.message = Some(error.to_string()),
+        }
+    }
+
+    /// Moves the cursor by a step as seen on the screen, so the arrows follow a flipped board.
+    fn move_cursor(&mut self, right: i32, up: i32)
+    {
+        let (right, up) = if self.flipped { (-right, -up) } else { (right, up) };
+        self.cursor.0 = (self.cursor.0 as i32 + right).clamp(0, 7) as u32;
+        self.cursor.1 = (self.cursor.1 as i32 + up).clamp(0, 7) as u32;
+    }
+
+    /// Handles a key, returns false once the player quits.
+    fn key(&mut self, key: KeyEvent) -> bool
+    {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') { return false; }
+
+        match key.code
+        {
+            KeyCode::Left => self.move_cursor(-1, 0),
+            KeyCode::Right => self.move_cursor(1, 0),
+            KeyCode::Up => self.move_cursor(0, 1),
+            KeyCode::Down => self.move_cursor(0, -1),
+            KeyCode::Tab => self.flipped = !self.flipped,
+            // with nothing typed, enter and space play with the cursor
+            KeyCode::Enter if !self.text.trim().is_empty() => self.submit_typed_move(),
+            KeyCode::Enter =>
+            {
+                self.text.clear();
+                self.press_square();
+            }
+            KeyCode::Char(' ') if self.text.is_empty() => self.press_square(),
+            KeyCode::Char(character) =>
+            {
+                self.text.push(character);
+                self.message = None;
+            }
+            KeyCode::Backspace =>
+            {
+                self.text.pop();
+                self.message = None;
+            }
+            KeyCode::Esc if self.text.is_empty() && self.selected_piece.is_none() => return false,
+            KeyCode::Esc =>
+            {
+                self.text.clear();
+                self.message = None;
+                self.clear_selection();
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// Status line text, the same states the window shows.
+    fn status(&self) -> String
+    {
+        let side = if self.game.is_white_turn() { "White" } else { "Black" };
+        match self.game.get_game_state()
+        {
+            GameState::InProgress => format!("{} to move.", side),
+            GameState::Check => format!("{} is in check.", side),
+            GameState::GameOver => format!("Game is over, {} is checkmated.", side.to_lowercase()),
+        }
+    }
+
+    /// Background of a square, the strongest highlight wins.
+    fn square_color(&self, square: (u32, u32)) -> Color
+    {
+        if square == self.cursor { CURSOR }
+        else if Some(square) == self.selected_piece { SELECTED }
+        else if self.possible_moves.contains(&square) { POSSIBLE_MOVE }
+        else if self.game.checked_king().is_some_and(|king| transform_input(&king) == square) { CHECK }
+        else if self.last_move.is_some_and(|(from, to)| square == from || square == to) { LAST_MOVE }
+        else if (square.0 + square.1).is_multiple_of(2) { DARK }
+        else { LIGHT }
+    }
+
+    /// Pieces taken by one side, cheapest first, followed by "+N" if that side is ahead.
+    fn captured_line(&self, by_white: bool) -> String
+    {
+        // black pieces are the ones white took
+        let mut taken: Vec<char> = self.game.get_captured().iter().copied().filter(|piece| piece.is_lowercase() == by_white).collect();
+        taken.sort_by_key(|piece| piece_value(*piece));
+
+        let mut line: String = taken.iter().map(|piece| piece_symbol(*piece)).collect();
+        let advantage = if by_white { self.game.material_difference() } else { -self.game.material_difference() };
+        if advantage > 0 { line += &format!(" +{}", advantage); }
+        line
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()>
+    {
+        queue!(out, ResetColor, terminal::Clear(terminal::ClearType::All))?;
+
+        let files = if self.flipped { "hgfedcba" } else { "abcdefgh" };
+        let labels: String = files.chars().map(|file| format!(" {} ", file)).collect();
+        queue!(out, cursor::MoveTo(2, 0), Print(&labels), cursor::MoveTo(2, 9), Print(&labels))?;
+
+        for row in 0..8
+        {
+            let rank = if self.flipped { row } else { 7 - row };
+            queue!(out, ResetColor, cursor::MoveTo(0, row as u16 + 1), Print(rank + 1), Print(' '))?;
+
+            for column in 0..8
+            {
+                let square = (if self.flipped { 7 - column } else { column }, rank);
+                let piece = self.piece_at(square);
+                queue!
+                (
+                    out,
+                    SetBackgroundColor(self.square_color(square)),
+                    SetForegroundColor(if piece.is_uppercase() { WHITE_PIECE } else { BLACK_PIECE }),
+                    Print(format!(" {} ", piece_symbol(piece))),
+                )?;
+            }
+            queue!(out, ResetColor, Print(' '), Print(rank + 1))?;
+        }
+
+        // captures are shown next to the side that made them, like the trays in the window
+        let (top_is_white, bottom_is_white) = (self.flipped, !self.flipped);
+        queue!
+        (
+            out,
+            cursor::MoveTo(PANEL_X, 0), Print(self.captured_line(top_is_white)),
+            cursor::MoveTo(PANEL_X, 9), Print(self.captured_line(bottom_is_white)),
+        )?;
+
+        // the list keeps the latest moves in view
+        let rows: Vec<String> = self.moves.chunks(2).enumerate()
+            .map(|(index, pair)| format!("{:>3}. {:<8}{}", index + 1, pair[0], pair.get(1).map_or("", |san| san.as_str())))
+            .collect();
+        for (line, row) in rows.iter().skip(rows.len().saturating_sub(MOVE_ROWS)).enumerate()
+        {
+            queue!(out, cursor::MoveTo(PANEL_X, line as u16 + 1), Print(row))?;
+        }
+
+        queue!(out, cursor::MoveTo(0, 11), Print(self.status()))?;
+        queue!(out, cursor::MoveTo(0, 12), Print("Move: "), Print(&self.text), Print('|'))?;
+        if let Some(message) = &self.message
+        {
+            queue!(out, Print("  "), SetForegroundColor(CHECK), Print(message), ResetColor)?;
+        }
+        queue!
+        (
+            out,
+            cursor::MoveTo(0, 14),
+            Print("Arrows: cursor  Space: pick up/drop  Enter: play typed move  Tab: flip  Esc: quit"),
+        )?;
+
+        out.flush()
+    }
+}
+
+fn main() -> io::Result<()>
+{
+    let _terminal = RawTerminal::enter()?;
+    let mut stdout = io::stdout();
+    let mut state = TuiState::new();
+
+    loop
+    {
+        state.draw(&mut stdout)?;
+
+        match event::read()?
+        {
+            Event::Key(key) => if !state.key(key) { break; },
+            // everything is drawn again on the next pass anyway
+            Event::Resize(..) | Event::Mouse(..) => (),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use super::Game;
+
+/// How many starting positions Chess960 has, numbered from 0 to 959.
+pub const CHESS960_POSITIONS: u32 = 960;
+
+/// The number of the standard starting position among the Chess960 ones.
+pub const CHESS960_STANDARD: u32 = 518;
+
+/// Where the two knights go among the five squares left after the bishops and queen, by the standard numbering.
+const KNIGHT_PLACES: [(usize, usize); 10] =
+    [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+impl Game {
+    /// Sets up Chess960 starting position number `_index`, counted like Scharnagl's numbering
+    /// where 518 is the standard position. Returns None for numbers of 960 and above.
+    /// Castling follows the Chess960 rules, see `is_chess960`.
+    pub fn new_chess960(_index: u32) -> Option<Game> {
+        if _index >= CHESS960_POSITIONS {
+            return None;
+        }
+        let back_rank = chess960_back_rank(_index);
+
+        // the rights are written like Shredder-FEN, so they belong to the rooks of this position
+        let rooks: Vec<usize> = (0..8).filter(|file| back_rank[*file] == 'r').collect();
+        let mut castling = String::new();
+        for colour_letter in [b'A', b'a'] {
+            castling.push((colour_letter + rooks[1] as u8) as char);
+            castling.push((colour_letter + rooks[0] as u8) as char);
+        }
+        let black: String = back_rank.iter().collect();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {} - 0 1",
+            black,
+            black.to_uppercase(),
+            castling
+        );
+
+        let mut game = Game::from_fen(&fen).ok()?;
+        game.chess960 = true;
+        Some(game)
+    }
+}
+
+/// The pieces of the back rank from the a to the h file for Chess960 position `index`, written like in `get_board`.
+fn chess960_back_rank(index: u32) -> [char; 8] {
+    let mut rank = ['*'; 8];
+    let mut index = index as usize;
+
+    // light squared bishop on b, d, f or h, then dark squared bishop on a, c, e or g
+    rank[index % 4 * 2 + 1] = 'b';
+    index /= 4;
+    rank[index % 4 * 2] = 'b';
+    index /= 4;
+
+    let empty = |rank: &[char; 8]| -> Vec<usize> { (0..8).filter(|file| rank[*file] == '*').collect() };
+    rank[empty(&rank)[index % 6]] = 'q';
+    index /= 6;
+
+    let (first, second) = KNIGHT_PLACES[index];
+    let left = empty(&rank);
+    rank[left[first]] = 'n';
+    rank[left[second]] = 'n';
+
+    // the king always stands between the rooks
+    for (file, piece) in empty(&rank).into_iter().zip(['r', 'k', 'r']) {
+        rank[file] = piece;
+    }
+    rank
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chess960_back_rank, CHESS960_POSITIONS, CHESS960_STANDARD};
+    use crate::{assert_perft, Game, Variant};
+    use std::collections::HashSet;
+
+    #[test]
+    fn numbers_every_position_once() {
+        assert_eq!(Game::new_chess960(CHESS960_STANDARD).unwrap().get_board(), Game::new().get_board());
+        assert_eq!(chess960_back_rank(0).iter().collect::<String>(), "bbqnnrkr");
+        assert_eq!(chess960_back_rank(959).iter().collect::<String>(), "rkrnnqbb");
+        assert!(Game::new_chess960(CHESS960_POSITIONS).is_none());
+
+        let ranks: HashSet<[char; 8]> = (0..CHESS960_POSITIONS).map(chess960_back_rank).collect();
+        assert_eq!(ranks.len(), 960);
+        for rank in ranks {
+            let files = |piece: char| -> Vec<usize> { (0..8).filter(|file| rank[*file] == piece).collect() };
+            let (bishops, rooks, king) = (files('b'), files('r'), files('k')[0]);
+            assert_eq!(bishops[0] % 2 + bishops[1] % 2, 1);
+            assert!((rooks[0] < king) & (king < rooks[1]));
+        }
+    }
+
+    #[test]
+    fn castles_by_moving_the_king_onto_its_rook() {
+        // king on b1 with the rooks on a1 and h1, after the knight and bishops left
+        let mut game = Game::from_fen("rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w HAha - 0 1").unwrap();
+        assert!(game.is_chess960());
+        let mut king_moves = game.get_possible_moves("B1").unwrap();
+        king_moves.sort();
+        assert_eq!(king_moves, vec!["A1", "C1", "H1"]);
+
+        // the king lands on g1 and the rook on f1
+        assert!(game.make_move("B1", "H1"));
+        assert_eq!(game.to_fen(), "rk5r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 b ha - 1 1");
+        assert_eq!(game.get_captured(), &[] as &[char]);
+        // queen side the king stays next to where it stood, the rook jumps over it
+        assert!(game.make_move("B8", "A8"));
+        assert_eq!(game.to_fen(), "2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 2");
+    }
+
+    #[test]
+    fn king_and_rook_may_swap_squares() {
+        // king on g1 and rook on f1 castle queen side too, the king on c1 only moves the rook
+        let mut game = Game::from_fen("1k6/8/8/8/8/8/8/5RK1 w F - 0 1").unwrap();
+        assert!(game.make_move("G1", "F1"));
+        assert_eq!(game.to_fen(), "1k6/8/8/8/8/8/8/2KR4 b - - 1 1");
+
+        let mut game = Game::from_fen("7k/8/8/8/8/8/8/1RK5 w B - 0 1").unwrap();
+        assert!(game.make_move("C1", "B1"));
+        assert_eq!(game.to_fen(), "7k/8/8/8/8/8/8/2KR4 b - - 1 1");
+
+        // a rook beyond the castling rook attacks the king's square once the castling rook leaves
+        let mut game = Game::from_fen("1k6/8/8/8/8/8/8/r1RK4 w C - 0 1").unwrap();
+        assert!(!game.get_possible_moves("D1").unwrap().contains(&"C1".to_string()));
+    }
+
+    #[test]
+    fn perft_matches_known_numbers() {
+        // positions from the usual Chess960 perft suite
+        assert_perft(Variant::Standard, "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[21, 528, 12189]);
+        assert_perft(Variant::Standard, "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[21, 807, 18002]);
+        assert_perft(Variant::Standard, "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", &[22, 593, 13440]);
+    }
+
+    #[test]
+    fn reads_x_fen() {
+        // K stands for the outermost rook, a file letter for any other
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/1R2K1RR w KB - 0 1").unwrap();
+        assert!(game.is_chess960());
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/1R2K1RR w HB - 0 1");
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/1R2K1RR w GB - 0 1").unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/1R2K1RR w GB - 0 1");
+
+        // the standard squares keep the standard rules
+        assert!(!Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap().is_chess960());
+        assert!(Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap().is_chess960());
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w E -").is_err());
+    }
+}
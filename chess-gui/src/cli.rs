@@ -0,0 +1,439 @@
+use crate::clock::TimeControl;
+use crate::net::{Role, DEFAULT_PORT};
+use crate::settings::{self, Settings, MAX_DEPTH};
+use crate::Orientation;
+
+use chess_gui::{Game, ParsedMove, Pgn, Variant, CHESS960_POSITIONS};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+
+/// Shown for --help and after a mistake on the command line.
+pub const USAGE: &str = "\
+usage: chess-gui [options]
+
+Options left out keep the values from the settings file.
+The game left unfinished last time is offered again unless --fen, --pgn, --chess960 or --variant is given.
+
+  --fen <FEN>                 start from this position
+  --pgn <FILE>                start after the moves of the first game in a PGN file
+  --chess960 <NUMBER>         start from Chess960 position 0 to 959, or random
+  --variant <NAME>            play standard, kingofthehill, threecheck, antichess,
+                              crazyhouse, atomic or horde
+  --orientation <SIDE>        white, black or auto (the side to move at the bottom)
+  --time <MINUTES[+SECONDS]>  play with a clock, e.g. 5+3 for 5 minutes and 3 seconds a move
+  --white <PLAYER>            human or engine, human if left out
+  --black <PLAYER>            human or engine, human if left out
+  --depth <PLIES>             how far the engine looks ahead
+  --theme <NAME>              board theme from themes.toml
+  --size <WIDTHxHEIGHT>       window size in pixels
+  --host <PORT>               wait for an opponent on the network, 7878 is the usual port
+  --join <HOST[:PORT]>        play against the one hosting on HOST
+  --watch <HOST[:PORT]>       follow the game hosted on HOST without playing
+  --colour <SIDE>             with --host, white or black for the side the host plays
+  --headless                  read moves from stdin instead of opening a window,
+                              the run ends at mate, stalemate or any draw that can be claimed
+  --print-fen                 with --headless, print FEN instead of the board,
+                              --fen without a position does the same
+  --help                      show this text";
+
+/// Who makes the moves for a side.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Player
+{
+    Human,
+    Engine,
+    /// The opponent in a network game, moves arrive over the connection.
+    Remote,
+}
+
+impl Player
+{
+    /// Reads a player as written on the command line and in the saved game.
+    pub fn parse(text: &str) -> Option<Player>
+    {
+        match text
+        {
+            "human" => Some(Player::Human),
+            "engine" => Some(Player::Engine),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Player::Human => "human",
+            Player::Engine => "engine",
+            Player::Remote => "remote",
+        }
+    }
+}
+
+/// A mistake on the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliError(pub String);
+
+impl fmt::Display for CliError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Everything set on the command line, read before the window is opened.
+pub struct Options
+{
+    /// Position the game starts from and the moves already played from it.
+    pub start: Game,
+    pub moves: Vec<ParsedMove>,
+    /// True if the start came from --fen, --pgn, --chess960 or --variant, a saved game is not offered then.
+    pub start_given: bool,
+    pub orientation: Orientation,
+    pub time_control: Option<TimeControl>,
+    /// Players of white and black.
+    pub players: [Player; 2],
+    pub depth: u32,
+    pub theme: String,
+    pub window_size: (f32, f32),
+    pub network: Option<Role>,
+    /// Side the host of a network game plays.
+    pub host_white: bool,
+    pub headless: bool,
+    pub print_fen: bool,
+    pub help: bool,
+}
+
+impl Options
+{
+    /// Reads the arguments, not counting the program name, on top of the saved settings.
+    /// Options take their value as the next argument or after '=', as in `--time=5+3`.
+    /// --variant is read first wherever it stands, since the rules decide which positions can be played.
+    pub fn parse(args: impl IntoIterator<Item = String>, defaults: &Settings) -> Result<Options, CliError>
+    {
+        let mut options = Options
+        {
+            start: Game::new(),
+            moves: vec![],
+            start_given: false,
+            orientation: defaults.orientation,
+            time_control: defaults.time_control,
+            players: [Player::Human; 2],
+            depth: defaults.engine_depth,
+            theme: defaults.theme.clone(),
+            window_size: defaults.window_size,
+            network: None,
+            host_white: true,
+            headless: false,
+            print_fen: false,
+            help: false,
+        };
+        let (mut network_given, mut colour_given) = (false, false);
+        let mut valued = vec![];
+        let mut start_option_given = false;
+
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next()
+        {
+            let (name, attached) = match arg.split_once('=')
+            {
+                Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+
+            match name.as_str()
+            {
+                "--headless" => options.headless = true,
+                "--print-fen" => options.print_fen = true,
+                // the older way of asking for FEN output, from before --fen took a position
+                "--fen" if attached.is_none() && args.peek().is_none_or(|next| next.starts_with("--")) => options.print_fen = true,
+                "--help" | "-h" => options.help = true,
+                _ => match attached.or_else(|| args.next())
+                {
+                    Some(value) => valued.push((name, value)),
+                    None => return Err(error(&name, "needs a value")),
+                },
+            }
+        }
+
+        valued.sort_by_key(|(name, _)| name != "--variant");
+        for (name, value) in valued
+        {
+            if name == "--fen" || name == "--pgn" || name == "--chess960"
+            {
+                if start_option_given { return Err(CliError("give one of --fen, --pgn or --chess960, once".to_string())); }
+                start_option_given = true;
+                // a PGN file names its variant itself, and Chess960 is played by the standard rules
+                if name != "--fen" && options.start.get_variant() != Variant::Standard
+                {
+                    return Err(CliError(format!("{} goes without --variant", name)));
+                }
+            }
+            options.set(&name, &value)?;
+
+            let network_option = name == "--host" || name == "--join" || name == "--watch";
+            if network_option && network_given
+            {
+                return Err(CliError("give one of --host, --join or --watch, once".to_string()));
+            }
+            network_given |= network_option;
+            colour_given |= name == "--colour" || name == "--color";
+
+            options.start_given |= start_option_given || name == "--variant";
+        }
+
+        match &options.network
+        {
+            Some(_) if options.headless => return Err(CliError("network games need the window, not --headless".to_string())),
+            Some(Role::Join(_)) | Some(Role::Watch(_)) if options.start_given => return Err(CliError("the host picks the position, --fen, --pgn, --chess960 and --variant go with --host".to_string())),
+            Some(Role::Host(_)) => (),
+            _ if colour_given => return Err(CliError("--colour goes with --host".to_string())),
+            _ => (),
+        }
+
+        Ok(options)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), CliError>
+    {
+        match name
+        {
+            // --variant comes first, so the start holds the rules
+            "--fen" => self.start = Game::from_variant_fen(self.start.get_variant(), value).map_err(|problem| error(name, &problem.to_string()))?,
+            "--variant" =>
+            {
+                let variant = Variant::parse(value).ok_or_else(|| error(name, "must be standard, kingofthehill, threecheck, antichess, crazyhouse, atomic or horde"))?;
+                self.start = Game::new_variant(variant);
+            }
+            "--pgn" =>
+            {
+                let text = std::fs::read_to_string(value).map_err(|problem| error(name, &format!("{}: {}", value, problem)))?;
+                let pgn = Pgn::parse(&text).map_err(|problem| error(name, &problem.to_string()))?;
+                let (start, moves) = pgn.replay().map_err(|problem| error(name, &problem.to_string()))?;
+                self.start = start;
+                self.moves = moves;
+            }
+            "--chess960" =>
+            {
+                let index = if value == "random"
+                {
+                    random_chess960()
+                }
+                else
+                {
+                    value.parse().ok().filter(|index| *index < CHESS960_POSITIONS)
+                        .ok_or_else(|| error(name, &format!("must be a number from 0 to {} or random", CHESS960_POSITIONS - 1)))?
+                };
+                self.start = Game::new_chess960(index).expect("every number below CHESS960_POSITIONS is a position");
+            }
+            "--orientation" => self.orientation = settings::parse_orientation(value).ok_or_else(|| error(name, "must be white, black or auto"))?,
+            "--time" => self.time_control = Some(TimeControl::parse(value).ok_or_else(|| error(name, "must look like 5 or 5+3"))?),
+            "--white" | "--black" =>
+            {
+                let player = Player::parse(value).ok_or_else(|| error(name, "must be human or engine"))?;
+                self.players[if name == "--white" { 0 } else { 1 }] = player;
+            }
+            "--depth" =>
+            {
+                self.depth = value.parse().ok().filter(|depth| (1..=MAX_DEPTH).contains(depth))
+                    .ok_or_else(|| error(name, &format!("must be a number from 1 to {}", MAX_DEPTH)))?;
+            }
+            "--theme" => self.theme = value.to_string(),
+            "--size" =>
+            {
+                let size = value.split_once('x')
+                    .and_then(|(width, height)| Some((width.parse::<f32>().ok()?, height.parse::<f32>().ok()?)))
+                    .filter(|(width, height)| *width > 0.0 && *height > 0.0)
+                    .ok_or_else(|| error(name, "must look like 1040x640"))?;
+                self.window_size = size;
+            }
+            "--host" => self.network = Some(Role::Host(value.parse().map_err(|_| error(name, "must be a port number"))?)),
+            "--join" | "--watch" =>
+            {
+                if value.is_empty() { return Err(error(name, "needs the host's address")); }
+                // the port may be left out, but not from an IPv6 address in brackets
+                let address = if value.rsplit_once(':').is_none_or(|(_, port)| port.parse::<u16>().is_err()) || value.ends_with(']')
+                {
+                    format!("{}:{}", value, DEFAULT_PORT)
+                }
+                else
+                {
+                    value.to_string()
+                };
+                self.network = Some(if name == "--join" { Role::Join(address) } else { Role::Watch(address) });
+            }
+            "--colour" | "--color" =>
+            {
+                self.host_white = match value
+                {
+                    "white" => true,
+                    "black" => false,
+                    _ => return Err(error(name, "must be white or black")),
+                };
+            }
+            _ => return Err(CliError(format!("unknown option {}", name))),
+        }
+        Ok(())
+    }
+
+    /// The position after the moves read from the PGN file.
+    pub fn current_game(&self) -> Game
+    {
+        let mut game = self.start.clone();
+        for parsed in &self.moves
+        {
+            game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+            game.make_move(&parsed.from, &parsed.to);
+        }
+        game.set_promotion("Q");
+        game
+    }
+}
+
+/// The number of a Chess960 starting position picked at random.
+pub fn random_chess960() -> u32
+{
+    // the standard library seeds every hasher state randomly
+    (RandomState::new().build_hasher().finish() % CHESS960_POSITIONS as u64) as u32
+}
+
+fn error(name: &str, message: &str) -> CliError
+{
+    CliError(format!("{} {}", name, message))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{CliError, Options, Player};
+    use chess_gui::Variant;
+    use crate::net::Role;
+    use crate::settings::Settings;
+    use crate::Orientation;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError>
+    {
+        Options::parse(args.iter().map(|arg| arg.to_string()), &Settings::default())
+    }
+
+    #[test]
+    fn reads_every_option()
+    {
+        let options = parse(&
+        [
+            "--fen", "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1",
+            "--orientation=auto",
+            "--time", "5+3",
+            "--black", "engine",
+            "--depth", "2",
+            "--theme", "ocean",
+            "--size", "1200x700",
+            "--headless", "--print-fen",
+        ]).unwrap();
+
+        assert_eq!(options.start.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1");
+        assert_eq!(options.orientation, Orientation::AutoFlip);
+        assert_eq!(options.time_control.map(|control| control.initial), Some(300.0));
+        assert_eq!(options.players, [Player::Human, Player::Engine]);
+        assert_eq!(options.depth, 2);
+        assert_eq!(options.theme, "ocean");
+        assert_eq!(options.window_size, (1200.0, 700.0));
+        assert!(options.headless && options.print_fen && !options.help);
+    }
+
+    #[test]
+    fn fen_without_a_position_asks_for_fen_output()
+    {
+        for args in [&["--headless", "--fen"][..], &["--fen", "--headless"]]
+        {
+            let options = parse(args).unwrap();
+            assert!(options.headless && options.print_fen && !options.start_given);
+        }
+
+        let options = parse(&["--fen", "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", "--headless"]).unwrap();
+        assert!(!options.print_fen && options.start_given);
+        assert!(parse(&["--fen="]).is_err());
+    }
+
+    #[test]
+    fn defaults_to_a_new_game_between_humans()
+    {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.start.to_fen(), chess_gui::Game::new().to_fen());
+        assert_eq!(options.players, [Player::Human; 2]);
+        assert!(options.time_control.is_none() && !options.headless);
+    }
+
+    #[test]
+    fn starts_from_the_settings()
+    {
+        let settings = Settings { orientation: Orientation::BlackAtBottom, engine_depth: 5, ..Settings::default() };
+        let options = Options::parse(vec!["--depth".to_string(), "2".to_string()], &settings).unwrap();
+        assert_eq!(options.orientation, Orientation::BlackAtBottom);
+        assert_eq!(options.depth, 2);
+    }
+
+    #[test]
+    fn reads_network_games()
+    {
+        let options = parse(&["--host", "9000", "--colour", "black"]).unwrap();
+        assert_eq!(options.network, Some(Role::Host(9000)));
+        assert!(!options.host_white);
+
+        assert_eq!(parse(&["--join", "10.0.0.2"]).unwrap().network, Some(Role::Join("10.0.0.2:7878".to_string())));
+        assert_eq!(parse(&["--join=box.lan:9000"]).unwrap().network, Some(Role::Join("box.lan:9000".to_string())));
+
+        assert_eq!(parse(&["--watch", "10.0.0.2:9000"]).unwrap().network, Some(Role::Watch("10.0.0.2:9000".to_string())));
+
+        assert!(parse(&["--host", "9000", "--join", "10.0.0.2"]).is_err());
+        assert!(parse(&["--join", "10.0.0.2", "--watch", "10.0.0.2"]).is_err());
+        assert!(parse(&["--watch", "10.0.0.2", "--color", "white"]).is_err());
+        assert!(parse(&["--join", "10.0.0.2", "--fen", "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]).is_err());
+        assert!(parse(&["--colour", "black"]).is_err());
+        assert!(parse(&["--join", "10.0.0.2", "--chess960", "random"]).is_err());
+        assert!(parse(&["--host", "http"]).is_err());
+    }
+
+    #[test]
+    fn reads_chess960_starts()
+    {
+        let options = parse(&["--chess960", "0"]).unwrap();
+        assert_eq!(options.start.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+        assert!(options.start.is_chess960() && options.start_given);
+        assert!(parse(&["--chess960", "random"]).unwrap().start.is_chess960());
+
+        assert!(parse(&["--chess960", "960"]).is_err());
+        assert!(parse(&["--chess960", "0", "--fen", "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]).is_err());
+    }
+
+    #[test]
+    fn reads_variants()
+    {
+        let options = parse(&["--variant", "antichess"]).unwrap();
+        assert_eq!(options.start.get_variant(), Variant::Antichess);
+        assert_eq!(options.start.get_castling_rights(), [false; 4]);
+        assert!(options.start_given);
+
+        // the position is read by the rules of the variant, wherever --variant stands
+        let options = parse(&["--fen", "8/8/8/8/8/8/3q4/8 w - - 0 1", "--variant=antichess"]).unwrap();
+        assert_eq!(options.start.get_variant(), Variant::Antichess);
+        assert!(parse(&["--fen", "8/8/8/8/8/8/3q4/8 w - - 0 1"]).is_err());
+
+        assert!(parse(&["--variant", "bughouse"]).is_err());
+        assert!(parse(&["--variant", "threecheck", "--chess960", "0"]).is_err());
+        assert!(parse(&["--join", "10.0.0.2", "--variant", "threecheck"]).is_err());
+    }
+
+    #[test]
+    fn reports_mistakes()
+    {
+        assert_eq!(parse(&["--depth"]).err(), Some(CliError("--depth needs a value".to_string())));
+        assert_eq!(parse(&["--white", "robot"]).err(), Some(CliError("--white must be human or engine".to_string())));
+        assert_eq!(parse(&["--side", "white"]).err(), Some(CliError("unknown option --side".to_string())));
+        assert!(parse(&["--size", "big"]).is_err());
+        assert!(parse(&["--fen", "8/8/8/8/8/8/8/8 w - -"]).is_err());
+        assert!(parse(&["--pgn", "/no/such/file.pgn"]).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::layout::Layout;
+use crate::theme::Theme;
+use crate::ui::CLOCK_SCALE;
+
+use ggez::{graphics, Context, GameResult};
+use std::fmt;
+
+/// Width of the clock at the end of a tray, in tiles.
+pub const CLOCK_WIDTH: f32 = 1.2;
+
+/// Time each side starts with and gets back after every move, in seconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeControl
+{
+    pub initial: f32,
+    pub increment: f32,
+}
+
+impl TimeControl
+{
+    /// Reads minutes with an optional increment in seconds, e.g. "5+3", "10" or "0.5+1".
+    pub fn parse(text: &str) -> Option<TimeControl>
+    {
+        let (minutes, increment) = match text.split_once('+')
+        {
+            Some((minutes, increment)) => (minutes, increment.parse::<f32>().ok()?),
+            None => (text, 0.0),
+        };
+        let minutes = minutes.parse::<f32>().ok()?;
+        if !(minutes > 0.0 && increment >= 0.0 && minutes.is_finite() && increment.is_finite()) { return None; }
+
+        Some(TimeControl { initial: minutes * 60.0, increment })
+    }
+}
+
+/// Written the way `parse` reads it, e.g. "5+3".
+impl fmt::Display for TimeControl
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}+{}", self.initial / 60.0, self.increment)
+    }
+}
+
+/// Counts down the time of the side to move, a side whose time runs out loses.
+#[derive(Clone)]
+pub struct Clock
+{
+    control: TimeControl,
+    // Seconds left for white and black.
+    remaining: [f32; 2],
+}
+
+impl Clock
+{
+    pub fn new(control: TimeControl) -> Clock
+    {
+        Clock { control, remaining: [control.initial; 2] }
+    }
+
+    /// Continues a clock with the time each side had left, as read from a saved game.
+    pub fn resume(control: TimeControl, remaining: [f32; 2]) -> Clock
+    {
+        Clock { control, remaining }
+    }
+
+    pub fn control(&self) -> TimeControl
+    {
+        self.control
+    }
+
+    /// Seconds left for white and black.
+    pub fn remaining(&self) -> [f32; 2]
+    {
+        self.remaining
+    }
+
+    /// Takes time off the side to move, nothing is left to take once a side has run out.
+    pub fn tick(&mut self, white_to_move: bool, seconds: f32)
+    {
+        if self.flagged().is_some() { return; }
+
+        let side = if white_to_move { 0 } else { 1 };
+        self.remaining[side] = (self.remaining[side] - seconds).max(0.0);
+    }
+
+    /// Adds the increment for a side that just moved.
+    pub fn moved(&mut self, white: bool)
+    {
+        if self.flagged().is_some() { return; }
+
+        self.remaining[if white { 0 } else { 1 }] += self.control.increment;
+    }
+
+    /// Some(true) if white ran out of time, Some(false) if black did.
+    pub fn flagged(&self) -> Option<bool>
+    {
+        if self.remaining[0] <= 0.0 { Some(true) }
+        else if self.remaining[1] <= 0.0 { Some(false) }
+        else { None }
+    }
+
+    /// Time left as minutes and seconds, with tenths in the last ten seconds.
+    fn text(seconds: f32) -> String
+    {
+        if seconds < 10.0
+        {
+            format!("0:{:04.1}", (seconds * 10.0).floor() / 10.0)
+        }
+        else
+        {
+            let whole = seconds.ceil() as u32;
+            format!("{}:{:02}", whole / 60, whole % 60)
+        }
+    }
+
+    /// Draws one side's time at the right end of a tray, on a lighter background while it is running.
+    pub fn draw(&self, ctx: &mut Context, layout: &Layout, theme: &Theme, area: graphics::Rect, white: bool, running: bool) -> GameResult
+    {
+        let width = CLOCK_WIDTH * layout.cell;
+        let height = area.h / 2.0;
+        let rect = graphics::Rect::new(area.x + area.w - width, area.y + (area.h - height) / 2.0, width, height);
+
+        let fill = if running { [1.0, 1.0, 1.0, 0.25] } else { [0.0, 0.0, 0.0, 0.25] };
+        let background = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, fill.into())?;
+        graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+
+        let remaining = self.remaining[if white { 0 } else { 1 }];
+        let text = graphics::Text::new
+        (
+            graphics::TextFragment::from(Clock::text(remaining))
+                .font(theme.font)
+                .scale(layout.text_scale(CLOCK_SCALE)),
+        );
+        let dimensions = text.dimensions(ctx);
+        graphics::draw
+        (
+            ctx,
+            &text,
+            graphics::DrawParam::default()
+                .color(if remaining <= 0.0 { theme.check } else { theme.text })
+                .dest([rect.x + (rect.w - dimensions.w) / 2.0, rect.y + (rect.h - dimensions.h) / 2.0]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Clock, TimeControl};
+
+    #[test]
+    fn reads_time_controls()
+    {
+        assert_eq!(TimeControl::parse("5+3"), Some(TimeControl { initial: 300.0, increment: 3.0 }));
+        assert_eq!(TimeControl::parse("10"), Some(TimeControl { initial: 600.0, increment: 0.0 }));
+        assert_eq!(TimeControl::parse("0+1"), None);
+        assert_eq!(TimeControl::parse("five"), None);
+        assert_eq!(TimeControl::parse("0.5+1").unwrap().to_string(), "0.5+1");
+    }
+
+    #[test]
+    fn runs_out()
+    {
+        let mut clock = Clock::new(TimeControl { initial: 60.0, increment: 2.0 });
+        clock.tick(true, 30.0);
+        clock.moved(true);
+        assert_eq!(clock.remaining, [32.0, 60.0]);
+        assert_eq!(Clock::text(32.0), "0:32");
+        assert_eq!(Clock::text(5.25), "0:05.2");
+
+        clock.tick(false, 61.0);
+        assert_eq!(clock.flagged(), Some(false));
+        clock.moved(false);
+        assert_eq!(clock.flagged(), Some(false));
+    }
+}
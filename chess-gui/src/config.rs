@@ -0,0 +1,251 @@
+use linked_hash_map::LinkedHashMap;
+use std::fmt;
+
+/// A single value in a config file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value
+{
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+/// Reason a config file could not be read, with the line it was found on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError
+{
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Settings read from the small subset of TOML used for themes, piece sets and settings:
+/// `[section]` headers, `key = value` pairs with string, number or boolean values and `#` comments.
+/// Sections and keys keep the order they were written in, keys above the first header
+/// belong to the section named "".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config
+{
+    sections: LinkedHashMap<String, LinkedHashMap<String, Value>>,
+}
+
+impl Config
+{
+    pub fn parse(text: &str) -> Result<Config, ConfigError>
+    {
+        let mut config = Config::default();
+        let mut section = String::new();
+
+        for (index, raw_line) in text.lines().enumerate()
+        {
+            let error = |message: &str| ConfigError { line: index + 1, message: message.to_string() };
+            let line = strip_comment(raw_line).trim();
+
+            if line.is_empty() { continue; }
+
+            if line.starts_with('[')
+            {
+                if !line.ends_with(']') { return Err(error("unclosed section header")); }
+
+                section = line[1..line.len() - 1].trim().to_string();
+                if section.is_empty() { return Err(error("empty section name")); }
+
+                config.sections.entry(section.clone()).or_insert_with(LinkedHashMap::new);
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=')
+            {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(error("expected `key = value`")),
+            };
+            if key.is_empty() { return Err(error("missing key")); }
+
+            let value = parse_value(value).ok_or_else(|| error(&format!("invalid value for `{}`", key)))?;
+            config.set(&section, key, value);
+        }
+
+        Ok(config)
+    }
+
+    /// Section names in file order, not including the top level.
+    pub fn sections(&self) -> impl Iterator<Item = &str>
+    {
+        self.sections.keys().map(|name| name.as_str()).filter(|name| !name.is_empty())
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value>
+    {
+        self.sections.get(section)?.get(key)
+    }
+
+    pub fn get_str(&self, section: &str, key: &str) -> Option<&str>
+    {
+        match self.get(section, key)
+        {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_number(&self, section: &str, key: &str) -> Option<f64>
+    {
+        match self.get(section, key)
+        {
+            Some(Value::Number(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// All key value pairs of a section in file order.
+    pub fn entries(&self, section: &str) -> impl Iterator<Item = (&str, &Value)>
+    {
+        self.sections.get(section).into_iter().flat_map(|keys| keys.iter().map(|(key, value)| (key.as_str(), value)))
+    }
+
+    pub fn set(&mut self, section: &str, key: &str, value: Value)
+    {
+        self.sections
+            .entry(section.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+    }
+}
+
+/// Writes the config back out in the same format it is parsed from.
+impl fmt::Display for Config
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        // top level keys have to come before any header
+        let ordered = self.sections.iter().filter(|(name, _)| name.is_empty())
+            .chain(self.sections.iter().filter(|(name, _)| !name.is_empty()));
+
+        let mut first = true;
+        for (name, keys) in ordered
+        {
+            if !name.is_empty()
+            {
+                if !first { writeln!(f)?; }
+                writeln!(f, "[{}]", name)?;
+            }
+            for (key, value) in keys
+            {
+                match value
+                {
+                    Value::String(text) => writeln!(f, "{} = \"{}\"", key, text.replace('\\', "\\\\").replace('"', "\\\""))?,
+                    Value::Number(number) => writeln!(f, "{} = {}", key, number)?,
+                    Value::Bool(flag) => writeln!(f, "{} = {}", key, flag)?,
+                }
+            }
+            first = false;
+        }
+
+        Ok(())
+    }
+}
+
+/// Cuts a line at the first `#` that is not inside a string.
+fn strip_comment(line: &str) -> &str
+{
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices()
+    {
+        match c
+        {
+            '\\' if in_string && !escaped => { escaped = true; continue; }
+            '"' if !escaped => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => (),
+        }
+        escaped = false;
+    }
+
+    line
+}
+
+fn parse_value(text: &str) -> Option<Value>
+{
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"')
+    {
+        let mut value = String::new();
+        let mut chars = text[1..text.len() - 1].chars();
+        while let Some(c) = chars.next()
+        {
+            match c
+            {
+                '\\' => match chars.next()?
+                {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    escaped @ ('\\' | '"') => value.push(escaped),
+                    _ => return None,
+                },
+                '"' => return None,
+                _ => value.push(c),
+            }
+        }
+        return Some(Value::String(value));
+    }
+
+    match text
+    {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => text.replace('_', "").parse::<f64>().ok().filter(|number| number.is_finite()).map(Value::Number),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Config, Value};
+
+    #[test]
+    fn parses_sections_in_order()
+    {
+        let config = Config::parse(
+            "# themes\nversion = 2\n\n[mushroom]\nlight = \"#e4c46c\" # warm\n\n[ocean]\ndark = \"#224466\"\nbold = true\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.sections().collect::<Vec<_>>(), vec!["mushroom", "ocean"]);
+        assert_eq!(config.get_number("", "version"), Some(2.0));
+        assert_eq!(config.get_str("mushroom", "light"), Some("#e4c46c"));
+        assert_eq!(config.get("ocean", "bold"), Some(&Value::Bool(true)));
+        assert_eq!(config.get("ocean", "light"), None);
+    }
+
+    #[test]
+    fn reports_the_broken_line()
+    {
+        let error = Config::parse("[a]\nkey = 1\nkey \"value\"\n").unwrap_err();
+        assert_eq!(error.line, 3);
+
+        assert!(Config::parse("[a\n").is_err());
+        assert!(Config::parse("key = \"unterminated\n").is_err());
+        assert!(Config::parse("key = nonsense\n").is_err());
+    }
+
+    #[test]
+    fn writes_what_it_reads()
+    {
+        let mut config = Config::default();
+        config.set("", "theme", Value::String("say \"hi\" # not a comment".to_string()));
+        config.set("window", "width", Value::Number(800.0));
+        config.set("sound", "enabled", Value::Bool(false));
+
+        let text = config.to_string();
+        assert!(text.starts_with("theme = "));
+        assert_eq!(Config::parse(&text).unwrap(), config);
+    }
+}
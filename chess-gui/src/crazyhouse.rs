@@ -0,0 +1,184 @@
+use super::{piece_char, Game, Variant};
+
+/// Kinds of pieces a pocket holds, indexed like `Game::pieces` without the king: pawn, queen, rook, bishop and knight.
+pub const POCKET_KINDS: usize = 5;
+
+impl Game {
+    /// Returns how many pieces of each kind white or black holds to drop in Crazyhouse,
+    /// indexed pawn, queen, rook, bishop and knight. Always empty in other variants.
+    pub fn get_pocket(&self, _is_white: bool) -> [u32; POCKET_KINDS] {
+        self.pockets[if _is_white { 0 } else { 1 }]
+    }
+
+    /// The squares a piece of the side to move can be dropped on, or None if `_from` is not written
+    /// like "N@" or the game is not Crazyhouse. Pawns are not dropped on the first or last rank,
+    /// and no drop may leave the own king in check.
+    pub(crate) fn search_drops(&mut self, _from: &str) -> Option<Vec<String>> {
+        let index_of_piece = dropped_piece(_from).filter(|_| self.variant == Variant::Crazyhouse)?;
+        Some(self.drops_of_colour(self.white_turn, index_of_piece))
+    }
+
+    /// Drops of one kind of piece from a pocket, see `search_drops`.
+    pub(crate) fn drops_of_colour(&mut self, _is_white: bool, index_of_piece: usize) -> Vec<String> {
+        let colour = if _is_white { 0 } else { 1 };
+        let mut drops = vec![];
+        if self.pockets[colour][index_of_piece] == 0 {
+            return drops;
+        }
+
+        let occupied = self.colour_of_piece[0] | self.colour_of_piece[1];
+        for index in 0..64 {
+            let bit_pos = 2_u64.pow(index);
+            if (occupied & bit_pos != 0) | ((index_of_piece == 0) & ((index % 8 == 0) | (index % 8 == 7))) {
+                continue;
+            }
+            // the piece is put down for a moment to see if it shields the king
+            self.pieces[index_of_piece] |= bit_pos;
+            self.colour_of_piece[colour] |= bit_pos;
+            let safe = !self.king_in_check(_is_white);
+            self.pieces[index_of_piece] &= !bit_pos;
+            self.colour_of_piece[colour] &= !bit_pos;
+            if safe {
+                drops.push(self.transform_back(index / 8, index % 8));
+            }
+        }
+        drops
+    }
+
+    /// True if the side has a piece in its pocket that can be dropped.
+    pub(crate) fn can_drop(&mut self, _is_white: bool) -> bool {
+        self.variant == Variant::Crazyhouse
+            && (0..POCKET_KINDS).any(|index_of_piece| !self.drops_of_colour(_is_white, index_of_piece).is_empty())
+    }
+
+    /// Puts a piece from the pocket of the side to move on `_to`, see `make_move`. Returns false if the drop is not legal.
+    pub(crate) fn make_drop(&mut self, _from: &str, _to: &str) -> bool {
+        let legal = self.ending.is_none()
+            && self.search_drops(_from).is_some_and(|drops| drops.iter().any(|square| square == _to));
+        if !legal {
+            return false;
+        }
+
+        let index_of_piece = dropped_piece(_from).unwrap();
+        let colour = if self.white_turn { 0 } else { 1 };
+        let (to_file, to_rank) = self.transform_input(_to);
+        let bit_pos = 2_u64.pow(to_file * 8 + to_rank);
+        self.pieces[index_of_piece] |= bit_pos;
+        self.colour_of_piece[colour] |= bit_pos;
+        self.pockets[colour][index_of_piece] -= 1;
+
+        self.halfmove_clock += 1;
+        if !self.white_turn {
+            self.fullmove_number += 1;
+        }
+        self.en_passant_at = 16;
+        self.white_turn = !self.white_turn;
+        self.update_state();
+        true
+    }
+
+    /// After a move from `from_index` to `to_index` in Crazyhouse, puts what was taken in the mover's pocket
+    /// and keeps track of promoted pieces, which go back into a pocket as pawns.
+    /// Called after `do_move`, with the pieces taken before the move and whether it promoted a pawn.
+    pub(crate) fn fill_pocket(&mut self, from_index: u32, to_index: u32, captured_before: usize, promotes: bool) {
+        if self.variant != Variant::Crazyhouse {
+            return;
+        }
+        let colour = if self.white_turn { 0 } else { 1 };
+        let (from_bit, to_bit) = (2_u64.pow(from_index), 2_u64.pow(to_index));
+
+        let taken_promoted = self.promoted & to_bit != 0;
+        for taken in &self.captured[captured_before..] {
+            let index_of_piece = (0..POCKET_KINDS)
+                .find(|index| piece_char(*index, false) == taken.to_ascii_lowercase())
+                .unwrap_or(0);
+            self.pockets[colour][if taken_promoted { 0 } else { index_of_piece }] += 1;
+        }
+
+        let moved_promoted = self.promoted & from_bit != 0;
+        self.promoted &= !from_bit & !to_bit;
+        if moved_promoted | promotes {
+            self.promoted |= to_bit;
+        }
+    }
+}
+
+/// The piece a drop such as "N@" puts down, indexed like `Game::pieces`. None for a square or a king.
+pub(crate) fn dropped_piece(_from: &str) -> Option<usize> {
+    let mut chars = _from.chars();
+    let letter = chars.next()?;
+    if (chars.next() != Some('@')) | chars.next().is_some() {
+        return None;
+    }
+    (0..POCKET_KINDS).find(|index| piece_char(*index, true) == letter)
+}
+
+/// The from square `make_move` takes for dropping a piece, e.g. "N@" for a knight, indexed like `Game::pieces`.
+pub(crate) fn drop_square(index_of_piece: usize) -> String {
+    format!("{}@", piece_char(index_of_piece, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_perft, Ending, FenError, Game, MoveInputError, Variant};
+
+    #[test]
+    fn captured_pieces_are_dropped() {
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR[] w KQkq - 0 2").unwrap();
+        assert!(game.make_move("E4", "D5"));
+        assert_eq!(game.get_pocket(true), [1, 0, 0, 0, 0]);
+        assert!(game.make_move("D8", "D5"));
+        assert_eq!(game.get_pocket(false), [1, 0, 0, 0, 0]);
+        assert_eq!(game.to_fen(), "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3");
+
+        // pawns go on any of the 34 empty squares but d8
+        let drops = game.get_possible_moves("P@").unwrap();
+        assert_eq!(drops.len(), 33);
+        assert!(!drops.contains(&"A8".to_string()) && drops.contains(&"D4".to_string()));
+        assert_eq!(game.get_possible_moves("N@"), Some(vec![]));
+        assert!(!game.make_move("P@", "E1"));
+        assert_eq!(game.parse_move("@c4").unwrap().from, "P@");
+        assert_eq!(game.parse_move("N@f3"), Err(MoveInputError::Illegal));
+        assert!(game.make_move("P@", "C4"));
+        assert_eq!(game.get_pocket(true), [0; 5]);
+        assert_eq!(game.to_fen(), "rnb1kbnr/ppp1pppp/8/3q4/2P5/8/PPPP1PPP/RNBQKBNR[p] b KQkq - 1 3");
+    }
+
+    #[test]
+    fn drops_block_checks_and_mate() {
+        // only a drop between rook and king stops the check
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, "4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1").unwrap();
+        assert_eq!(game.get_possible_moves("N@").unwrap(), vec!["B1", "C1", "D1"]);
+
+        // a knight dropped next to a king walled in by its own pieces mates
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, "6rk/6pp/8/8/8/8/8/4K3[N] w - - 0 1").unwrap();
+        assert_eq!(game.move_to_san("N@", "F7").as_deref(), Some("N@f7#"));
+        assert!(game.make_move("N@", "F7"));
+        assert_eq!(game.get_ending(), Some(Ending::Checkmate { white_wins: true }));
+    }
+
+    #[test]
+    fn promoted_pieces_return_as_pawns() {
+        let fen = "r3k3/1P6/8/8/8/8/8/4K3[] w - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, fen).unwrap();
+        assert!(game.make_move("B7", "B8"));
+        assert_eq!(game.to_fen(), "rQ~2k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+        assert!(game.make_move("A8", "B8"));
+        assert_eq!(game.get_pocket(false), [1, 0, 0, 0, 0]);
+        assert_eq!(game.to_fen(), "1r2k3/8/8/8/8/8/8/4K3[p] w - - 0 2");
+        assert!(Game::from_variant_fen(Variant::Crazyhouse, "4k3/8/8/8/8/8/8/4K3[K] w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn promoted_marker_needs_a_piece() {
+        for fen in ["9~/8/8/8/8/8/8/4K2k w - - 0 1", "4k3/8/8/8/8/8/8/4K2~ w - - 0 1", "~4k3/8/8/8/8/8/8/4K3 w - - 0 1", "4kQ~~2/8/8/8/8/8/8/4K3 w - - 0 1"] {
+            assert!(matches!(Game::from_variant_fen(Variant::Crazyhouse, fen), Err(FenError::Malformed(_))), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn perft_matches_known_numbers() {
+        assert_perft(Variant::Crazyhouse, "2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1", &[301, 75353]);
+        assert_perft(Variant::Crazyhouse, "r1bqk2r/pppp1ppp/2n1p3/4P3/1b1Pn3/2NB1N2/PPP2PPP/R1BQK2R[] b KQkq - 0 1", &[42, 1347, 58057]);
+    }
+}
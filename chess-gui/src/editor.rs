@@ -0,0 +1,371 @@
+use crate::cli::random_chess960;
+use crate::layout::Layout;
+use crate::theme::{PieceSet, Theme};
+use crate::ui::{self, EDITOR_SCALE};
+
+use chess_gui::{FenError, Game, Variant, VARIANTS};
+use ggez::{graphics, Context, GameResult};
+
+/// Height of one button row, in tiles.
+const ROW_HEIGHT: f32 = 0.5;
+/// Pieces offered in the palette, white on the first row and black on the second.
+const PALETTE: [char; 12] = ['K', 'Q', 'R', 'B', 'N', 'P', 'k', 'q', 'r', 'b', 'n', 'p'];
+/// Castling rights as written in FEN, ordered like `Game::get_castling_rights`.
+const CASTLING_LETTERS: [char; 4] = ['K', 'Q', 'k', 'q'];
+
+/// Something in the editor panel that can be clicked.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditorButton
+{
+    /// Picks the piece placed by clicking the board.
+    Piece(char),
+    /// Picks removing pieces instead of placing them.
+    Eraser,
+    SideToMove,
+    Castling(usize),
+    EnPassant,
+    /// Steps through the variants the position is played by.
+    Variant,
+    Clear,
+    StartPosition,
+    /// Sets up a random Chess960 starting position, the game is then played by the Chess960 castling rules.
+    Chess960,
+    Play,
+    Cancel,
+}
+
+/// Position being set up, it only becomes a game once it passes the checks in `Game::from_variant_fen`.
+pub struct Editor
+{
+    // Pieces by square index file * 8 + rank like in the game, '*' for empty squares.
+    squares: [char; 64],
+    white_to_move: bool,
+    castling: [bool; 4],
+    // Castling rights are written with the rook's file, so the game follows the Chess960 rules.
+    chess960: bool,
+    // File of the pawn that just moved two steps and can be taken en passant.
+    en_passant: Option<u32>,
+    // Rules the position is played by once it is a game, they also decide which positions are legal.
+    variant: Variant,
+    // Piece placed by clicking the board, '*' removes pieces.
+    brush: char,
+    // Why the position could not be played.
+    pub message: Option<String>,
+}
+
+impl Editor
+{
+    /// Starts from the position of a game.
+    pub fn from_game(game: &Game) -> Editor
+    {
+        let mut squares = ['*'; 64];
+        for (row, line) in game.get_board().lines().enumerate()
+        {
+            for (file, piece) in line.chars().enumerate()
+            {
+                squares[file * 8 + 7 - row] = piece;
+            }
+        }
+
+        // the en passant square is the fourth field of the FEN, e.g. "e6"
+        let en_passant = game.to_fen().split_whitespace().nth(3).and_then(|square| square.bytes().next())
+            .filter(|file| (b'a'..=b'h').contains(file))
+            .map(|file| (file - b'a') as u32);
+
+        Editor
+        {
+            squares,
+            white_to_move: game.is_white_turn(),
+            castling: game.get_castling_rights(),
+            chess960: game.is_chess960(),
+            en_passant,
+            variant: game.get_variant(),
+            brush: 'P',
+            message: None,
+        }
+    }
+
+    /// The position in the same format as `Game::get_board`, so it can be drawn like a game.
+    pub fn get_board(&self) -> String
+    {
+        (0..8).rev()
+            .map(|rank| (0..8).map(|file| self.squares[file * 8 + rank]).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Puts the selected piece on a square, clicking with the same piece again takes it off.
+    pub fn place(&mut self, square: (u32, u32))
+    {
+        let index = (square.0 * 8 + square.1) as usize;
+        self.squares[index] = if self.squares[index] == self.brush { '*' } else { self.brush };
+        self.message = None;
+    }
+
+    pub fn remove(&mut self, square: (u32, u32))
+    {
+        self.squares[(square.0 * 8 + square.1) as usize] = '*';
+        self.message = None;
+    }
+
+    /// Handles every button except Play and Cancel, which need the rest of the application.
+    pub fn press(&mut self, button: EditorButton)
+    {
+        match button
+        {
+            EditorButton::Piece(piece) => self.brush = piece,
+            EditorButton::Eraser => self.brush = '*',
+            EditorButton::SideToMove =>
+            {
+                self.white_to_move = !self.white_to_move;
+                // the pawn that can be taken belongs to the other side now
+                self.en_passant = None;
+            }
+            EditorButton::Castling(right) => self.castling[right] = !self.castling[right],
+            EditorButton::EnPassant => self.en_passant = self.next_en_passant(),
+            EditorButton::Variant =>
+            {
+                let index = VARIANTS.iter().position(|variant| *variant == self.variant).map_or(0, |index| index + 1);
+                self.variant = VARIANTS[index % VARIANTS.len()];
+                // there is no castling without check
+                if !self.variant.has_check() { self.castling = [false; 4]; }
+            }
+            EditorButton::Clear =>
+            {
+                self.squares = ['*'; 64];
+                self.castling = [false; 4];
+                self.en_passant = None;
+            }
+            EditorButton::StartPosition => *self = Editor { brush: self.brush, ..Editor::from_game(&Game::new_variant(self.variant)) },
+            EditorButton::Chess960 =>
+            {
+                let start = Game::new_chess960(random_chess960()).expect("random_chess960 picks an existing position");
+                *self = Editor { brush: self.brush, variant: self.variant, ..Editor::from_game(&start) };
+            }
+            EditorButton::Play | EditorButton::Cancel => (),
+        }
+        self.message = None;
+    }
+
+    /// Cycles through the files with a pawn that could just have moved two steps, then back to none.
+    fn next_en_passant(&self) -> Option<u32>
+    {
+        // the pawn belongs to the side not to move and stands on its fourth rank
+        let (pawn, rank) = if self.white_to_move { ('p', 4) } else { ('P', 3) };
+        let behind = if self.white_to_move { [5, 6] } else { [2, 1] };
+
+        let start = self.en_passant.map_or(0, |file| file + 1);
+        (start..8).find(|file|
+        {
+            self.squares[(file * 8 + rank) as usize] == pawn
+                && behind.iter().all(|rank| self.squares[(file * 8 + rank) as usize] == '*')
+        })
+    }
+
+    pub fn to_fen(&self) -> String
+    {
+        let placement = self.get_board().split('\n').map(|line|
+        {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for piece in line.chars()
+            {
+                if piece == '*' { empty += 1; continue; }
+                if empty > 0 { rank += &empty.to_string(); empty = 0; }
+                rank.push(piece);
+            }
+            if empty > 0 { rank += &empty.to_string(); }
+            rank
+        })
+        .collect::<Vec<String>>()
+        .join("/");
+
+        let castling: String = (0..4).filter(|right| self.castling[*right]).map(|right| self.castling_letter(right)).collect();
+        let en_passant = match self.en_passant
+        {
+            Some(file) => format!("{}{}", (b'a' + file as u8) as char, if self.white_to_move { 6 } else { 3 }),
+            None => "-".to_string(),
+        };
+
+        format!
+        (
+            "{} {} {} {} 0 1",
+            placement,
+            if self.white_to_move { "w" } else { "b" },
+            if castling.is_empty() { "-" } else { &castling },
+            en_passant,
+        )
+    }
+
+    /// The FEN letter of a castling right. Chess960 rights name the file of the outermost rook on that side
+    /// of the king like Shredder-FEN, without such a rook the plain letter is left for `Game::from_fen` to refuse.
+    fn castling_letter(&self, right: usize) -> char
+    {
+        let (king, rook, rank) = if right < 2 { ('K', 'R', 0) } else { ('k', 'r', 7) };
+        let king_file = (0..8).find(|file| self.squares[file * 8 + rank] == king);
+        let rook_file = king_file.and_then(|king_file|
+        {
+            let mut files: Vec<usize> = if right.is_multiple_of(2) { (king_file + 1..8).rev().collect() } else { (0..king_file).collect() };
+            files.retain(|file| self.squares[file * 8 + rank] == rook);
+            files.first().copied()
+        });
+
+        match rook_file
+        {
+            Some(file) if self.chess960 => (if right < 2 { b'A' } else { b'a' } + file as u8) as char,
+            _ => CASTLING_LETTERS[right],
+        }
+    }
+
+    /// Turns the position into a game of the chosen variant, or explains why it can not be played.
+    pub fn to_game(&self) -> Result<Game, FenError>
+    {
+        Game::from_variant_fen(self.variant, &self.to_fen())
+    }
+
+    /// Every button with where it is drawn in the panel, top to bottom.
+    fn buttons(layout: &Layout) -> Vec<(EditorButton, graphics::Rect)>
+    {
+        let panel = layout.panel_rect();
+        let row_height = ROW_HEIGHT * layout.cell;
+        let mut buttons = vec![];
+
+        // two rows of six pieces, then the eraser on its own row
+        let piece_size = panel.w / 6.0;
+        for (index, piece) in PALETTE.iter().enumerate()
+        {
+            let rect = graphics::Rect::new
+            (
+                panel.x + (index % 6) as f32 * piece_size,
+                panel.y + (index / 6) as f32 * piece_size,
+                piece_size,
+                piece_size,
+            );
+            buttons.push((EditorButton::Piece(*piece), rect));
+        }
+
+        let mut y = panel.y + 2.0 * piece_size;
+        let mut row = |buttons: &mut Vec<(EditorButton, graphics::Rect)>, row_buttons: &[EditorButton]|
+        {
+            buttons.append(&mut ui::button_row(row_buttons, graphics::Rect::new(panel.x, y, panel.w, row_height)));
+            y += row_height;
+        };
+
+        row(&mut buttons, &[EditorButton::Eraser]);
+        row(&mut buttons, &[EditorButton::SideToMove]);
+        row(&mut buttons, &[EditorButton::Castling(0), EditorButton::Castling(1), EditorButton::Castling(2), EditorButton::Castling(3)]);
+        row(&mut buttons, &[EditorButton::EnPassant]);
+        row(&mut buttons, &[EditorButton::Variant]);
+        row(&mut buttons, &[EditorButton::Clear, EditorButton::StartPosition, EditorButton::Chess960]);
+        row(&mut buttons, &[EditorButton::Play, EditorButton::Cancel]);
+
+        buttons
+    }
+
+    pub fn button_at(layout: &Layout, x: f32, y: f32) -> Option<EditorButton>
+    {
+        ui::button_at(&Editor::buttons(layout), x, y)
+    }
+
+    /// Whether a button shows a setting that is switched on.
+    fn is_active(&self, button: EditorButton) -> bool
+    {
+        match button
+        {
+            EditorButton::Piece(piece) => self.brush == piece,
+            EditorButton::Eraser => self.brush == '*',
+            EditorButton::Castling(right) => self.castling[right],
+            EditorButton::Chess960 => self.chess960,
+            _ => false,
+        }
+    }
+
+    fn label(&self, button: EditorButton) -> String
+    {
+        match button
+        {
+            EditorButton::Piece(piece) => piece.to_string(),
+            EditorButton::Eraser => "Remove pieces".to_string(),
+            EditorButton::SideToMove => if self.white_to_move { "White to move" } else { "Black to move" }.to_string(),
+            EditorButton::Castling(right) => CASTLING_LETTERS[right].to_string(),
+            EditorButton::EnPassant => match self.en_passant
+            {
+                Some(file) => format!("En passant: {}", (b'a' + file as u8) as char),
+                None => "En passant: -".to_string(),
+            },
+            EditorButton::Variant => format!("Rules: {}", self.variant),
+            EditorButton::Clear => "Empty".to_string(),
+            EditorButton::StartPosition => "Start".to_string(),
+            EditorButton::Chess960 => "960".to_string(),
+            EditorButton::Play => "Play".to_string(),
+            EditorButton::Cancel => "Cancel".to_string(),
+        }
+    }
+
+    /// Draws the palette, settings and buttons in the side panel, and the reason the last Play failed.
+    pub fn draw(&self, ctx: &mut Context, layout: &Layout, theme: &Theme, pieces: &PieceSet) -> GameResult
+    {
+        let buttons = Editor::buttons(layout);
+
+        for (button, rect) in &buttons
+        {
+            let fill = if self.is_active(*button) { theme.highlight } else { graphics::Color::new(0.0, 0.0, 0.0, 0.25) };
+            let background = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), *rect, fill)?;
+            graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+
+            if let EditorButton::Piece(piece) = button
+            {
+                let sprite = pieces.sprites.get(piece).unwrap();
+                let scale = rect.w / sprite.width() as f32;
+                graphics::draw
+                (
+                    ctx,
+                    sprite,
+                    graphics::DrawParam::default()
+                        .scale([scale, scale])
+                        .dest([rect.x, rect.y]),
+                )?;
+                continue;
+            }
+
+            let text = graphics::Text::new
+            (
+                graphics::TextFragment::from(self.label(*button))
+                    .font(theme.font)
+                    .scale(layout.text_scale(EDITOR_SCALE)),
+            );
+            let dimensions = text.dimensions(ctx);
+            graphics::draw
+            (
+                ctx,
+                &text,
+                graphics::DrawParam::default()
+                    .color(theme.text)
+                    .dest([rect.x + (rect.w - dimensions.w) / 2.0, rect.y + (rect.h - dimensions.h) / 2.0]),
+            )?;
+        }
+
+        if let Some(message) = &self.message
+        {
+            let below = buttons.last().map_or(layout.panel_rect().y, |(_, rect)| rect.y + rect.h) + layout.cell * 0.1;
+            let mut text = graphics::Text::new
+            (
+                graphics::TextFragment::from(message.as_str())
+                    .font(theme.font)
+                    .scale(layout.text_scale(EDITOR_SCALE)),
+            );
+            // long messages wrap inside the panel
+            text.set_bounds([layout.panel_rect().w, f32::INFINITY], graphics::Align::Left);
+            graphics::draw
+            (
+                ctx,
+                &text,
+                graphics::DrawParam::default()
+                    .color(theme.check)
+                    .dest([layout.panel_rect().x, below]),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+/// How a finished game ended. Written to PGN as the result and termination, and sent over the network
+/// as the result followed by `reason`, e.g. "0-1 resignation".
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Ending {
+    Checkmate { white_wins: bool },
+    Resignation { white_wins: bool },
+    /// A side ran out of time.
+    Timeout { white_wins: bool },
+    /// King of the Hill, a king reached the centre.
+    Hill { white_wins: bool },
+    /// Three-check, a side gave its third check.
+    ThreeChecks { white_wins: bool },
+    /// Antichess, the side to move has lost all its pieces or has no move left.
+    NoMovesLeft { white_wins: bool },
+    /// Atomic, a king was blown up.
+    Explosion { white_wins: bool },
+    /// Horde, black took every white piece.
+    HordeCaptured,
+    /// Both sides agreed to a draw.
+    Agreement,
+    /// A draw claimed because the same position came up for the third time.
+    Repetition,
+    /// A draw claimed after fifty moves by each side without a capture or pawn move.
+    FiftyMoves,
+}
+
+impl Ending {
+    /// True if white won, false if black won and None for a draw.
+    pub fn winner(self) -> Option<bool> {
+        match self {
+            Ending::Checkmate { white_wins }
+            | Ending::Resignation { white_wins }
+            | Ending::Timeout { white_wins }
+            | Ending::Hill { white_wins }
+            | Ending::ThreeChecks { white_wins }
+            | Ending::NoMovesLeft { white_wins }
+            | Ending::Explosion { white_wins } => Some(white_wins),
+            Ending::HordeCaptured => Some(false),
+            Ending::Agreement | Ending::Repetition | Ending::FiftyMoves => None,
+        }
+    }
+
+    /// The result as written at the end of a PGN game.
+    pub fn result(self) -> &'static str {
+        match self.winner() {
+            Some(true) => "1-0",
+            Some(false) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+
+    /// One word for how the game ended, the winner is left to the result.
+    pub fn reason(self) -> &'static str {
+        match self {
+            Ending::Checkmate { .. } => "checkmate",
+            Ending::Resignation { .. } => "resignation",
+            Ending::Timeout { .. } => "time",
+            Ending::Hill { .. } => "hill",
+            Ending::ThreeChecks { .. } => "three-checks",
+            Ending::NoMovesLeft { .. } => "no-moves",
+            Ending::Explosion { .. } => "explosion",
+            Ending::HordeCaptured => "horde-captured",
+            Ending::Agreement => "agreement",
+            Ending::Repetition => "repetition",
+            Ending::FiftyMoves => "fifty-move",
+        }
+    }
+
+    /// Reads an ending back from its result and reason, None if they do not fit together.
+    pub fn from_result(result: &str, reason: &str) -> Option<Ending> {
+        let white_wins = match result {
+            "1-0" => Some(true),
+            "0-1" => Some(false),
+            "1/2-1/2" => None,
+            _ => return None,
+        };
+
+        match (reason, white_wins) {
+            ("checkmate", Some(white_wins)) => Some(Ending::Checkmate { white_wins }),
+            ("resignation", Some(white_wins)) => Some(Ending::Resignation { white_wins }),
+            ("time", Some(white_wins)) => Some(Ending::Timeout { white_wins }),
+            ("hill", Some(white_wins)) => Some(Ending::Hill { white_wins }),
+            ("three-checks", Some(white_wins)) => Some(Ending::ThreeChecks { white_wins }),
+            ("no-moves", Some(white_wins)) => Some(Ending::NoMovesLeft { white_wins }),
+            ("explosion", Some(white_wins)) => Some(Ending::Explosion { white_wins }),
+            ("horde-captured", Some(false)) => Some(Ending::HordeCaptured),
+            ("agreement", None) => Some(Ending::Agreement),
+            ("repetition", None) => Some(Ending::Repetition),
+            ("fifty-move", None) => Some(Ending::FiftyMoves),
+            _ => None,
+        }
+    }
+}
+
+/// A sentence for the player, e.g. "Black resigned.".
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let side = |white: bool| if white { "White" } else { "Black" };
+        match *self {
+            Ending::Checkmate { white_wins } => write!(f, "{} wins by checkmate.", side(white_wins)),
+            Ending::Resignation { white_wins } => write!(f, "{} resigned.", side(!white_wins)),
+            Ending::Timeout { white_wins } => write!(f, "{} lost on time.", side(!white_wins)),
+            Ending::Hill { white_wins } => write!(f, "{} wins by reaching the centre.", side(white_wins)),
+            Ending::ThreeChecks { white_wins } => write!(f, "{} wins by giving three checks.", side(white_wins)),
+            Ending::NoMovesLeft { white_wins } => write!(f, "{} wins by running out of moves.", side(white_wins)),
+            Ending::Explosion { white_wins } => write!(f, "{} wins by blowing up the king.", side(white_wins)),
+            Ending::HordeCaptured => write!(f, "Black wins by taking the whole horde."),
+            Ending::Agreement => write!(f, "Draw agreed."),
+            Ending::Repetition => write!(f, "Draw by threefold repetition."),
+            Ending::FiftyMoves => write!(f, "Draw by the fifty-move rule."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ending;
+
+    #[test]
+    fn reads_what_it_writes() {
+        let endings = [
+            Ending::Checkmate { white_wins: true },
+            Ending::Resignation { white_wins: false },
+            Ending::Timeout { white_wins: true },
+            Ending::Hill { white_wins: false },
+            Ending::ThreeChecks { white_wins: true },
+            Ending::NoMovesLeft { white_wins: false },
+            Ending::Explosion { white_wins: true },
+            Ending::HordeCaptured,
+            Ending::Agreement,
+            Ending::Repetition,
+            Ending::FiftyMoves,
+        ];
+        for ending in endings {
+            assert_eq!(Ending::from_result(ending.result(), ending.reason()), Some(ending));
+        }
+
+        assert_eq!(Ending::Resignation { white_wins: false }.result(), "0-1");
+        assert_eq!(Ending::Resignation { white_wins: false }.to_string(), "White resigned.");
+        // a draw can not be won
+        assert_eq!(Ending::from_result("1-0", "repetition"), None);
+        assert_eq!(Ending::from_result("1/2-1/2", "resignation"), None);
+        assert_eq!(Ending::from_result("*", "checkmate"), None);
+    }
+}
@@ -0,0 +1,188 @@
+use super::crazyhouse::drop_square;
+use super::{piece_char, piece_value, Game, Variant, POCKET_KINDS};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Score of a checkmate or other win by the rules, far above any material difference. Mates found sooner score higher.
+const MATE: i32 = 100_000;
+/// Value of a pawn in the search, the other pieces follow `piece_value`.
+const PAWN: i32 = 100;
+/// Extra score of a pawn, knight or bishop for each step closer to the centre.
+const CENTRE_BONUS: i32 = 8;
+
+impl Game {
+    /// Picks a move for the side to move by looking `_depth` plies ahead, at least one.
+    /// Positions are judged by material and how central the pieces are, so the engine is weak
+    /// but always plays legal moves.
+    /// Returns the from and to squares for `make_move`, a Crazyhouse drop from a pocket like "N@". None if there is no legal move.
+    /// Pawns reaching the last rank become queens.
+    pub fn best_move(&self, _depth: u32) -> Option<(String, String)> {
+        self.best_move_until(_depth, &AtomicBool::new(false))
+    }
+
+    /// Like `best_move`, but gives up once `_stop` is set and returns None,
+    /// so a search nobody waits for any more does not keep running.
+    pub fn best_move_until(&self, _depth: u32, _stop: &AtomicBool) -> Option<(String, String)> {
+        let mut game = self.clone();
+        game.set_promotion("Q");
+
+        let mut best = None;
+        let mut alpha = -MATE - 1;
+        for (from, to) in game.legal_moves() {
+            let mut after = game.clone();
+            after.make_move(&from, &to);
+            let score = -after.negamax(_depth.max(1) - 1, 1, -MATE - 1, -alpha, _stop);
+            if _stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some((from, to));
+            }
+        }
+        best
+    }
+
+    /// Every legal move of the side to move, captures first so the search can cut off sooner.
+    fn legal_moves(&mut self) -> Vec<(String, String)> {
+        let own = self.colour_of_piece[if self.white_turn { 0 } else { 1 }];
+        let others = self.colour_of_piece[if self.white_turn { 1 } else { 0 }];
+
+        let mut moves = vec![];
+        for index in (0..64).filter(|index| own & 2_u64.pow(*index) != 0) {
+            let from = self.transform_back(index / 8, index % 8);
+            for to in self.get_possible_moves(&from).unwrap_or_default() {
+                let (file, rank) = self.transform_input(&to);
+                let is_capture = others & 2_u64.pow(file * 8 + rank) != 0;
+                moves.push((is_capture, from.clone(), to));
+            }
+        }
+        for from in (0..POCKET_KINDS).map(drop_square) {
+            for to in self.get_possible_moves(&from).unwrap_or_default() {
+                moves.push((false, from.clone(), to));
+            }
+        }
+        moves.sort_by_key(|(is_capture, _, _)| !is_capture);
+        moves.into_iter().map(|(_, from, to)| (from, to)).collect()
+    }
+
+    /// Score of the position for the side to move, `_ply` plies below the root.
+    /// Once `_stop` is set the score is meaningless and only returned to unwind the search.
+    fn negamax(&mut self, _depth: u32, _ply: i32, mut _alpha: i32, _beta: i32, _stop: &AtomicBool) -> i32 {
+        if _stop.load(Ordering::Relaxed) {
+            return 0;
+        }
+        let moves = self.legal_moves();
+        if let Some(white_wins) = self.ending.and_then(|ending| ending.winner()) {
+            return if white_wins == self.white_turn { MATE - _ply } else { -MATE + _ply };
+        }
+        if moves.is_empty() {
+            let in_check = self.colour_in_check_or_mate(self.white_turn) != 0;
+            return if in_check { -MATE + _ply } else { 0 };
+        }
+        if _depth == 0 {
+            return self.material_score();
+        }
+
+        for (from, to) in moves {
+            let mut after = self.clone();
+            after.make_move(&from, &to);
+            let score = -after.negamax(_depth - 1, _ply + 1, -_beta, -_alpha, _stop);
+            if score >= _beta {
+                return score;
+            }
+            _alpha = _alpha.max(score);
+        }
+        _alpha
+    }
+
+    /// Material of the side to move minus the other side's, in hundredths of a pawn.
+    /// Pawns and minor pieces get a little extra towards the centre, so the engine develops
+    /// its pieces instead of shuffling when no material can be won. In Antichess losing material is what counts.
+    fn material_score(&self) -> i32 {
+        let board: Vec<char> = self.get_board().chars().filter(|c| *c != '\n').collect();
+        let white: i32 = board
+            .iter()
+            .enumerate()
+            .map(|(index, piece)| {
+                // distance from the four centre squares, 0 to 3
+                let (column, row) = ((index % 8) as i32, (index / 8) as i32);
+                let distance = (2 * column - 7).abs().max((2 * row - 7).abs()) / 2;
+                let centre = if matches!(piece.to_ascii_lowercase(), 'p' | 'n' | 'b') {
+                    (3 - distance) * CENTRE_BONUS
+                } else {
+                    0
+                };
+                let value = piece_value(*piece) * PAWN + centre;
+                if piece.is_uppercase() {
+                    value
+                } else if piece.is_lowercase() {
+                    -value
+                } else {
+                    0
+                }
+            })
+            .sum();
+        // pieces in a crazyhouse pocket count like those on the board
+        let pockets: i32 = (0..POCKET_KINDS)
+            .map(|index| {
+                let value = piece_value(piece_char(index, true)) * PAWN;
+                (self.pockets[0][index] as i32 - self.pockets[1][index] as i32) * value
+            })
+            .sum();
+        let white = white + pockets;
+        let white = if self.variant == Variant::Antichess { -white } else { white };
+        if self.white_turn {
+            white
+        } else {
+            -white
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, Variant};
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn takes_a_free_queen() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(game.best_move(1), Some(("D1".to_string(), "D5".to_string())));
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        // the rook on a1 mates on a8, taking the knight instead lets black escape
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R3K2n w - - 0 1").unwrap();
+        assert_eq!(game.best_move(2), Some(("A1".to_string(), "A8".to_string())));
+    }
+
+    #[test]
+    fn plays_for_the_variant_win() {
+        // the king steps onto the hill instead of taking the rook, and the game is over once it stands there
+        let game = Game::from_variant_fen(Variant::KingOfTheHill, "4k3/8/8/8/8/4K3/3r4/8 w - - 0 1").unwrap();
+        assert_eq!(game.best_move(2), Some(("E3".to_string(), "E4".to_string())));
+        let game = Game::from_variant_fen(Variant::KingOfTheHill, "4k3/8/8/8/4K3/8/8/3r4 w - - 0 1").unwrap();
+        assert_eq!(game.best_move(1), None);
+
+        // in antichess the queen is given away
+        let game = Game::from_variant_fen(Variant::Antichess, "8/8/8/8/8/8/1p6/Q6K w - - 0 1").unwrap();
+        assert_eq!(game.best_move(1), Some(("A1".to_string(), "B2".to_string())));
+    }
+
+    #[test]
+    fn has_nothing_to_play_when_mated() {
+        let mut game = Game::new();
+        for (from, to) in [("F2", "F3"), ("E7", "E5"), ("G2", "G4"), ("D8", "H4")] {
+            assert!(game.make_move(from, to));
+        }
+        assert_eq!(game.best_move(2), None);
+    }
+
+    #[test]
+    fn stops_when_told() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(game.best_move_until(1, &AtomicBool::new(true)), None);
+        assert_eq!(game.best_move_until(1, &AtomicBool::new(false)), Some(("D1".to_string(), "D5".to_string())));
+    }
+}
@@ -0,0 +1,434 @@
+use super::{piece_char, Game, Variant, CHECKS_TO_WIN, POCKET_KINDS};
+use std::fmt;
+
+/// The letters of the castling rights in FEN, ordered like `Game::castling`.
+const CASTLING_LETTERS: [char; 4] = ['K', 'Q', 'k', 'q'];
+
+/// Why a FEN string could not be turned into a game.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FenError {
+    /// The text does not follow the FEN format, says which part is wrong.
+    Malformed(String),
+    /// Each colour needs exactly one king, holds how many white and black kings there are.
+    /// Antichess allows any number, and the white horde in Horde has none.
+    KingCount(u32, u32),
+    /// A pawn stands on the first or last rank, holds the square.
+    PawnOnBackRank(String),
+    /// The side that just moved is still in check.
+    OpponentInCheck,
+    /// A castling right is given but there is no king on the back rank or no rook on that side of it.
+    ImpossibleCastling(char),
+    /// The en passant square does not lie behind a pawn that just moved two steps.
+    ImpossibleEnPassant(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::Malformed(message) => write!(f, "not a valid FEN, {}", message),
+            FenError::KingCount(white, black) => write!(
+                f,
+                "wrong number of kings, white has {} and black has {}",
+                white, black
+            ),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on the back rank at {}", square),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            FenError::ImpossibleCastling(letter) => write!(
+                f,
+                "castling right {} needs the king on the back rank and a rook on that side of it",
+                letter
+            ),
+            FenError::ImpossibleEnPassant(square) => write!(
+                f,
+                "no pawn can be taken en passant on {}",
+                square
+            ),
+        }
+    }
+}
+
+impl Game {
+    /// Sets up a game from a position in Forsyth-Edwards Notation, e.g. the starting position is
+    /// "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".
+    /// The move counters may be left out. The position has to be one that can be played from:
+    /// one king each, no pawns on the back ranks and the side not to move not in check.
+    ///
+    /// Castling rights may also be written as X-FEN or Shredder-FEN for Chess960: KQkq stand for the outermost
+    /// rook on that side of the king, a file letter such as "Bg" for the rook on that file. The game follows the
+    /// Chess960 rules when a file letter is used or a right does not belong to a king on e1 or e8 and a rook in the corner.
+    pub fn from_fen(_fen: &str) -> Result<Game, FenError> {
+        Game::from_variant_fen(Variant::Standard, _fen)
+    }
+
+    /// Sets up a game of a variant from a position in Forsyth-Edwards Notation, see `from_fen`.
+    /// Three-check positions may give the checks each side has left after the en passant square, like "3+3".
+    /// Antichess positions may have any number of kings, and either side may stand in check.
+    /// Horde positions have no white king, and white pawns may stand on the first rank.
+    /// Crazyhouse positions give the pockets in brackets after the board, like "[Qn]" for a white queen and a black knight,
+    /// or as a ninth rank. A '~' after a piece marks it as a promoted pawn.
+    pub fn from_variant_fen(_variant: Variant, _fen: &str) -> Result<Game, FenError> {
+        let mut fields: Vec<&str> = _fen.split_whitespace().collect();
+        let mut checks = [0; 2];
+        if (_variant == Variant::ThreeCheck) && (fields.len() % 2 == 1) && (fields.len() > 4) {
+            let left = fields.remove(4);
+            let (white, black) = left
+                .split_once('+')
+                .and_then(|(white, black)| Some((white.parse::<u32>().ok()?, black.parse::<u32>().ok()?)))
+                .filter(|(white, black)| (*white <= CHECKS_TO_WIN) & (*black <= CHECKS_TO_WIN))
+                .ok_or_else(|| FenError::Malformed(format!("'{}' are not the checks left like 3+3", left)))?;
+            checks = [CHECKS_TO_WIN - white, CHECKS_TO_WIN - black];
+        }
+        if !(fields.len() == 4 || fields.len() == 6) {
+            return Err(FenError::Malformed(format!(
+                "expected 4 or 6 fields but found {}",
+                fields.len()
+            )));
+        }
+
+        let mut game = Game::new();
+        game.variant = _variant;
+        game.checks = checks;
+        game.pieces = [0; 6];
+        game.colour_of_piece = [0; 2];
+
+        let mut placement = fields[0];
+        if _variant == Variant::Crazyhouse {
+            let pocket = if let Some((board, pocket)) = placement.strip_suffix(']').and_then(|text| text.split_once('[')) {
+                placement = board;
+                pocket
+            } else if placement.split('/').count() == 9 {
+                let (board, pocket) = placement.rsplit_once('/').unwrap();
+                placement = board;
+                pocket
+            } else {
+                ""
+            };
+            for c in pocket.chars().filter(|c| *c != '-') {
+                let index_of_piece = (0..POCKET_KINDS)
+                    .find(|index| piece_char(*index, false) == c.to_ascii_lowercase())
+                    .ok_or_else(|| FenError::Malformed(format!("'{}' can not be held in a pocket", c)))?;
+                game.pockets[if c.is_uppercase() { 0 } else { 1 }][index_of_piece] += 1;
+            }
+        }
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::Malformed("the board needs 8 ranks".to_string()));
+        }
+        for (row, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - row as u32;
+            let mut file = 0;
+            let mut after_piece = false;
+            for c in rank_text.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty;
+                    after_piece = false;
+                    continue;
+                }
+                if (c == '~') & (_variant == Variant::Crazyhouse) {
+                    // only a piece just placed on the board can be a promoted pawn
+                    if !after_piece {
+                        return Err(FenError::Malformed(format!("'~' on rank {} does not follow a piece", rank + 1)));
+                    }
+                    game.promoted |= 2_u64.pow((file - 1) * 8 + rank);
+                    after_piece = false;
+                    continue;
+                }
+                let index_of_piece = (0..6)
+                    .find(|index| piece_char(*index, false) == c.to_ascii_lowercase())
+                    .ok_or_else(|| FenError::Malformed(format!("unknown piece '{}'", c)))?;
+                if file >= 8 {
+                    return Err(FenError::Malformed(format!("rank {} has more than 8 squares", rank + 1)));
+                }
+                let bit_pos = 2_u64.pow(file * 8 + rank);
+                game.pieces[index_of_piece] |= bit_pos;
+                game.colour_of_piece[if c.is_uppercase() { 0 } else { 1 }] |= bit_pos;
+                file += 1;
+                after_piece = true;
+            }
+            if file != 8 {
+                return Err(FenError::Malformed(format!("rank {} does not have 8 squares", rank + 1)));
+            }
+        }
+
+        game.white_turn = match fields[1] {
+            "w" => true,
+            "b" => false,
+            other => {
+                return Err(FenError::Malformed(format!(
+                    "side to move must be w or b, not '{}'",
+                    other
+                )))
+            }
+        };
+
+        game.castling = [false; 4];
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let (right, rook_file) = game.read_castling_right(c)?;
+                game.castling[right] = true;
+                game.castling_rooks[right] = rook_file;
+                if !CASTLING_LETTERS.contains(&c) {
+                    game.chess960 = true;
+                }
+            }
+        }
+        let standard_squares = (0..4).all(|right| {
+            let king_index = if right < 2 { 32 } else { 39 };
+            !game.castling[right]
+                | ((game.castling_rooks[right] == [7, 0][right % 2])
+                    & (game.pieces[5] & game.colour_of_piece[right / 2] & 2_u64.pow(king_index) != 0))
+        });
+        game.chess960 |= !standard_squares;
+
+        game.en_passant_at = 16;
+        if fields[3] != "-" {
+            let square = fields[3].to_uppercase();
+            let valid_name = square.len() == 2
+                && ('A'..='H').contains(&square.chars().next().unwrap())
+                && ('1'..='8').contains(&square.chars().nth(1).unwrap());
+            if !valid_name {
+                return Err(FenError::Malformed(format!("'{}' is not a square", fields[3])));
+            }
+            let (file, rank) = game.transform_input(&square);
+            game.en_passant_at = match (rank, game.white_turn) {
+                (5, true) => file as u8 + 8,
+                (2, false) => file as u8,
+                _ => return Err(FenError::ImpossibleEnPassant(fields[3].to_string())),
+            };
+        }
+
+        if fields.len() == 6 {
+            game.halfmove_clock = fields[4]
+                .parse()
+                .map_err(|_| FenError::Malformed(format!("'{}' is not a move count", fields[4])))?;
+            game.fullmove_number = fields[5]
+                .parse()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| FenError::Malformed(format!("'{}' is not a move number", fields[5])))?;
+        }
+
+        game.check_position()?;
+        game.update_state();
+        Ok(game)
+    }
+
+    /// Returns the position in Forsyth-Edwards Notation, see `from_fen`.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for (row, rank_text) in self.get_board().split('\n').enumerate() {
+            if row > 0 {
+                placement.push('/');
+            }
+            let mut empty = 0;
+            for (file, c) in rank_text.chars().enumerate() {
+                if c == '*' {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    placement += &empty.to_string();
+                    empty = 0;
+                }
+                placement.push(c);
+                if self.promoted & 2_u64.pow(file as u32 * 8 + 7 - row as u32) != 0 {
+                    placement.push('~');
+                }
+            }
+            if empty > 0 {
+                placement += &empty.to_string();
+            }
+        }
+        // crazyhouse pockets follow in brackets, white's pieces first and the strongest first
+        if self.variant == Variant::Crazyhouse {
+            placement.push('[');
+            for (colour, is_white) in [(0, true), (1, false)] {
+                for index_of_piece in [1, 2, 3, 4, 0] {
+                    for _ in 0..self.pockets[colour][index_of_piece] {
+                        placement.push(piece_char(index_of_piece, is_white));
+                    }
+                }
+            }
+            placement.push(']');
+        }
+
+        // Chess960 games name the rook's file, so reading the FEN back keeps the rules
+        let castling: String = (0..4)
+            .filter(|right| self.castling[*right])
+            .map(|right| match (self.chess960, right < 2) {
+                (false, _) => CASTLING_LETTERS[right],
+                (true, true) => (b'A' + self.castling_rooks[right] as u8) as char,
+                (true, false) => (b'a' + self.castling_rooks[right] as u8) as char,
+            })
+            .collect();
+
+        let mut en_passant = if self.en_passant_at < 16 {
+            let file = (b'a' + self.en_passant_at % 8) as char;
+            format!("{}{}", file, if self.en_passant_at < 8 { 3 } else { 6 })
+        } else {
+            "-".to_string()
+        };
+
+        // three-check writes the checks each side has left after the en passant square
+        if self.variant == Variant::ThreeCheck {
+            en_passant += &format!(
+                " {}+{}",
+                CHECKS_TO_WIN.saturating_sub(self.checks[0]),
+                CHECKS_TO_WIN.saturating_sub(self.checks[1])
+            );
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            if self.white_turn { "w" } else { "b" },
+            if castling.is_empty() { "-".to_string() } else { castling },
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// Reads one castling letter, X-FEN or Shredder-FEN, into the right it gives and the file of its rook.
+    fn read_castling_right(&self, letter: char) -> Result<(usize, u32), FenError> {
+        let colour = if letter.is_ascii_uppercase() { 0 } else { 1 };
+        let rank = if colour == 0 { 0 } else { 7 };
+        let own = |index_of_piece: usize, file: &u32| {
+            self.pieces[index_of_piece] & self.colour_of_piece[colour] & 2_u64.pow(file * 8 + rank) != 0
+        };
+        let king_file = (0..8)
+            .find(|file| own(5, file))
+            .ok_or(FenError::ImpossibleCastling(letter))?;
+
+        let (king_side, rook_file) = match letter.to_ascii_lowercase() {
+            'k' => (true, (king_file + 1..8).filter(|file| own(2, file)).max()),
+            'q' => (false, (0..king_file).filter(|file| own(2, file)).min()),
+            file @ 'a'..='h' => {
+                let file = (file as u8 - b'a') as u32;
+                (file > king_file, Some(file).filter(|file| own(2, file)))
+            }
+            _ => return Err(FenError::Malformed(format!("unknown castling right '{}'", letter))),
+        };
+        let rook_file = rook_file.ok_or(FenError::ImpossibleCastling(letter))?;
+        Ok((colour * 2 + if king_side { 0 } else { 1 }, rook_file))
+    }
+
+    /// Checks that a set up position can be played from.
+    fn check_position(&self) -> Result<(), FenError> {
+        let kings = |colour: usize| (self.pieces[5] & self.colour_of_piece[colour]).count_ones();
+        if self.variant.kings().is_some_and(|needed| needed != [kings(0), kings(1)]) {
+            return Err(FenError::KingCount(kings(0), kings(1)));
+        }
+
+        for file in 0..8 {
+            for rank in [0, 7] {
+                // the horde starts with pawns on the first rank
+                let horde = (self.variant == Variant::Horde) & (rank == 0);
+                let pawns = if horde { self.pieces[0] & self.colour_of_piece[1] } else { self.pieces[0] };
+                if pawns & 2_u64.pow(file * 8 + rank) != 0 {
+                    return Err(FenError::PawnOnBackRank(self.transform_back(file, rank).to_lowercase()));
+                }
+            }
+        }
+
+        if self.variant.has_check() && self.king_in_check(!self.white_turn) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        if self.en_passant_at < 16 {
+            // the pawn that moved two steps stands in front of the square, seen from the side that moved it
+            let file = (self.en_passant_at % 8) as u32;
+            let (pawn_rank, colour) = if self.en_passant_at < 8 { (3, 0) } else { (4, 1) };
+            if self.pieces[0] & self.colour_of_piece[colour] & 2_u64.pow(file * 8 + pawn_rank) == 0 {
+                let rank = if self.en_passant_at < 8 { 3 } else { 6 };
+                return Err(FenError::ImpossibleEnPassant(format!("{}{}", (b'a' + file as u8) as char, rank)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FenError, Game};
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn start_position_round_trip() {
+        assert_eq!(Game::new().to_fen(), START);
+        let game = Game::from_fen(START).unwrap();
+        assert_eq!(game.get_board(), Game::new().get_board());
+        assert_eq!(game.to_fen(), START);
+    }
+
+    #[test]
+    fn counters_and_en_passant_follow_the_game() {
+        let mut game = Game::new();
+        assert!(game.make_move("E2", "E4"));
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert!(game.make_move("G8", "F6"));
+        assert!(game.make_move("E1", "E2"));
+        assert_eq!(game.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2");
+
+        let copy = Game::from_fen(&game.to_fen()).unwrap();
+        assert_eq!(copy.to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn en_passant_square_can_be_played() {
+        let mut game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(game.make_move("E5", "D6"));
+        assert_eq!(game.get_captured(), &['p']);
+    }
+
+    #[test]
+    fn rejects_unplayable_positions() {
+        assert!(matches!(Game::from_fen("8/8/8/8/8/8/8/8 w - -"), Err(FenError::KingCount(0, 0))));
+        assert!(matches!(Game::from_fen("4k3/8/8/8/8/8/8/4KK2 w - -"), Err(FenError::KingCount(2, 1))));
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/P3K3 w - -").err(),
+            Some(FenError::PawnOnBackRank("a1".to_string()))
+        );
+        // black is in check from the rook but it is white to move
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4RK2 w - -").err(), Some(FenError::OpponentInCheck));
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 w K -").err(),
+            Some(FenError::ImpossibleCastling('K'))
+        );
+        assert!(matches!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - e6"), Err(FenError::ImpossibleEnPassant(_))));
+        assert!(matches!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 x - -"), Err(FenError::Malformed(_))));
+        assert!(matches!(Game::from_fen("4k3/8/8/8/8/8/4K3 w - -"), Err(FenError::Malformed(_))));
+        assert!(matches!(Game::from_fen("4k3/8/8/8/8/8/8/4K4 w - -"), Err(FenError::Malformed(_))));
+        assert!(matches!(Game::from_fen("4k3/8/8/8/8/8/8/4K2PP w - -"), Err(FenError::Malformed(_))));
+    }
+
+    #[test]
+    fn castling() {
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut king_moves = game.get_possible_moves("E1").unwrap();
+        king_moves.sort();
+        assert_eq!(king_moves, vec!["C1", "D1", "D2", "E2", "F1", "F2", "G1"]);
+
+        assert!(game.make_move("E1", "G1"));
+        assert_eq!(game.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        assert!(game.make_move("E8", "C8"));
+        assert_eq!(game.to_fen(), "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2");
+
+        // moving a rook gives up castling on its side only
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(game.make_move("A1", "A2"));
+        assert_eq!(game.to_fen(), "r3k2r/8/8/8/8/8/R7/4K2R b Kkq - 1 1");
+
+        // no castling through an attacked square or out of check
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/5r2/R3K2R w KQ - 0 1").unwrap();
+        let king_moves = game.get_possible_moves("E1").unwrap();
+        assert!(!king_moves.contains(&"G1".to_string()));
+        assert!(king_moves.contains(&"C1".to_string()));
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1").unwrap();
+        let king_moves = game.get_possible_moves("E1").unwrap();
+        assert!(!king_moves.contains(&"G1".to_string()) && !king_moves.contains(&"C1".to_string()));
+    }
+}
@@ -0,0 +1,201 @@
+use chess_gui::Game;
+
+use std::io::{self, BufRead, Write};
+
+/// How a headless game ended, each with its own exit status so scripts can tell them apart.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Outcome
+{
+    /// The moves ran out before the game was decided.
+    Unfinished,
+    WhiteWins,
+    BlackWins,
+    /// Stalemate, too little material to mate, the third repetition of a position or the fifty-move rule.
+    /// Draws a player could claim end the run at once, there is nobody to ask.
+    Draw,
+    /// A line could not be read as a legal move, nothing after it was played.
+    BadMove,
+}
+
+impl Outcome
+{
+    pub fn exit_code(self) -> i32
+    {
+        match self
+        {
+            Outcome::Unfinished => 0,
+            Outcome::WhiteWins => 1,
+            Outcome::BlackWins => 2,
+            Outcome::Draw => 3,
+            Outcome::BadMove => 4,
+        }
+    }
+
+    /// The result as written at the end of a PGN game.
+    fn result(self) -> &'static str
+    {
+        match self
+        {
+            Outcome::WhiteWins => "1-0",
+            Outcome::BlackWins => "0-1",
+            Outcome::Draw => "1/2-1/2",
+            Outcome::Unfinished | Outcome::BadMove => "*",
+        }
+    }
+}
+
+/// What is printed after every move.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Output
+{
+    /// The board as returned by `Game::get_board`, followed by an empty line.
+    Board,
+    /// One FEN line per move.
+    Fen,
+}
+
+/// The outcome if the game is over, None while it goes on. `positions` holds every position of the run so far,
+/// the current one last, to count repetitions.
+fn finished(game: &Game, positions: &[Game]) -> Option<Outcome>
+{
+    if let Some(ending) = game.get_ending()
+    {
+        return Some(match ending.winner()
+        {
+            Some(true) => Outcome::WhiteWins,
+            Some(false) => Outcome::BlackWins,
+            None => Outcome::Draw,
+        });
+    }
+
+    let repetitions = positions.iter().filter(|position| position.is_same_position(game)).count();
+    let drawn = game.is_stalemate() || game.has_insufficient_material() || repetitions >= 3 || game.can_claim_fifty_moves();
+    if drawn { Some(Outcome::Draw) } else { None }
+}
+
+/// Plays the moves read from `input`, one per line in any notation the move box accepts,
+/// and prints the position after each of them. Empty lines and lines starting with '#' are skipped.
+/// Stops at the first move that can not be played or once the game is over, then prints the result.
+pub fn run(mut game: Game, input: impl BufRead, output: &mut impl Write, format: Output) -> io::Result<Outcome>
+{
+    let mut positions = vec![game.clone()];
+    let mut outcome = finished(&game, &positions).unwrap_or(Outcome::Unfinished);
+
+    for (number, line) in input.lines().enumerate()
+    {
+        if outcome != Outcome::Unfinished { break; }
+
+        let line = line?;
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') { continue; }
+
+        let parsed = match game.parse_move(text)
+        {
+            Ok(parsed) => parsed,
+            Err(error) =>
+            {
+                eprintln!("line {}: {}: {}", number + 1, text, error);
+                outcome = Outcome::BadMove;
+                break;
+            }
+        };
+
+        game.set_promotion(&parsed.promotion.map_or("Q".to_string(), |piece| piece.to_string()));
+        game.make_move(&parsed.from, &parsed.to);
+
+        match format
+        {
+            Output::Board => writeln!(output, "{}\n", game.get_board())?,
+            Output::Fen => writeln!(output, "{}", game.to_fen())?,
+        }
+
+        positions.push(game.clone());
+        outcome = finished(&game, &positions).unwrap_or(Outcome::Unfinished);
+    }
+
+    writeln!(output, "{}", outcome.result())?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{run, Outcome, Output};
+    use chess_gui::{Game, Variant};
+
+    fn play(moves: &str, format: Output) -> (Outcome, String)
+    {
+        play_from(Game::new(), moves, format)
+    }
+
+    fn play_from(game: Game, moves: &str, format: Output) -> (Outcome, String)
+    {
+        let mut output = vec![];
+        let outcome = run(game, moves.as_bytes(), &mut output, format).unwrap();
+        (outcome, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn prints_each_position()
+    {
+        let (outcome, output) = play("e4\n\n# a comment\ne7e5\n", Output::Fen);
+        assert_eq!(outcome, Outcome::Unfinished);
+        assert_eq!
+        (
+            output,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\n\
+             rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2\n\
+             *\n"
+        );
+
+        let (_, output) = play("Nf3\n", Output::Board);
+        assert!(output.starts_with("rnbqkbnr\npppppppp\n********\n********\n********\n*****N**\nPPPPPPPP\nRNBQKB*R\n\n"));
+    }
+
+    #[test]
+    fn reports_the_result()
+    {
+        let (outcome, output) = play("f3\ne5\ng4\nQh4\na3\n", Output::Fen);
+        assert_eq!(outcome, Outcome::BlackWins);
+        // nothing is played after the mate
+        assert_eq!(output.lines().count(), 5);
+        assert!(output.ends_with("0-1\n"));
+
+        let (outcome, _) = play("e4\ne5\nKe3\n", Output::Fen);
+        assert_eq!(outcome, Outcome::BadMove);
+    }
+
+    #[test]
+    fn ends_at_every_kind_of_draw()
+    {
+        let from = |fen: &str| Game::from_fen(fen).unwrap();
+
+        // Qc7 stalemates the king in the corner
+        let (outcome, output) = play_from(from("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1"), "Qc7\nKa7\n", Output::Fen);
+        assert_eq!(outcome, Outcome::Draw);
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.ends_with("1/2-1/2\n"));
+
+        // taking the last pawn leaves king and knight against king
+        let (outcome, _) = play_from(from("4k3/8/8/8/8/8/3p4/4KN2 w - - 0 1"), "Kxd2\n", Output::Fen);
+        assert_eq!(outcome, Outcome::Draw);
+
+        // the knights come back home twice, the start position is on the board for the third time
+        let (outcome, output) = play("Nf3\nNf6\nNg1\nNg8\nNf3\nNf6\nNg1\nNg8\ne4\n", Output::Fen);
+        assert_eq!(outcome, Outcome::Draw);
+        assert_eq!(output.lines().count(), 9);
+
+        let (outcome, _) = play_from(from("4k3/8/8/8/8/8/8/R3K3 w - - 99 80"), "Ra2\n", Output::Fen);
+        assert_eq!(outcome, Outcome::Draw);
+    }
+
+    #[test]
+    fn having_no_move_wins_in_antichess()
+    {
+        let game = Game::from_variant_fen(Variant::Antichess, "8/8/8/8/8/p7/P7/7R w - - 0 1").unwrap();
+        let (outcome, output) = play_from(game, "Rh2\n", Output::Fen);
+        // after Rh2 black's pawn is stuck, and black wins by having no move
+        assert_eq!(outcome, Outcome::BlackWins);
+        assert!(output.ends_with("0-1\n"));
+    }
+}
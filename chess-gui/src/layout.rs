@@ -0,0 +1,146 @@
+use ggez::graphics;
+
+/// A chess board is 8x8 tiles, with a margin of one tile around it.
+pub const GRID_SIZE: i16 = 10;
+/// Width of the side panel next to the board, in tiles.
+pub const PANEL_SIZE: i16 = 4;
+/// Height of the captured piece trays at both ends of the panel, in tiles.
+const TRAY_HEIGHT: f32 = 1.0;
+/// Height of the row of game actions below the move list, in tiles.
+const ACTIONS_HEIGHT: f32 = 0.45;
+/// Height of the move entry box and its message line below the actions, in tiles.
+const INPUT_HEIGHT: f32 = 0.9;
+/// Tile size the sprite scale and font sizes were designed for.
+pub const GRID_CELL_SIZE: (i16, i16) = (32 * 4, 32 * 4);
+
+/// Tile size the window opens with, small enough to fit on laptop screens.
+const DEFAULT_CELL_SIZE: f32 = 80.0;
+/// Smallest tile size that keeps pieces and labels readable.
+const MIN_CELL_SIZE: f32 = 40.0;
+
+/// Size of the application window when it opens.
+pub const SCREEN_SIZE: (f32, f32) = ((GRID_SIZE + PANEL_SIZE) as f32 * DEFAULT_CELL_SIZE, GRID_SIZE as f32 * DEFAULT_CELL_SIZE);
+/// The window can not be resized below this.
+pub const MIN_SCREEN_SIZE: (f32, f32) = ((GRID_SIZE + PANEL_SIZE) as f32 * MIN_CELL_SIZE, GRID_SIZE as f32 * MIN_CELL_SIZE);
+
+/// Where the board grid sits in the window, recomputed whenever the window is resized.
+/// Cells are addressed as (column, row) from the top left corner of the board,
+/// the margin is the cell just outside, i.e. -1 and 8. The side panel is to the right of the grid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Layout
+{
+    /// Side of one tile in window pixels.
+    pub cell: f32,
+    /// Top left corner of the grid, grid and panel are centered so a wider or taller window gets equal bars on both sides.
+    pub origin: (f32, f32),
+    /// Size of the whole window.
+    pub screen: (f32, f32),
+}
+
+impl Layout
+{
+    /// Fits the largest whole-pixel grid and its side panel into a window of the given size.
+    pub fn new(width: f32, height: f32) -> Layout
+    {
+        let columns = (GRID_SIZE + PANEL_SIZE) as f32;
+        // whole pixels keep the nearest filtered pixel art crisp
+        let cell = (width / columns).min(height / GRID_SIZE as f32).floor().max(1.0);
+
+        Layout
+        {
+            cell,
+            origin: (((width - cell * columns) / 2.0).floor(), ((height - cell * GRID_SIZE as f32) / 2.0).floor()),
+            screen: (width, height),
+        }
+    }
+
+    /// How many times larger everything is drawn than at the design tile size.
+    pub fn scale(&self) -> f32
+    {
+        self.cell / GRID_CELL_SIZE.0 as f32
+    }
+
+    /// Font size for text designed to be `size` pixels at the design tile size.
+    pub fn text_scale(&self, size: f32) -> graphics::PxScale
+    {
+        graphics::PxScale { x: size * self.scale(), y: size * self.scale() }
+    }
+
+    /// Window position of the top left corner of a cell, fractional cells are allowed.
+    pub fn cell_position(&self, cell: (f32, f32)) -> (f32, f32)
+    {
+        (
+            self.origin.0 + (cell.0 + 1.0) * self.cell,
+            self.origin.1 + (cell.1 + 1.0) * self.cell,
+        )
+    }
+
+    /// The (fractional) cell whose top left corner is at a window position.
+    pub fn position_to_cell(&self, x: f32, y: f32) -> (f32, f32)
+    {
+        (
+            (x - self.origin.0) / self.cell - 1.0,
+            (y - self.origin.1) / self.cell - 1.0,
+        )
+    }
+
+    /// Board cell under a window position, if it lies on the board.
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(u32, u32)>
+    {
+        let any_cell = self.position_to_cell(x, y);
+        if any_cell.0 < 0.0 || any_cell.0 >= 8.0 || any_cell.1 < 0.0 || any_cell.1 >= 8.0 { return None; }
+
+        Some((any_cell.0 as u32, any_cell.1 as u32))
+    }
+
+    /// Horizontal center of the board in window pixels.
+    pub fn board_center_x(&self) -> f32
+    {
+        self.origin.0 + GRID_SIZE as f32 * self.cell / 2.0
+    }
+
+    /// Window rectangle of the side panel, level with the board.
+    pub fn panel_rect(&self) -> graphics::Rect
+    {
+        let top_left = self.cell_position((9.0, 0.0));
+        graphics::Rect::new(top_left.0, top_left.1, (PANEL_SIZE - 1) as f32 * self.cell, 8.0 * self.cell)
+    }
+
+    /// Part of the panel showing what the player at the top or bottom of the board has captured.
+    pub fn tray_rect(&self, top: bool) -> graphics::Rect
+    {
+        let panel = self.panel_rect();
+        let height = TRAY_HEIGHT * self.cell;
+        let y = if top { panel.y } else { panel.y + panel.h - height };
+        graphics::Rect::new(panel.x, y, panel.w, height)
+    }
+
+    /// Part of the panel between the two trays, holding the move list.
+    pub fn move_list_rect(&self) -> graphics::Rect
+    {
+        let panel = self.panel_rect();
+        let tray = TRAY_HEIGHT * self.cell;
+        graphics::Rect::new(panel.x, panel.y + tray, panel.w, panel.h - 2.0 * tray - (ACTIONS_HEIGHT + INPUT_HEIGHT) * self.cell)
+    }
+
+    /// Part of the panel right below the move list, with buttons to resign and to offer or claim a draw.
+    pub fn actions_rect(&self) -> graphics::Rect
+    {
+        let list = self.move_list_rect();
+        graphics::Rect::new(list.x, list.y + list.h, list.w, ACTIONS_HEIGHT * self.cell)
+    }
+
+    /// Part of the panel between the actions and the bottom tray, where moves are typed.
+    pub fn input_rect(&self) -> graphics::Rect
+    {
+        let actions = self.actions_rect();
+        graphics::Rect::new(actions.x, actions.y + actions.h, actions.w, INPUT_HEIGHT * self.cell)
+    }
+
+    /// Window rectangle covered by a board cell.
+    pub fn cell_rect(&self, cell: (u32, u32)) -> graphics::Rect
+    {
+        let position = self.cell_position((cell.0 as f32, cell.1 as f32));
+        graphics::Rect::new(position.0, position.1, self.cell, self.cell)
+    }
+}
@@ -1,12 +1,46 @@
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::collapsible_if,
+    clippy::collapsible_match,
+    clippy::len_zero,
+    clippy::manual_range_contains,
+    clippy::needless_return,
+    clippy::single_match,
+    reason = "the engine keeps the style of the jblomlof-chess crate it was imported from"
+)]
+
 use std::{fmt, vec};
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+mod atomic;
+mod chess960;
+mod crazyhouse;
+mod ending;
+mod engine;
+mod fen;
+mod notation;
+mod pgn;
+#[cfg(feature = "serde")]
+mod schema;
+mod variant;
+
+pub use chess960::{CHESS960_POSITIONS, CHESS960_STANDARD};
+pub use crazyhouse::POCKET_KINDS;
+pub use ending::Ending;
+pub use fen::FenError;
+pub use notation::{MoveInputError, ParsedMove};
+pub use pgn::{Pgn, PgnError};
+#[cfg(feature = "serde")]
+pub use schema::{Castling, Colour, GameData, SCHEMA_VERSION};
+pub use variant::{Variant, CHECKS_TO_WIN, VARIANTS};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum GameState {
     InProgress,
     Check,
     GameOver,
 }
+#[derive(Copy, Clone, PartialEq)]
 enum Piece {
     Pawn = 0,
     Queen = 1,
@@ -20,6 +54,12 @@ enum Piece {
  * - Document well!
  * - Write well and clean code!
  */
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "GameData", try_from = "GameData")
+)]
 pub struct Game {
     /* save board, active colour, ... */
 
@@ -34,6 +74,23 @@ pub struct Game {
                         //I can be smart. I know enpassants are only possible on rank-3(y==2) and rank-6(y==5) thus i can save on memory
                         //and only use 0-15 as squares and 16+ as not possible.
                         //this one will count the opposite way, eg 3 will indicate D3 and 11 will indicate D6
+    captured: Vec<char>, // every piece taken so far in order, written like in get_board
+    castling: [bool; 4], // white king side, white queen side, black king side, black queen side
+    castling_rooks: [u32; 4], // the file each castling right's rook starts on, ordered like castling
+    chess960: bool, // castling is written as the king moving onto its rook, see search_castling_moves
+    halfmove_clock: u32, // moves since the last capture or pawn move
+    fullmove_number: u32, // starts at 1 and goes up after every black move
+    variant: Variant, // the rules the game is played by, see variant.rs
+    checks: [u32; 2], // checks given by white and black, only counted in three-check
+    ending: Option<Ending>, // set by update_state once checkmate or the variant's own rules decide the game
+    pockets: [[u32; 5]; 2], // pieces white and black hold to drop in crazyhouse, indexed like pieces without the king
+    promoted: u64, // squares of pieces that were pawns, only kept in crazyhouse where they go back into a pocket as pawns
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
 }
 
 impl Game {
@@ -43,8 +100,19 @@ impl Game {
             /* initialise board, set active colour to white, ... */
             state: GameState::InProgress,
             white_turn: true,
-            en_passant_at: 0,
+            en_passant_at: 16, // no pawn has moved yet
             to_promote_to: 1,
+            captured: vec![],
+            castling: [true; 4],
+            castling_rooks: [7, 0, 7, 0],
+            chess960: false,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            variant: Variant::Standard,
+            checks: [0; 2],
+            ending: None,
+            pockets: [[0; 5]; 2],
+            promoted: 0,
             colour_of_piece: [
                 {
                     let mut white_map: u64 = 0;
@@ -158,6 +226,29 @@ impl Game {
         board_state
     }
 
+    /// Returns the pieces taken so far in the order they were taken.
+    /// They are written like in get_board, so uppercase characters are white pieces black has taken.
+    /// A promoted pawn that is taken counts as the piece it became.
+    pub fn get_captured(&self) -> &[char] {
+        &self.captured
+    }
+
+    /// Returns the material on the board in pawns, positive if white is ahead.
+    /// Pawns count 1, knights and bishops 3, rooks 5 and queens 9.
+    pub fn material_difference(&self) -> i32 {
+        self.get_board()
+            .chars()
+            .map(|c| {
+                let value = piece_value(c);
+                if c.is_uppercase() {
+                    value
+                } else {
+                    -value
+                }
+            })
+            .sum()
+    }
+
     //Returns true if it is white to move. Returns false otherwise meaning its black to move
     pub fn is_white_turn(&self) -> bool {
         self.white_turn
@@ -165,9 +256,13 @@ impl Game {
 
     /// If the move is legal it will make the move.
     /// Inputs are the positions, first arg is from square and second arg is to square.
+    /// In Crazyhouse a piece is dropped from the pocket by giving its letter and '@' as from square, e.g. "N@".
     /// Return true if the move was made.
     /// Else returns false, thus meaning the move was invalid.
     pub fn make_move(&mut self, _from: &str, _to: &str) -> bool {
+        if crazyhouse::dropped_piece(_from).is_some() {
+            return self.make_drop(_from, _to);
+        }
         if self
             .get_possible_moves(_from)
             .unwrap()
@@ -183,25 +278,32 @@ impl Game {
                 return false;
             }
             let (to_file, to_rank) = self.transform_input(_to);
+            // castling onto the own rook is no capture
+            let resets_clock = (self.pieces[0] & 2_u64.pow(from_file * 8 + from_rank) != 0)
+                | (self.colour_of_piece[if self.white_turn { 1 } else { 0 }] & 2_u64.pow(to_file * 8 + to_rank) != 0);
+            let promotes = (self.pieces[0] & 2_u64.pow(from_file * 8 + from_rank) != 0) & ((to_rank == 0) | (to_rank == 7));
+            let captured_before = self.captured.len();
+            self.update_castling_rights(from_file * 8 + from_rank, to_file * 8 + to_rank);
             self.do_move(from_file, from_rank, to_file, to_rank);
-
-            if (self.colour_in_check_or_mate(true) == 1)
-                | (self.colour_in_check_or_mate(false) == 1)
-            {
-                self.state = GameState::Check;
-            } else if (self.colour_in_check_or_mate(true) == 2)
-                | (self.colour_in_check_or_mate(false) == 2)
-            {
-                self.state = GameState::GameOver;
-            } else {
-                self.state = GameState::InProgress;
+            self.fill_pocket(from_file * 8 + from_rank, to_file * 8 + to_rank, captured_before, promotes);
+            if self.variant == Variant::Atomic {
+                self.clear_exploded_castling();
+            }
+            if (self.variant == Variant::ThreeCheck) && self.king_in_check(!self.white_turn) {
+                self.checks[if self.white_turn { 0 } else { 1 }] += 1;
             }
+            self.halfmove_clock = if resets_clock { 0 } else { self.halfmove_clock + 1 };
+            if !self.white_turn {
+                self.fullmove_number += 1;
+            }
+
             self.en_passant_at = 16; // 16 means no enpassant
 
             //was it a enpassant move coming?
             let bit_pos = 2_u64.pow(to_file * 8 + to_rank);
             match self.get_that_piece_type(bit_pos) {
-                Piece::Pawn => if from_rank.abs_diff(to_rank) == 2 {
+                // a Horde pawn stepping two squares from the first rank can not be taken en passant
+                Piece::Pawn if (from_rank.abs_diff(to_rank) == 2) & (from_rank != 0) => {
                     if from_rank < to_rank { // this means a white pawn was moving. 
                         self.en_passant_at = from_file as u8;
                     } else {
@@ -210,12 +312,143 @@ impl Game {
                 },
                 _ =>(),
             }
+            // the state depends on the en passant square just set and on who moves next
             self.white_turn = !self.white_turn;
+            self.update_state();
             return true;
         }
         false
     }
 
+    /// Sets the state from whether the variant's rules decided the game or either colour is in check or checkmate.
+    fn update_state(&mut self) {
+        // the variant's rules ask for the possible moves, which are only given while the game is undecided
+        self.ending = None;
+        if let Some(ending) = self.variant_ending() {
+            self.ending = Some(ending);
+            self.state = GameState::GameOver;
+        } else if (self.colour_in_check_or_mate(true) == 1) | (self.colour_in_check_or_mate(false) == 1) {
+            self.state = GameState::Check;
+        } else if (self.colour_in_check_or_mate(true) == 2)
+            | (self.colour_in_check_or_mate(false) == 2)
+        {
+            self.state = GameState::GameOver;
+            self.ending = Some(Ending::Checkmate { white_wins: !self.white_turn });
+        } else {
+            self.state = GameState::InProgress;
+        }
+    }
+
+    /// A king loses both castling rights when it moves, a rook its own when it moves or is taken.
+    /// Called before the move is made, while the moving piece still stands on `from_index`.
+    fn update_castling_rights(&mut self, from_index: u32, to_index: u32) {
+        for right in 0..4 {
+            let king_moves = self.pieces[5] & self.colour_of_piece[right / 2] & 2_u64.pow(from_index) != 0;
+            let rook_index = self.castling_rook_index(right);
+            if king_moves | (from_index == rook_index) | (to_index == rook_index) {
+                self.castling[right] = false;
+            }
+        }
+    }
+
+    /// The square index of the rook a castling right belongs to.
+    fn castling_rook_index(&self, right: usize) -> u32 {
+        self.castling_rooks[right] * 8 + if right < 2 { 0 } else { 7 }
+    }
+
+    /// Returns true if the game is played by the Chess960 rules, where castling is written
+    /// as the king moving onto its own rook since the king may end on or next to its starting square.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Returns which castling rights are left, in the order white king side, white queen side,
+    /// black king side and black queen side. A right only means the king and rook have not moved,
+    /// castling also needs the squares between them free and safe.
+    pub fn get_castling_rights(&self) -> [bool; 4] {
+        self.castling
+    }
+
+    /// Returns the number of moves made since the last capture or pawn move, counting both colours.
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// Returns the number of the move being played, it starts at 1 and goes up after every black move.
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Returns true if both games are in the same position as the repetition rule counts them:
+    /// the same pieces on the same squares, the same side to move and the same castling and en passant rights.
+    /// In Three-check the checks given have to match too, in Crazyhouse the pockets and which pieces were promoted.
+    pub fn is_same_position(&self, other: &Game) -> bool {
+        self.pieces == other.pieces
+            && self.colour_of_piece == other.colour_of_piece
+            && self.white_turn == other.white_turn
+            && self.castling == other.castling
+            && self.castling_rooks == other.castling_rooks
+            && self.checks == other.checks
+            && self.pockets == other.pockets
+            && self.promoted == other.promoted
+            && self.en_passant_capture() == other.en_passant_capture()
+    }
+
+    /// The en passant square as the repetition rule sees it, only while a pawn can really take there.
+    fn en_passant_capture(&self) -> Option<u8> {
+        if self.en_passant_at >= 16 {
+            return None;
+        }
+        let file = (self.en_passant_at % 8) as u32;
+        // the capturing pawn stands next to the one that moved two steps
+        let (pawn_rank, target_rank) = if self.white_turn { (4, 5) } else { (3, 2) };
+        let own = self.colour_of_piece[if self.white_turn { 0 } else { 1 }];
+        let target = self.transform_back(file, target_rank);
+        let mut search = self.clone();
+        [file.wrapping_sub(1), file + 1]
+            .into_iter()
+            .filter(|beside| *beside < 8)
+            .filter(|beside| self.pieces[0] & own & 2_u64.pow(beside * 8 + pawn_rank) != 0)
+            .any(|beside| {
+                search
+                    .get_possible_moves(&self.transform_back(beside, pawn_rank))
+                    .is_some_and(|moves| moves.contains(&target))
+            })
+            .then_some(self.en_passant_at)
+    }
+
+    /// Returns true once a draw can be claimed under the fifty-move rule,
+    /// after fifty moves by each side without a capture or pawn move.
+    pub fn can_claim_fifty_moves(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// Returns true if the side to move has no legal move and the game was not decided otherwise, a draw.
+    /// Where having no move decides the game, like in Antichess, `get_ending` says who won instead.
+    pub fn is_stalemate(&self) -> bool {
+        self.ending.is_none() && self.clone().no_valid_moves_for_colour(self.white_turn)
+    }
+
+    /// Returns true if neither side has the pieces left to checkmate: bare kings, a single bishop or knight,
+    /// or only bishops that all stand on squares of one colour.
+    /// Only standard rules are looked at, in the variants other ways of winning remain.
+    pub fn has_insufficient_material(&self) -> bool {
+        if self.variant != Variant::Standard {
+            return false;
+        }
+        let (pawns, queens, rooks) = (self.pieces[0], self.pieces[1], self.pieces[2]);
+        let (bishops, knights) = (self.pieces[3], self.pieces[4]);
+        if pawns | queens | rooks != 0 {
+            return false;
+        }
+        // a1 is dark, a square is dark if its file and rank add up to an even number
+        let dark_squares = (0..64_u32)
+            .filter(|index| (index / 8 + index % 8) % 2 == 0)
+            .fold(0_u64, |mask, index| mask | 2_u64.pow(index));
+        let one_colour = (bishops & dark_squares == 0) | (bishops & !dark_squares == 0);
+        (bishops | knights).count_ones() <= 1 || (knights == 0 && one_colour)
+    }
+
     /// Set the piece type that a pawn becames following a promotion.
     /// Input should be broadly accepted. For example if you choose knight promotion:
     /// "knight" "Knight" "KNIGHT" "n" "N" are valid.
@@ -245,20 +478,29 @@ impl Game {
     }
 
     /// Get the current game state.
-    /// Values avaiable are inProgress, Check, GameOver(which is checkmate right now)
+    /// Values avaiable are inProgress, Check, GameOver(checkmate or a win by the variant's rules, see get_ending)
     pub fn get_game_state(&self) -> GameState {
         self.state
     }
 
+    /// Returns how the rules of the game decided it, by checkmate or the variant's own win condition.
+    /// None while the game goes on. Resignations, time and draws are left to the players.
+    pub fn get_ending(&self) -> Option<Ending> {
+        self.ending
+    }
+
     /// Returns u32.
     /// Returns 1 if the specified colour is in check. returns 0 if the specified colour is not in check and returns 2 if the specified colour is in checkmate
     /// Input is boolean, true if the desired colour to see if in check is white. False if the desired colour to see if in check is black.
     pub fn colour_in_check_or_mate(&mut self, _is_white: bool) -> u32 {
         // returning 0 if not check nor mate, 1 if check, 2 if mate
-        let (x, y) = self.get_king_pos(_is_white);
-        if self.helper_colour_in_check(_is_white, x, y) {
-            // some sort of check
-            if self.no_valid_moves_for_colour(_is_white) {
+        if !self.variant.has_check() {
+            return 0;
+        }
+        if self.king_in_check(_is_white) {
+            // some sort of check, a game the variant's rules decided leaves no moves without being mate
+            let decided_otherwise = self.ending.is_some_and(|ending| !matches!(ending, Ending::Checkmate { .. }));
+            if !decided_otherwise && self.no_valid_moves_for_colour(_is_white) {
                 return 2;
             }
 
@@ -267,13 +509,47 @@ impl Game {
         return 0;
     }
 
+    /// Returns true if the king of the specified colour is attacked right now.
+    /// Unlike `colour_in_check_or_mate` this does not look for moves, so it is cheap enough to ask every frame.
+    pub fn is_in_check(&self, _is_white: bool) -> bool {
+        self.variant.has_check() && self.king_in_check(_is_white)
+    }
+
+    /// Returns the square of the side to move's king if it is in check or checkmate, e.g. "E1".
+    /// Only the side to move can be in check after a legal move, so this is the king to point out.
+    pub fn checked_king(&self) -> Option<String> {
+        if !self.is_in_check(self.white_turn) {
+            return None;
+        }
+        let (file, rank) = self.get_king_pos(self.white_turn)?;
+        Some(self.transform_back(file, rank))
+    }
+
     /// Returns a option<vector> with all the possible valid moves for that piece on a specific tile.
     /// Return value wrapped in some. If no possible move exist for the piece an empty vector will be returned.
     /// Input is accepted as the square position eg. "A4" would be the square in the A-file at rank-4.
     /// If no piece exist on the input square it returns none.
+    /// In Crazyhouse "N@" gives the squares a knight of the side to move can be dropped on, see `make_move`.
+    ///
+    /// Once the game is decided every piece gets an empty vector.
     ///
     /// (Not done) (en passent done) (optional) Don't forget to include en passent and castling.
     pub fn get_possible_moves(&mut self, _postion: &str) -> Option<Vec<String>> {
+        if crazyhouse::dropped_piece(_postion).is_some() {
+            let drops = self.search_drops(_postion)?;
+            return Some(if self.ending.is_some() { vec![] } else { drops });
+        }
+        let moves = self.search_piece_moves(_postion)?;
+        if self.ending.is_some() {
+            return Some(vec![]);
+        }
+        let (file_coord, rank_coord) = self.transform_input(_postion);
+        let is_white = self.is_white(file_coord, rank_coord);
+        Some(self.forced_captures(is_white, moves, _postion))
+    }
+
+    /// The moves of the piece on a square by how it moves, before any rule of the variant that looks at the other pieces.
+    fn search_piece_moves(&mut self, _postion: &str) -> Option<Vec<String>> {
         let (file_coord, rank_coord): (u32, u32) = self.transform_input(_postion);
         let bit_pos = 2_u64.pow(file_coord * 8 + rank_coord);
         if self.is_black(file_coord, rank_coord) | self.is_white(file_coord, rank_coord) {
@@ -316,30 +592,50 @@ impl Game {
                 }
             }
         }
-        true
+        !self.can_drop(_is_white)
+    }
+
+    /// True if the king of the colour is attacked, see `helper_colour_in_check`.
+    /// A side without a king, like the horde or an Atomic king that was blown up, is never in check.
+    /// Neither is an Atomic king standing next to the other king.
+    fn king_in_check(&self, _is_white: bool) -> bool {
+        if self.variant == Variant::Atomic && self.kings_touch() {
+            return false;
+        }
+        match self.get_king_pos(_is_white) {
+            Some((x, y)) => self.helper_colour_in_check(_is_white, x, y),
+            None => false,
+        }
+    }
+
+    /// True if the colour has a king on the board. Horde is played without a white king,
+    /// and in Atomic a king can be lost by an explosion.
+    fn has_king(&self, _is_white: bool) -> bool {
+        self.pieces[5] & self.colour_of_piece[if _is_white { 0 } else { 1 }] != 0
     }
 
     fn helper_colour_in_check(&self, _is_white: bool, _x: u32, _y: u32) -> bool {
+        // an Atomic king can not capture, it would blow itself up
         self.is_bishop_logic_threat(_is_white, _x, _y)
             | self.is_rook_logic_threat(_is_white, _x, _y)
             | self.is_knight_logic_threat(_is_white, _x, _y)
             | self.is_pawn_logic_threat(_is_white, _x, _y)
-            | self.is_king_logic_threat(_is_white, _x, _y)
+            | ((self.variant != Variant::Atomic) & self.is_king_logic_threat(_is_white, _x, _y))
     }
 
-    fn get_king_pos(&self, _is_white: bool) -> (u32, u32) {
-        let mut king_index: u32 = 0; // will be iterated throu to find the value.
+    /// The file and rank of the colour's king, None if it has none.
+    fn get_king_pos(&self, _is_white: bool) -> Option<(u32, u32)> {
         let mut bit_pos: u64;
-        loop {
+        for king_index in 0..64 {
             bit_pos = 2_u64.pow(king_index);
             if self.pieces[5] & bit_pos == bit_pos {
                 if self.colour_of_piece[if _is_white { 0 } else { 1 }] & bit_pos == bit_pos {
                     //found the correct coloured king
-                    return (king_index / 8, king_index % 8);
+                    return Some((king_index / 8, king_index % 8));
                 }
             }
-            king_index += 1;
         }
+        None
     }
 
     fn is_king_logic_threat(&self, _is_white: bool, x: u32, y: u32) -> bool {
@@ -545,40 +841,20 @@ impl Game {
     }
 
     fn is_pawn_logic_threat(&self, _is_white: bool, x: u32, y: u32) -> bool {
-        let mut _bit_pos: u64;
-        if _is_white {
-            if y > 0 {
-                if x > 0 {
-                    _bit_pos = 2_u64.pow((x - 1) * 8 + y - 1);
-                    if self.pieces[0] & self.colour_of_piece[1] & _bit_pos == _bit_pos {
-                        return true;
-                    }
-                }
-                if x < 7 {
-                    _bit_pos = 2_u64.pow((x + 1) * 8 + y - 1);
-                    if self.pieces[0] & self.colour_of_piece[1] & _bit_pos == _bit_pos {
-                        return true;
-                    }
-                }
-            }
+        // white is attacked by black pawns from the rank above, black by white pawns from the rank below
+        let enemy_pawns = self.pieces[0] & self.colour_of_piece[if _is_white { 1 } else { 0 }];
+        let pawn_rank = if _is_white { y as i32 + 1 } else { y as i32 - 1 };
+        if !(0..8).contains(&pawn_rank) {
             return false;
-        } else {
-            if y < 7 {
-                if x > 0 {
-                    _bit_pos = 2_u64.pow((x - 1) * 8 + y + 1);
-                    if self.pieces[0] & self.colour_of_piece[1] & _bit_pos == _bit_pos {
-                        return true;
-                    }
-                }
-                if x < 7 {
-                    _bit_pos = 2_u64.pow((x + 1) * 8 + y + 1);
-                    if self.pieces[0] & self.colour_of_piece[1] & _bit_pos == _bit_pos {
-                        return true;
-                    }
-                }
+        }
+        for pawn_file in [x as i32 - 1, x as i32 + 1] {
+            if (0..8).contains(&pawn_file)
+                && enemy_pawns & 2_u64.pow(pawn_file as u32 * 8 + pawn_rank as u32) != 0
+            {
+                return true;
             }
-            return false;
         }
+        false
     }
 
     fn is_rook_logic_threat(&self, _is_white: bool, x: u32, y: u32) -> bool {
@@ -651,13 +927,27 @@ impl Game {
         _to_file: u32,
         _to_rank: u32,
     ) -> bool {
+        // without check the king is a piece like any other
+        if !self.variant.has_check() {
+            return false;
+        }
         let _remember_colours = self.colour_of_piece;
         let _remember_pieces = self.pieces;
+        let _remember_captured = self.captured.len();
+        // a side may play without a king, as the horde does
+        let (own_king, other_king) = (self.has_king(_is_white), self.has_king(!_is_white));
         self.do_move(_from_file, _from_rank, _to_file, _to_rank);
-        let (x, y) = self.get_king_pos(_is_white);
-        let will_cause_check = self.helper_colour_in_check(_is_white, x, y);
+        // in Atomic blowing up the own king is never allowed, blowing up the other one wins even out of check
+        let will_cause_check = if own_king && !self.has_king(_is_white) {
+            true
+        } else if other_king && !self.has_king(!_is_white) {
+            false
+        } else {
+            self.king_in_check(_is_white)
+        };
         self.colour_of_piece = _remember_colours;
         self.pieces = _remember_pieces;
+        self.captured.truncate(_remember_captured);
         will_cause_check
     }
 
@@ -665,10 +955,27 @@ impl Game {
         //assuming input is valid
         let _bit_pos_to = 2_u64.pow(_to_file * 8 + _to_rank);
 
+        if let Some(right) = self.castling_right_of_move(_from_file, _from_rank, _to_file, _to_rank) {
+            // the king and rook leave together, so one may land where the other stood
+            let colour = right / 2;
+            let king_from = 2_u64.pow(_from_file * 8 + _from_rank);
+            let rook_from = 2_u64.pow(self.castling_rook_index(right));
+            let (king_file, rook_file) = if right % 2 == 0 { (6, 5) } else { (2, 3) };
+            let king_to = 2_u64.pow(king_file * 8 + _from_rank);
+            let rook_to = 2_u64.pow(rook_file * 8 + _from_rank);
+            self.pieces[5] = self.pieces[5] & !king_from | king_to;
+            self.pieces[2] = self.pieces[2] & !rook_from | rook_to;
+            self.colour_of_piece[colour] = self.colour_of_piece[colour] & !king_from & !rook_from | king_to | rook_to;
+            return;
+        }
+
+        let captured_before = self.captured.len();
+
         //if there is a capture e.g there is a piece on the to square
         // kill it
-        for piece in self.pieces.iter_mut() {
+        for (index_of_piece, piece) in self.pieces.iter_mut().enumerate() {
             if *piece & _bit_pos_to == _bit_pos_to {
+                self.captured.push(piece_char(index_of_piece, self.colour_of_piece[0] & _bit_pos_to == _bit_pos_to));
                 self.colour_of_piece[0] &= !_bit_pos_to;
                 self.colour_of_piece[1] &= !_bit_pos_to;
                 *piece &= !_bit_pos_to;
@@ -681,9 +988,15 @@ impl Game {
            match self.get_that_piece_type(2_u64.pow(_from_file * 8 + _from_rank)) {
                
             Piece::Pawn => if self.is_white(_from_file, _from_rank) { // the attacking piece is white thus kill black
+                if self.pieces[0] & self.colour_of_piece[1] & 2_u64.pow(_to_file * 8 + 4) != 0 {
+                    self.captured.push('p');
+                }
                 self.colour_of_piece[1] &= !2_u64.pow(_to_file * 8 + 4);
                 self.pieces[0] &= !2_u64.pow(_to_file * 8 + 4); // 4 because the pawn always jumps to rank5
              } else { // kill white
+                if self.pieces[0] & self.colour_of_piece[0] & 2_u64.pow(_to_file * 8 + 3) != 0 {
+                    self.captured.push('P');
+                }
                 self.colour_of_piece[0] &= !2_u64.pow(_to_file * 8 + 3);
                 self.pieces[0] &= !2_u64.pow(_to_file * 8 + 3); //3 since white pawns will get enpassanted on rank 4
              },
@@ -693,6 +1006,7 @@ impl Game {
         }
 
         let _bit_pos_from = 2_u64.pow(_from_file * 8 + _from_rank);
+
         for piece in self.pieces.iter_mut() {
             if *piece & _bit_pos_from == _bit_pos_from {
                 if self.colour_of_piece[0] & _bit_pos_from == _bit_pos_from {
@@ -707,6 +1021,11 @@ impl Game {
                 break;
             }
         }
+        // in Atomic the capturing piece goes up with what it took, a promoting pawn included
+        if (self.variant == Variant::Atomic) & (self.captured.len() > captured_before) {
+            self.explode(_to_file, _to_rank);
+        }
+
         //look for pawns promoting
         if (_to_rank == 0) | (_to_rank == 7) {
             if self.pieces[0] & _bit_pos_to == _bit_pos_to {
@@ -747,27 +1066,107 @@ impl Game {
                     let new_rank = _from_rank as i32 + j;
                     if (new_file >= 0) & (new_file < 8) & (new_rank >= 0) & (new_rank < 8) {
                         let _bit_pos = 2_u64.pow(new_file as u32 * 8 + new_rank as u32);
-                        if self.colour_of_piece[if _is_white { 0 } else { 1 }] & _bit_pos
-                            != _bit_pos
-                        {
-                            if !self.would_cause_check(
+                        // an Atomic king can not take, the explosion would reach it
+                        let occupied = if self.variant == Variant::Atomic {
+                            self.colour_of_piece[0] | self.colour_of_piece[1]
+                        } else {
+                            self.colour_of_piece[if _is_white { 0 } else { 1 }]
+                        };
+                        if occupied & _bit_pos != _bit_pos
+                            && !self.would_cause_check(
                                 _is_white,
                                 _from_file,
                                 _from_rank,
                                 new_file as u32,
                                 new_rank as u32,
-                            ) {
-                                king_possible_moves
-                                    .push(self.transform_back(new_file as u32, new_rank as u32));
-                            }
+                            )
+                        {
+                            king_possible_moves
+                                .push(self.transform_back(new_file as u32, new_rank as u32));
                         }
                     }
                 }
             }
         }
+        if self.variant.has_check() {
+            king_possible_moves.append(&mut self.search_castling_moves(_is_white, _from_file, _from_rank));
+        }
         king_possible_moves
     }
 
+    /// Castling is written as the king moving two squares towards the rook, or in Chess960 as the king
+    /// moving onto the rook. Either way the king ends on the g or c file with the rook beside it towards the centre.
+    /// The squares both pieces cross or land on have to be empty apart from the two of them, and the king
+    /// may not start on, cross or land on an attacked square.
+    fn search_castling_moves(&mut self, _is_white: bool, _from_file: u32, _from_rank: u32) -> Vec<String> {
+        let mut castling_moves: Vec<String> = vec![];
+        let colour = if _is_white { 0 } else { 1 };
+        if _from_rank != if _is_white { 0 } else { 7 } {
+            return castling_moves;
+        }
+        let king_bit = 2_u64.pow(_from_file * 8 + _from_rank);
+        let occupied = self.colour_of_piece[0] | self.colour_of_piece[1];
+
+        for right in colour * 2..colour * 2 + 2 {
+            let rook_file = self.castling_rooks[right];
+            let rook_bit = 2_u64.pow(self.castling_rook_index(right));
+            let king_side = right % 2 == 0;
+            if !self.castling[right]
+                | (self.pieces[2] & self.colour_of_piece[colour] & rook_bit == 0)
+                | ((rook_file > _from_file) != king_side)
+            {
+                continue;
+            }
+            let (king_file, rook_to_file) = if king_side { (6, 5) } else { (2, 3) };
+            let files = [_from_file, rook_file, king_file, rook_to_file];
+            let (low, high) = (*files.iter().min().unwrap(), *files.iter().max().unwrap());
+            let path_free = (low..=high)
+                .map(|file| 2_u64.pow(file * 8 + _from_rank))
+                .all(|bit| (bit == king_bit) | (bit == rook_bit) | (occupied & bit == 0));
+            if !path_free {
+                continue;
+            }
+
+            // the king is looked at without the two pieces, they leave their squares together
+            let _remember_colours = self.colour_of_piece;
+            let _remember_pieces = self.pieces;
+            self.colour_of_piece[colour] &= !king_bit & !rook_bit;
+            self.pieces[5] &= !king_bit;
+            self.pieces[2] &= !rook_bit;
+            let safe = (_from_file.min(king_file)..=_from_file.max(king_file)).all(|file| !self.helper_colour_in_check(_is_white, file, _from_rank));
+            self.colour_of_piece = _remember_colours;
+            self.pieces = _remember_pieces;
+
+            if safe {
+                let target = if self.chess960 { rook_file } else { king_file };
+                castling_moves.push(self.transform_back(target, _from_rank));
+            }
+        }
+        castling_moves
+    }
+
+    /// The castling right a king move uses, None if it is no castling move. In Chess960 the king moves
+    /// onto its own rook, otherwise two files towards it. Only asked of legal moves, so the right is not checked.
+    fn castling_right_of_move(&self, _from_file: u32, _from_rank: u32, _to_file: u32, _to_rank: u32) -> Option<usize> {
+        let colour = if self.is_white(_from_file, _from_rank) { 0 } else { 1 };
+        if (self.pieces[5] & self.colour_of_piece[colour] & 2_u64.pow(_from_file * 8 + _from_rank) == 0)
+            | (_to_rank != _from_rank)
+        {
+            return None;
+        }
+        let right = colour * 2 + if _to_file > _from_file { 0 } else { 1 };
+        let castles = if self.chess960 {
+            self.pieces[2] & self.colour_of_piece[colour] & 2_u64.pow(_to_file * 8 + _from_rank) != 0
+        } else {
+            _from_file.abs_diff(_to_file) == 2
+        };
+        if castles {
+            Some(right)
+        } else {
+            None
+        }
+    }
+
     fn search_pawn_moves(
         &mut self,
         _is_white: bool,
@@ -775,7 +1174,7 @@ impl Game {
         _from_rank: u32,
     ) -> Vec<String> {
         let mut pawn_possible_moves: Vec<String> = vec![];
-        let mut new_rank: u32 = if _is_white {
+        let new_rank: u32 = if _is_white {
             _from_rank + 1
         } else {
             _from_rank - 1
@@ -799,11 +1198,10 @@ impl Game {
                             pawn_possible_moves
                                 .push(self.transform_back(new_file as u32, new_rank));
                         }
-                    } else if (self.en_passant_at < 16) & (_bit_pos == _bit_pos & 2_u64.pow((self.en_passant_at as u32 % 8) * 8 + if self.en_passant_at <= 7 {2} else {5})){
-                        if !self.would_cause_check(_is_white, _from_file, _from_rank, new_file as u32, new_rank) {
-                            pawn_possible_moves
-                            .push(self.transform_back(new_file as u32, new_rank));
-                        }
+                    } else if (self.en_passant_at < 16) & ((self.en_passant_at >= 8) == _is_white) & (_bit_pos == _bit_pos & 2_u64.pow((self.en_passant_at as u32 % 8) * 8 + if self.en_passant_at <= 7 {2} else {5}))
+                        && !self.would_cause_check(_is_white, _from_file, _from_rank, new_file as u32, new_rank) {
+                        pawn_possible_moves
+                        .push(self.transform_back(new_file as u32, new_rank));
                     }
                 } else {
                     if (self.colour_of_piece[0] | self.colour_of_piece[1]) & _bit_pos != _bit_pos {
@@ -818,13 +1216,13 @@ impl Game {
                                 .push(self.transform_back(new_file as u32, new_rank));
                         }
                     }
-                    if (if _is_white { 1 } else { 6 }) == _from_rank {
-                        if _is_white {
-                            new_rank += 1
-                        } else {
-                            new_rank -= 1
-                        };
-                        _bit_pos = 2_u64.pow(new_file as u32 * 8 + new_rank);
+                    // the two step move needs both squares in front to be free,
+                    // in Horde the white pawns on the first rank may make it too
+                    if (((if _is_white { 1 } else { 6 }) == _from_rank) | (_is_white & (_from_rank == 0)))
+                        & ((self.colour_of_piece[0] | self.colour_of_piece[1]) & _bit_pos != _bit_pos)
+                    {
+                        let double_rank = if _is_white { new_rank + 1 } else { new_rank - 1 };
+                        _bit_pos = 2_u64.pow(new_file as u32 * 8 + double_rank);
                         if (self.colour_of_piece[0] | self.colour_of_piece[1]) & _bit_pos
                             != _bit_pos
                         {
@@ -833,10 +1231,10 @@ impl Game {
                                 _from_file,
                                 _from_rank,
                                 new_file as u32,
-                                new_rank,
+                                double_rank,
                             ) {
                                 pawn_possible_moves
-                                    .push(self.transform_back(new_file as u32, new_rank));
+                                    .push(self.transform_back(new_file as u32, double_rank));
                             }
                         }
                     }
@@ -1112,6 +1510,64 @@ impl Game {
     }
 }
 
+/// The character a piece is written as in get_board, index like in Game::pieces.
+fn piece_char(index_of_piece: usize, is_white: bool) -> char {
+    let c = ['p', 'q', 'r', 'b', 'n', 'k'][index_of_piece];
+    if is_white {
+        c.to_ascii_uppercase()
+    } else {
+        c
+    }
+}
+
+/// Material value of a piece character in pawns, kings and empty squares are worth nothing.
+pub fn piece_value(piece: char) -> i32 {
+    match piece.to_ascii_lowercase() {
+        'p' => 1,
+        'n' | 'b' => 3,
+        'r' => 5,
+        'q' => 9,
+        _ => 0,
+    }
+}
+
+/// Counts the move sequences `depth` plies deep, to check the move generation against known numbers.
+/// Pawns only promote to the piece set with `set_promotion`, so positions have to be picked without promotions.
+#[cfg(test)]
+fn perft(game: &Game, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let own = game.colour_of_piece[if game.white_turn { 0 } else { 1 }];
+    let mut search = game.clone();
+    let mut total = 0;
+    for index in (0..64).filter(|index| own & 2_u64.pow(*index) != 0) {
+        let from = game.transform_back(index / 8, index % 8);
+        for to in search.get_possible_moves(&from).unwrap() {
+            let mut after = game.clone();
+            after.make_move(&from, &to);
+            total += perft(&after, depth - 1);
+        }
+    }
+    for from in (0..POCKET_KINDS).map(crazyhouse::drop_square) {
+        for to in search.get_possible_moves(&from).unwrap_or_default() {
+            let mut after = game.clone();
+            after.make_move(&from, &to);
+            total += perft(&after, depth - 1);
+        }
+    }
+    total
+}
+
+/// Checks a position against move counts from another move generator, one count per depth from 1.
+#[cfg(test)]
+fn assert_perft(variant: Variant, fen: &str, counts: &[u64]) {
+    let game = Game::from_variant_fen(variant, fen).unwrap();
+    for (depth, count) in counts.iter().enumerate() {
+        assert_eq!(perft(&game, depth as u32 + 1), *count, "{} at depth {}", fen, depth + 1);
+    }
+}
+
 /// Implement print routine for Game.
 ///
 /// Output example:
@@ -1198,10 +1654,10 @@ impl fmt::Debug for Game {
 
 #[cfg(test)]
 mod tests {
-    use std::fmt::Debug;
-
+    use super::Ending;
     use super::Game;
     use super::GameState;
+    use super::Variant;
 
     // check test framework
     #[test]
@@ -1735,4 +2191,254 @@ mod tests {
 
 
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_pawn_attacks() {
+        // the pawn on e4 keeps the white king off f3
+        let mut game = Game::new();
+        assert_eq!(true, game.make_move("F2", "F4"));
+        assert_eq!(true, game.make_move("E7", "E5"));
+        assert_eq!(true, game.make_move("E1", "F2"));
+        assert_eq!(true, game.make_move("E5", "E4"));
+        assert_eq!(false, game.get_possible_moves("F2").unwrap().contains(&"F3".to_string()));
+        assert_eq!(true, game.get_possible_moves("F2").unwrap().contains(&"G3".to_string()));
+
+        // black's own pawns on c7 and d7 do not attack d6 and e6
+        let mut game = Game::new();
+        assert_eq!(true, game.make_move("E2", "E4"));
+        assert_eq!(true, game.make_move("E7", "E5"));
+        assert_eq!(true, game.make_move("A2", "A3"));
+        assert_eq!(true, game.make_move("E8", "E7"));
+        assert_eq!(true, game.make_move("A3", "A4"));
+        let mut king_moves = game.get_possible_moves("E7").unwrap();
+        king_moves.sort();
+        assert_eq!(vec!["D6", "E6", "E8", "F6"], king_moves);
+    }
+
+    #[test]
+    fn test_double_step_needs_both_squares_free() {
+        let mut game = Game::new();
+        assert_eq!(true, game.make_move("B1", "C3"));
+        assert_eq!(true, game.make_move("G8", "F6"));
+        assert_eq!(true, game.make_move("A2", "A3"));
+        // the knights stand right in front of the c- and f-pawns
+        assert_eq!(Some(vec![]), game.get_possible_moves("F7"));
+        assert_eq!(true, game.make_move("E7", "E6"));
+        assert_eq!(Some(vec![]), game.get_possible_moves("C2"));
+    }
+
+    #[test]
+    fn test_new_game_has_no_en_passant_square() {
+        // 16 stands for no square, 0 would be a3 and let the b-pawn take there
+        let mut game = Game::new();
+        assert_eq!(16, game.en_passant_at);
+        assert_eq!(Some(vec!["B3".to_string(), "B4".to_string()]), game.get_possible_moves("B2"));
+    }
+
+    #[test]
+    fn test_castling() {
+        let mut game = Game::new();
+        for (from, to) in [("E2", "E4"), ("E7", "E5"), ("G1", "F3"), ("B8", "C6"), ("F1", "C4"), ("G8", "F6")] {
+            assert_eq!(true, game.make_move(from, to));
+        }
+        assert_eq!(true, game.get_possible_moves("E1").unwrap().contains(&"G1".to_string()));
+        assert_eq!(true, game.make_move("E1", "G1"));
+        assert_eq!(
+            format!("{game:?}"),
+            "r*bqkb*r\npppp*ppp\n**n**n**\n****p***\n**B*P***\n*****N**\nPPPP*PPP\nRNBQ*RK*\n"
+        );
+        assert_eq!([false, false, true, true], game.get_castling_rights());
+
+        // a rook that moved takes its right with it, and castling is not allowed out of check
+        assert_eq!(true, game.make_move("H8", "G8"));
+        assert_eq!([false, false, false, true], game.get_castling_rights());
+        let mut game = Game::new();
+        for (from, to) in [("E2", "E4"), ("F7", "F6"), ("G1", "F3"), ("G7", "G5"), ("F1", "C4"), ("A7", "A6"), ("D1", "E2"), ("A6", "A5"), ("C4", "F7")] {
+            assert_eq!(true, game.make_move(from, to));
+        }
+        assert_eq!(false, game.get_possible_moves("E8").unwrap().contains(&"G8".to_string()));
+    }
+
+    #[test]
+    fn test_move_counters() {
+        let mut game = Game::new();
+        assert_eq!((0, 1), (game.get_halfmove_clock(), game.get_fullmove_number()));
+        assert_eq!(true, game.make_move("G1", "F3"));
+        assert_eq!((1, 1), (game.get_halfmove_clock(), game.get_fullmove_number()));
+        assert_eq!(true, game.make_move("G8", "F6"));
+        assert_eq!((2, 2), (game.get_halfmove_clock(), game.get_fullmove_number()));
+        // pawn moves and captures start the count again
+        assert_eq!(true, game.make_move("E2", "E4"));
+        assert_eq!((0, 2), (game.get_halfmove_clock(), game.get_fullmove_number()));
+        assert_eq!(true, game.make_move("F6", "E4"));
+        assert_eq!((0, 3), (game.get_halfmove_clock(), game.get_fullmove_number()));
+    }
+
+    #[test]
+    fn test_captures_are_tracked() {
+        let mut game = Game::new();
+        // a pawn can not take on an empty square before any pawn moved two steps
+        assert_eq!(false, game.make_move("B2", "A3"));
+        assert_eq!(true, game.make_move("E2", "E4"));
+        assert_eq!(true, game.make_move("D7", "D5"));
+        assert_eq!(true, game.make_move("E4", "D5"));
+        assert_eq!(true, game.make_move("D8", "D5"));
+        assert_eq!(game.get_captured(), &['p', 'P']);
+        assert_eq!(0, game.material_difference());
+
+        assert_eq!(true, game.make_move("B1", "C3"));
+        assert_eq!(true, game.make_move("D5", "A2"));
+        assert_eq!(game.get_captured(), &['p', 'P', 'P']);
+        assert_eq!(-1, game.material_difference());
+    }
+
+    #[test]
+    fn test_en_passant_capture_is_tracked() {
+        let mut game = Game::new();
+        assert_eq!(true, game.make_move("E2", "E4"));
+        assert_eq!(true, game.make_move("A7", "A6"));
+        assert_eq!(true, game.make_move("E4", "E5"));
+        assert_eq!(true, game.make_move("D7", "D5"));
+        assert_eq!(true, game.make_move("E5", "D6"));
+        assert_eq!(game.get_captured(), &['p']);
+        assert_eq!(1, game.material_difference());
+        // looking for legal moves must not count the captures it tries
+        game.get_possible_moves("D6");
+        assert_eq!(game.get_captured(), &['p']);
+    }
+
+    #[test]
+    fn test_mate_right_after_a_double_step() {
+        // the g-pawn's en passant square must not let white's own h-pawn block the mate
+        let mut game = Game::new();
+        assert_eq!(true, game.make_move("F2", "F3"));
+        assert_eq!(true, game.make_move("E7", "E5"));
+        assert_eq!(true, game.make_move("G2", "G4"));
+        assert_eq!(true, game.make_move("D8", "H4"));
+        assert_eq!(GameState::GameOver, game.get_game_state());
+        assert_eq!(Some(vec![]), game.get_possible_moves("H2"));
+    }
+
+    #[test]
+    fn test_check_answered_by_en_passant() {
+        // d7-d5 checks the walled in king, only taking the pawn en passant answers it
+        let mut game = Game::from_fen("k7/3p4/1n6/4PP2/3PKP2/3PPP2/8/8 b - - 0 1").unwrap();
+        assert_eq!(true, game.make_move("D7", "D5"));
+        assert_eq!(GameState::Check, game.get_game_state());
+        assert_eq!(Some(vec!["D6".to_string()]), game.get_possible_moves("E5"));
+    }
+
+    #[test]
+    fn test_is_in_check() {
+        let mut game = Game::new();
+        assert!(!game.is_in_check(true));
+        assert_eq!(true, game.make_move("E2", "E4"));
+        assert_eq!(true, game.make_move("F7", "F6"));
+        assert_eq!(true, game.make_move("D1", "H5"));
+        assert!(game.is_in_check(false));
+        assert!(!game.is_in_check(true));
+
+        // a stalemated king has no move but is not attacked
+        let stalemate = Game::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!stalemate.is_in_check(false));
+    }
+
+    #[test]
+    fn test_checked_king() {
+        let mut game = Game::new();
+        assert_eq!(None, game.checked_king());
+        assert_eq!(true, game.make_move("E2", "E4"));
+        assert_eq!(true, game.make_move("F7", "F6"));
+        assert_eq!(true, game.make_move("D1", "H5"));
+        assert_eq!(Some("E8".to_string()), game.checked_king());
+
+        // the mated king is pointed out as well, the stalemated one is not
+        let mate = Game::from_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(Some("A8".to_string()), mate.checked_king());
+        let stalemate = Game::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(None, stalemate.checked_king());
+    }
+
+    #[test]
+    fn test_promotion_capture_is_tracked() {
+        let mut game = Game::new();
+        game.pieces = [0, 0, 0, 0, 0, 0];
+        game.pieces[5] = 1 + 2_u64.pow(32 + 7); // white king A1, black king E8
+        game.pieces[0] = 2_u64.pow(48 + 6); // white pawn G7
+        game.pieces[2] = 2_u64.pow(56 + 7); // black rook H8
+        game.colour_of_piece = [1 + 2_u64.pow(48 + 6), 2_u64.pow(32 + 7) + 2_u64.pow(56 + 7)];
+        assert_eq!(-4, game.material_difference());
+
+        assert_eq!(true, game.make_move("G7", "H8"));
+        assert_eq!(game.get_captured(), &['r']);
+        assert_eq!(9, game.material_difference());
+    }
+
+    #[test]
+    fn test_repetition_and_fifty_moves() {
+        // knights out and back bring the start position round again, with the same side to move
+        let mut game = Game::new();
+        let start = game.clone();
+        for (from, to) in [("G1", "F3"), ("G8", "F6"), ("F3", "G1")] {
+            assert_eq!(true, game.make_move(from, to));
+            assert!(!game.is_same_position(&start));
+        }
+        assert_eq!(true, game.make_move("F6", "G8"));
+        assert!(game.is_same_position(&start));
+        assert!(!game.can_claim_fifty_moves());
+
+        // castling rights count, the king stepping out and back loses them
+        let mut king_walk = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let before = king_walk.clone();
+        for (from, to) in [("E1", "F1"), ("E8", "F8"), ("F1", "E1"), ("F8", "E8")] {
+            assert_eq!(true, king_walk.make_move(from, to));
+        }
+        assert!(!king_walk.is_same_position(&before));
+
+        // right after a double step the en passant square only counts if a pawn can take there
+        let mut no_taker = Game::new();
+        assert_eq!(true, no_taker.make_move("E2", "E4"));
+        let after_double_step = no_taker.clone();
+        for (from, to) in [("G8", "F6"), ("G1", "F3"), ("F6", "G8"), ("F3", "G1")] {
+            assert_eq!(true, no_taker.make_move(from, to));
+        }
+        assert!(no_taker.is_same_position(&after_double_step));
+
+        let mut taker = Game::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(true, taker.make_move("E2", "E4"));
+        let after_double_step = taker.clone();
+        for (from, to) in [("E8", "D8"), ("E1", "D1"), ("D8", "E8"), ("D1", "E1")] {
+            assert_eq!(true, taker.make_move(from, to));
+        }
+        assert!(!taker.is_same_position(&after_double_step));
+
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 100 80").unwrap().can_claim_fifty_moves());
+    }
+
+    #[test]
+    fn test_stalemate() {
+        assert_eq!(true, Game::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap().is_stalemate());
+        // mate is no stalemate, and neither is a position with moves left
+        assert_eq!(false, Game::from_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").unwrap().is_stalemate());
+        assert_eq!(false, Game::new().is_stalemate());
+
+        // in Antichess having no move left wins
+        let antichess = Game::from_variant_fen(Variant::Antichess, "8/8/8/8/8/p7/P7/8 w - - 0 1").unwrap();
+        assert_eq!(false, antichess.is_stalemate());
+        assert_eq!(Some(Ending::NoMovesLeft { white_wins: true }), antichess.get_ending());
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let insufficient = |fen: &str| Game::from_fen(fen).unwrap().has_insufficient_material();
+        assert_eq!(true, insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert_eq!(true, insufficient("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"));
+        assert_eq!(true, insufficient("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        // bishops on squares of both colours, or a knight with a bishop, can still mate
+        assert_eq!(false, insufficient("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1"));
+        assert_eq!(false, insufficient("4k3/8/8/8/8/8/8/2B1KN2 w - - 0 1"));
+        assert_eq!(false, insufficient("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert_eq!(false, Game::new().has_insufficient_material());
+        // in King of the Hill a bare king can still walk to the centre
+        assert_eq!(false, Game::from_variant_fen(Variant::KingOfTheHill, "4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap().has_insufficient_material());
+    }
+}
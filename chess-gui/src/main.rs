@@ -1,84 +1,1489 @@
-use jblomlof_chess::{GameState, Game};
+mod actions;
+mod autosave;
+mod cli;
+mod clock;
+mod config;
+mod editor;
+mod headless;
+mod layout;
+mod move_input;
+mod move_list;
+mod net;
+mod resources;
+mod settings;
+mod settings_screen;
+mod sound;
+mod theme;
+mod tray;
+mod ui;
+
+use actions::Action;
+use autosave::{ResumeButton, SavedGame, AUTOSAVE_INTERVAL};
+use chess_gui::{Ending, Game, ParsedMove, Pgn, Variant};
+use cli::{Options, Player};
+use clock::{Clock, TimeControl, CLOCK_WIDTH};
+use editor::{Editor, EditorButton};
+use layout::{Layout, MIN_SCREEN_SIZE};
+use move_input::MoveInput;
+use move_list::MoveList;
+use net::{Event, Message, Network, Role, PROTOCOL_VERSION};
+use resources::ErrorScreen;
+use settings::{Settings, MAX_DEPTH};
+use settings_screen::SettingsButton;
+use sound::Sounds;
+use theme::{PieceSet, Theme};
 
 use ggez::{conf, event, graphics, Context, ContextBuilder, GameError, GameResult};
-use std::{collections::HashMap, env, path};
-
-/// A chess board is 8x8 tiles.
-const GRID_SIZE: i16 = 10;
-/// Sutible size of each tile.
-const GRID_CELL_SIZE: (i16, i16) = (32 * 4, 32 * 4);
-
-/// Size of the application window.
-const SCREEN_SIZE: (f32, f32) = 
-(
-    GRID_SIZE as f32 * GRID_CELL_SIZE.0 as f32,
-    GRID_SIZE as f32 * GRID_CELL_SIZE.1 as f32,
-);
-
-// GUI Color representations
-const BLACK: graphics::Color = graphics::Color::new(228.0 / 255.0, 196.0 / 255.0, 108.0 / 255.0, 1.0);
-const WHITE: graphics::Color = graphics::Color::new(188.0 / 255.0, 140.0 / 255.0, 76.0 / 255.0, 1.0);
-const HIGHLIGHT: graphics::Color = graphics::Color::new(230.0 / 255.0, 200.0 / 255.0, 50.0 / 255.0, 0.5);
+use std::{env, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread};
+
+/// Font size of the rank and file labels drawn in the margin.
+const LABEL_SCALE: f32 = 40.0;
+
+/// Seconds it takes a piece to slide from one square to another.
+const ANIMATION_TIME: f32 = 0.15;
+/// Seconds the flash of an Atomic explosion takes to flare up and fade.
+const EXPLOSION_TIME: f32 = 0.5;
+/// Distance in window pixels the cursor has to travel before a press turns into a drag.
+const DRAG_THRESHOLD: f32 = 6.0;
+/// File of the squares standing for a Crazyhouse pocket, off the board. The rank is the piece's index in `POCKET_LETTERS`.
+const POCKET_FILE: u32 = 8;
+/// Letters of the pieces a pocket holds, in the order of `Game::get_pocket`.
+const POCKET_LETTERS: [char; 5] = ['P', 'Q', 'R', 'B', 'N'];
+
+/// Key that cycles through the board orientations.
+const FLIP_KEY: event::KeyCode = event::KeyCode::F;
+/// Key that switches to the next board theme.
+const THEME_KEY: event::KeyCode = event::KeyCode::T;
+/// Key that switches to the next piece set.
+const PIECE_SET_KEY: event::KeyCode = event::KeyCode::P;
+/// Key that opens the board editor, or leaves it without playing the position.
+const EDIT_KEY: event::KeyCode = event::KeyCode::E;
+/// Key that opens and closes the settings screen.
+const SETTINGS_KEY: event::KeyCode = event::KeyCode::S;
+/// Key that saves the game as a PGN file in the working directory.
+const EXPORT_KEY: event::KeyCode = event::KeyCode::X;
+
+/// Which side of the board is drawn at the bottom of the window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Orientation
+{
+    WhiteAtBottom,
+    BlackAtBottom,
+    /// Keeps the side to move at the bottom, flipping after every move.
+    AutoFlip,
+}
+
+impl Orientation
+{
+    /// The orientation selected after this one when pressing the flip key.
+    fn next(self) -> Orientation
+    {
+        match self
+        {
+            Orientation::WhiteAtBottom => Orientation::BlackAtBottom,
+            Orientation::BlackAtBottom => Orientation::AutoFlip,
+            Orientation::AutoFlip => Orientation::WhiteAtBottom,
+        }
+    }
+}
+
+/// A piece held by the mouse.
+struct Drag
+{
+    // Square the piece was picked up from, a pocket square for a piece out of a Crazyhouse pocket.
+    square: (u32, u32),
+    piece: char,
+    // Where the button was pressed and where the cursor is now, in window pixels.
+    start: (f32, f32),
+    position: (f32, f32),
+    // Press and release without moving counts as a click.
+    moved: bool,
+    was_selected: bool,
+}
+
+/// A move as it was played, ply n of the game is `moves[n - 1]`.
+struct PlayedMove
+{
+    from: (u32, u32),
+    to: (u32, u32),
+    san: String,
+}
+
+/// A piece sliding across the board towards the square it stands on.
+struct Animation
+{
+    piece: char,
+    // Start position in (file, rank) units, fractional when released between squares.
+    from: (f32, f32),
+    to: (u32, u32),
+    progress: f32,
+}
+
+/// The engine looking for a move on its own thread. Dropping it stops the search.
+struct EngineSearch
+{
+    receiver: mpsc::Receiver<Option<(String, String)>>,
+    stop: Arc<AtomicBool>,
+    // FEN of the position searched, a move found for any other position is thrown away.
+    fen: String,
+}
+
+impl Drop for EngineSearch
+{
+    fn drop(&mut self)
+    {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Flash over the squares an Atomic capture blew up, spreading from the square of the capture.
+struct Explosion
+{
+    centre: (u32, u32),
+    squares: Vec<(u32, u32)>,
+    progress: f32,
+}
     
 /// GUI logic and event implementation structure.
 struct AppState 
 {
-    sprites: HashMap<char, graphics::Image>,
+    // Colours and sprites, switchable while playing.
+    themes: Vec<Theme>,
+    theme_index: usize,
+    piece_sets: Vec<String>,
+    piece_set_index: usize,
+    pieces: PieceSet,
+
+    layout: Layout,
     // Imported game representation.
     game: Game,
 
-    selectedPiece: Option<(u32, u32)>,
-    possibleMoves: Vec<(u32, u32)>
+    orientation: Orientation,
+
+    // Squares are stored as (file, rank), both counted from 0.
+    selected_piece: Option<(u32, u32)>,
+    possible_moves: Vec<(u32, u32)>,
+
+    // Every position of the game, positions[n] is the one after ply n and positions[0] the start.
+    positions: Vec<Game>,
+    moves: Vec<PlayedMove>,
+    // Ply shown while looking back through the game, the board can not be played on meanwhile.
+    viewing: Option<usize>,
+    move_list: MoveList,
+    move_input: MoveInput,
+    // Position being set up, the game is put aside while it is open.
+    editor: Option<Editor>,
+
+    // Players of white and black, and how far the engine looks ahead when it plays.
+    players: [Player; 2],
+    engine_depth: u32,
+    // The engine thinks on its own thread so the window keeps responding, its move arrives here.
+    engine_search: Option<EngineSearch>,
+
+    // Every new game gets a fresh clock when playing with a time control.
+    time_control: Option<TimeControl>,
+    clock: Option<Clock>,
+
+    // Preferences as saved, only changed by the player so command line options are not written back.
+    settings: Settings,
+    settings_open: bool,
+    // Missing when no audio device could be opened.
+    sounds: Option<Sounds>,
+    sound_on: bool,
+
+    // Game from the last session, offered on startup until the player resumes it or starts anew.
+    saved_game: Option<SavedGame>,
+    // Seconds since the game was last saved.
+    since_autosave: f32,
+
+    // Connection to the opponent in a network game.
+    network: Option<Network>,
+    // How the game ended when it was not decided on the board, e.g. by resignation.
+    ended: Option<Ending>,
+    // Side that offered a draw the other side has not answered yet, true for white.
+    draw_offer: Option<bool>,
+
+    dragging: Option<Drag>,
+    animations: Vec<Animation>,
+    explosions: Vec<Explosion>,
 }
 
 impl AppState 
 {
     /// Initialise new application, i.e. initialise new game and load resources.
-    fn new(ctx: &mut Context) -> GameResult<AppState> 
+    /// The game starts from the position and with the players given on the command line,
+    /// everything left out there comes from the saved settings. A saved game is offered to be resumed.
+    fn new(ctx: &mut Context, options: &Options, settings: Settings, saved_game: Option<SavedGame>, network: Option<Network>) -> GameResult<AppState> 
     {
-        let state = AppState 
+        // the drawable size differs from the requested one on high DPI screens
+        let (width, height) = graphics::drawable_size(ctx);
+        graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))?;
+
+        let mut state = AppState 
         {
-            sprites: AppState::load_sprites(ctx),
+            themes: Theme::load_all(ctx).unwrap_or_else(|error|
+            {
+                eprintln!("Could not load themes, using the bundled one: {}", error);
+                vec![Theme::mushroom()]
+            }),
+
+            theme_index: 0,
+
+            piece_sets: PieceSet::available(ctx),
+
+            piece_set_index: 0,
+
+            pieces: PieceSet::load_or_bundled(ctx, &settings.piece_set)?,
+
+            layout: Layout::new(width, height),
 
             game: Game::new(),
 
-            selectedPiece: None,
+            orientation: options.orientation,
+
+            selected_piece: None,
             
-            possibleMoves: vec![]
+            possible_moves: vec![],
+
+            positions: vec![Game::new()],
+
+            moves: vec![],
+
+            viewing: None,
+
+            move_list: MoveList::new(),
+
+            move_input: MoveInput::new(),
+
+            editor: None,
+
+            players: options.players,
+
+            engine_depth: options.depth,
+
+            engine_search: None,
+
+            time_control: options.time_control,
+
+            clock: None,
+
+            sounds: match Sounds::new(ctx)
+            {
+                Ok(sounds) => Some(sounds),
+                Err(error) =>
+                {
+                    eprintln!("Playing without sound: {}", error);
+                    None
+                }
+            },
+
+            sound_on: settings.sound,
+
+            settings,
+
+            settings_open: false,
+
+            saved_game,
+
+            since_autosave: 0.0,
+
+            network,
+
+            ended: None,
+
+            draw_offer: None,
+
+            dragging: None,
+
+            animations: vec![],
+            explosions: vec![],
+        };
+
+        match state.themes.iter().position(|theme| theme.name == options.theme)
+        {
+            Some(index) => state.theme_index = index,
+            None => eprintln!("No theme named {}, using {}.", options.theme, state.theme().name),
+        }
+        if let Some(index) = state.piece_sets.iter().position(|name| *name == state.settings.piece_set)
+        {
+            state.piece_set_index = index;
+        }
+
+        state.replay(options.start.clone(), &options.moves);
+        // the clock starts with the moves played in the window
+        state.clock = state.time_control.map(Clock::new);
+
+        match options.network
+        {
+            Some(Role::Host(_)) =>
+            {
+                state.players[if options.host_white { 1 } else { 0 }] = Player::Remote;
+                if !options.host_white && state.orientation != Orientation::AutoFlip { state.orientation = Orientation::BlackAtBottom; }
+            }
+            // nothing can be played until the host has said which side is whose, spectators never play
+            Some(Role::Join(_)) | Some(Role::Watch(_)) => state.players = [Player::Remote; 2],
+            None => (),
+        }
+
+        Ok(state)
+    }
+
+    /// Starts a game from a position and plays moves already made in it, silently and without animation.
+    fn replay(&mut self, start: Game, moves: &[ParsedMove])
+    {
+        self.start_game(start);
+
+        for parsed in moves
+        {
+            self.game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+            self.apply_move(transform_input(&parsed.from), transform_input(&parsed.to), false);
+        }
+        self.game.set_promotion("Q");
+    }
+
+    /// Continues the game offered on startup with its players and clock.
+    fn resume_saved_game(&mut self)
+    {
+        let saved = match self.saved_game.take()
+        {
+            Some(saved) => saved,
+            None => return,
+        };
+
+        self.replay(saved.start, &saved.moves);
+        self.players = saved.players;
+        self.engine_depth = saved.engine_depth;
+        self.clock = saved.clock;
+    }
+
+    /// Writes the game being played to disk so it can be resumed, a game that is over or not begun is not kept.
+    fn autosave(&mut self)
+    {
+        self.since_autosave = 0.0;
+        // the saved game stays as it is until the player has decided about it,
+        // and network games belong to the host, they are not resumed on their own
+        if self.saved_game.is_some() || self.network.is_some() { return; }
+
+        let result = if self.moves.is_empty() || self.is_over()
+        {
+            SavedGame::remove()
+        }
+        else
+        {
+            let san = self.moves.iter().map(|played| played.san.clone()).collect();
+            match SavedGame::new(self.positions[0].clone(), san, self.players, self.engine_depth, self.clock.clone())
+            {
+                Ok(saved) => saved.save(),
+                Err(error) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error.to_string())),
+            }
+        };
+
+        if let Err(error) = result
+        {
+            eprintln!("Failed to save the game: {}", error);
+        }
+    }
+    fn theme(&self) -> &Theme
+    {
+        &self.themes[self.theme_index]
+    }
+
+    fn next_theme(&mut self)
+    {
+        self.theme_index = (self.theme_index + 1) % self.themes.len();
+        self.settings.theme = self.theme().name.clone();
+        self.save_settings();
+    }
+
+    /// Switches to the next installed piece set, the bundled set stands in for broken ones.
+    fn next_piece_set(&mut self, ctx: &mut Context) -> GameResult
+    {
+        self.piece_set_index = (self.piece_set_index + 1) % self.piece_sets.len();
+        self.pieces = PieceSet::load_or_bundled(ctx, &self.piece_sets[self.piece_set_index])?;
+        self.settings.piece_set = self.piece_sets[self.piece_set_index].clone();
+        self.save_settings();
+
+        Ok(())
+    }
+
+    fn next_orientation(&mut self)
+    {
+        self.orientation = self.orientation.next();
+        self.settings.orientation = self.orientation;
+        self.save_settings();
+    }
+
+    /// Writes the settings file, a failure only costs the preferences of this session.
+    fn save_settings(&self)
+    {
+        if let Err(error) = self.settings.save()
+        {
+            eprintln!("Failed to save settings: {}", error);
+        }
+    }
+
+    /// Steps the setting of a row on the settings screen to its next value.
+    fn settings_click(&mut self, ctx: &mut Context, x: f32, y: f32)
+    {
+        match settings_screen::button_at(&self.layout, x, y)
+        {
+            Some(SettingsButton::Theme) => self.next_theme(),
+            Some(SettingsButton::PieceSet) => self.next_piece_set(ctx).expect("Failed to load any piece set."),
+            Some(SettingsButton::Orientation) => self.next_orientation(),
+            Some(SettingsButton::Sound) =>
+            {
+                self.sound_on = !self.sound_on;
+                self.settings.sound = self.sound_on;
+                self.save_settings();
+            }
+            Some(SettingsButton::TimeControl) =>
+            {
+                self.time_control = settings_screen::next_time_control(self.time_control);
+                self.settings.time_control = self.time_control;
+                self.save_settings();
+                // a game under way keeps its clock, the new one comes with the next game
+                if self.moves.is_empty() { self.clock = self.time_control.map(Clock::new); }
+            }
+            Some(SettingsButton::EngineDepth) =>
+            {
+                self.engine_depth = self.engine_depth % MAX_DEPTH + 1;
+                self.settings.engine_depth = self.engine_depth;
+                self.save_settings();
+            }
+            Some(SettingsButton::Close) => self.settings_open = false,
+            None => (),
+        }
+    }
+
+    /// Text of a row on the settings screen, the setting and its current value.
+    fn settings_label(&self, button: SettingsButton) -> String
+    {
+        match button
+        {
+            SettingsButton::Theme => format!("Theme: {}", self.theme().name),
+            SettingsButton::PieceSet => format!("Pieces: {}", self.piece_sets[self.piece_set_index]),
+            SettingsButton::Orientation => format!("Board: {}", settings::orientation_name(self.orientation)),
+            SettingsButton::Sound => format!("Sound: {}", if self.sound_on { "on" } else { "off" }),
+            SettingsButton::TimeControl => format!("Clock: {}", self.time_control.map_or("none".to_string(), |control| control.to_string())),
+            SettingsButton::EngineDepth => format!("Engine depth: {}", self.engine_depth),
+            SettingsButton::Close => "Close".to_string(),
+        }
+    }
+
+    /// True when black's side of the board is drawn at the bottom of the window.
+    fn is_flipped(&self) -> bool
+    {
+        match self.orientation
+        {
+            Orientation::WhiteAtBottom => false,
+            Orientation::BlackAtBottom => true,
+            Orientation::AutoFlip => !self.position().is_white_turn(),
+        }
+    }
+
+    /// Converts a board square (file, rank) into the grid cell (column, row) it is drawn in.
+    fn square_to_cell(&self, square: (u32, u32)) -> (u32, u32)
+    {
+        if self.is_flipped() { (7 - square.0, square.1) } else { (square.0, 7 - square.1) }
+    }
+
+    /// Converts a grid cell (column, row) into the board square (file, rank) drawn in it.
+    fn cell_to_square(&self, cell: (u32, u32)) -> (u32, u32)
+    {
+        if self.is_flipped() { (7 - cell.0, cell.1) } else { (cell.0, 7 - cell.1) }
+    }
+
+    /// Converts a (file, rank) position into grid (column, row) units, keeping fractions.
+    /// The mapping is its own inverse, so it also converts grid positions back to the board.
+    fn board_point_to_cell(&self, point: (f32, f32)) -> (f32, f32)
+    {
+        if self.is_flipped() { (7.0 - point.0, point.1) } else { (point.0, 7.0 - point.1) }
+    }
+
+    /// The position on the board, an earlier one while looking back through the game.
+    fn position(&self) -> &Game
+    {
+        match self.viewing
+        {
+            Some(ply) => &self.positions[ply],
+            None => &self.game,
+        }
+    }
+
+    /// Number of moves made to reach the position on the board.
+    fn current_ply(&self) -> usize
+    {
+        self.viewing.unwrap_or(self.moves.len())
+    }
+
+    /// Shows the position after a ply, going back to the game being played once the last ply is reached.
+    fn view(&mut self, ply: usize)
+    {
+        self.viewing = if ply < self.moves.len() { Some(ply) } else { None };
+
+        self.clear_selection();
+        self.dragging = None;
+        self.animations.clear();
+        self.explosions.clear();
+        self.move_list.follow(&self.layout, self.current_ply());
+    }
+
+    /// Squares the move leading to the shown position went from and to.
+    fn shown_last_move(&self) -> Option<((u32, u32), (u32, u32))>
+    {
+        let ply = self.current_ply();
+        if ply == 0 { return None; }
+
+        Some((self.moves[ply - 1].from, self.moves[ply - 1].to))
+    }
+
+    /// The board drawn in the window, the editor's position while setting one up.
+    fn shown_board(&self) -> String
+    {
+        match &self.editor
+        {
+            Some(editor) => editor.get_board(),
+            None => self.position().get_board(),
+        }
+    }
+
+    /// The piece character on a square, '*' if it is empty.
+    fn piece_at(&self, square: (u32, u32)) -> char
+    {
+        self.shown_board().as_bytes()[(square.0 + (7 - square.1) * 9) as usize] as char
+    }
+
+    /// Selects the piece on a square, or in a pocket, and looks up where it can go.
+    fn select(&mut self, square: (u32, u32))
+    {
+        self.selected_piece = Some(square);
+
+        self.possible_moves.clear();
+
+        if let Some(moves) = self.game.get_possible_moves(&transform_back(square.0, square.1))
+        {
+            for i in moves
+            {
+                self.possible_moves.push(transform_input(&i));
+            }
+        }
+    }
+
+    /// Selects a piece pressed with the mouse, on the board or in a pocket, and holds it until the button is released.
+    fn pick_up(&mut self, square: (u32, u32), piece: char, position: (f32, f32))
+    {
+        let was_selected = self.selected_piece == Some(square);
+        self.select(square);
+
+        self.dragging = Some(Drag
+        {
+            square,
+            piece,
+            start: position,
+            position,
+            moved: false,
+            was_selected,
+        });
+    }
+
+    fn clear_selection(&mut self)
+    {
+        self.selected_piece = None;
+        self.possible_moves.clear();
+    }
+
+    /// Opens the editor on the position of the game, nothing can be played until it is closed.
+    fn open_editor(&mut self)
+    {
+        self.editor = Some(Editor::from_game(&self.game));
+
+        self.viewing = None;
+        self.clear_selection();
+        self.dragging = None;
+        self.animations.clear();
+        self.explosions.clear();
+        self.move_input.focused = false;
+    }
+
+    /// Plays the position set up in the editor, or tells why it is not a legal position.
+    fn play_edited_position(&mut self)
+    {
+        let result = match &self.editor
+        {
+            Some(editor) => editor.to_game(),
+            None => return,
         };
 
-        Ok(state)
-    }
-    #[rustfmt::skip] // Skips formatting on this function (not recommended)
-    /// Loads chess piese images into hashmap, for ease of use.
-    fn load_sprites(ctx: &mut Context) -> HashMap<char, graphics::Image> 
-    {
-        [
-            ('k', "/black_king.png".to_string()),
-            ('q', "/black_queen.png".to_string()),
-            ('r', "/black_rook.png".to_string()),
-            ('p', "/black_pawn.png".to_string()),
-            ('b', "/black_bishop.png".to_string()),
-            ('n', "/black_knight.png".to_string()),
-            ('K', "/white_king.png".to_string()),
-            ('Q', "/white_queen.png".to_string()),
-            ('R', "/white_rook.png".to_string()),
-            ('P', "/white_pawn.png".to_string()),
-            ('B', "/white_bishop.png".to_string()),
-            ('N', "/white_knight.png".to_string()),
-            ('s', "/shadow.png".to_string())
-        ]
-            .iter()
-            .map(|(piece, path)| 
+        match result
+        {
+            Ok(game) => self.start_game(game),
+            Err(error) =>
+            {
+                if let Some(editor) = self.editor.as_mut() { editor.message = Some(error.to_string()); }
+            }
+        }
+    }
+
+    /// Replaces the game and its history with a new game.
+    fn start_game(&mut self, game: Game)
+    {
+        self.move_list.reset(game.get_fullmove_number(), !game.is_white_turn());
+        self.positions = vec![game.clone()];
+        self.game = game;
+        self.moves.clear();
+        self.viewing = None;
+        self.editor = None;
+        // a search still running was for the old game, it is stopped
+        self.engine_search = None;
+        self.clock = self.time_control.map(Clock::new);
+        self.ended = None;
+        self.draw_offer = None;
+
+        self.clear_selection();
+        self.dragging = None;
+        self.animations.clear();
+        self.explosions.clear();
+        self.move_input.text.clear();
+        self.move_input.message = None;
+    }
+
+    /// Places or removes a piece, or presses an editor button under the cursor.
+    fn edit_click(&mut self, button: event::MouseButton, x: f32, y: f32)
+    {
+        let square = self.layout.cell_at(x, y).map(|cell| self.cell_to_square(cell));
+        let pressed = Editor::button_at(&self.layout, x, y);
+
+        match (pressed, square, button)
+        {
+            (Some(EditorButton::Play), _, event::MouseButton::Left) => self.play_edited_position(),
+            (Some(EditorButton::Cancel), _, event::MouseButton::Left) => self.editor = None,
+            (Some(pressed), _, event::MouseButton::Left) =>
+            {
+                if let Some(editor) = self.editor.as_mut() { editor.press(pressed); }
+            }
+            (None, Some(square), event::MouseButton::Left) =>
+            {
+                if let Some(editor) = self.editor.as_mut() { editor.place(square); }
+            }
+            // right click takes pieces off, whichever piece is picked
+            (None, Some(square), event::MouseButton::Right) =>
+            {
+                if let Some(editor) = self.editor.as_mut() { editor.remove(square); }
+            }
+            _ => (),
+        }
+    }
+
+    /// Plays a move of the game being played, by a player, the engine or the opponent over the network.
+    /// Sounds it, and sends it to the opponent unless it came from them. Returns false if the game rejected the move.
+    fn play_move(&mut self, ctx: &Context, from: (u32, u32), to: (u32, u32), animate: bool) -> bool
+    {
+        let mover = if self.game.is_white_turn() { 0 } else { 1 };
+        let captured_before = self.game.get_captured().len();
+        let board_before = self.game.get_board();
+        if !self.apply_move(from, to, animate) { return false; }
+
+        // every square emptied by an Atomic capture but the one the piece left flashes
+        if self.game.get_variant() == Variant::Atomic && self.game.get_captured().len() > captured_before
+        {
+            let board_after = self.game.get_board();
+            let squares: Vec<(u32, u32)> = board_before.chars().zip(board_after.chars())
+                .filter(|(_, c)| *c != '\n')
+                .enumerate()
+                .filter(|(_, (before, after))| *before != '*' && *after == '*')
+                .map(|(index, _)| (index as u32 % 8, 7 - index as u32 / 8))
+                .filter(|square| *square != from)
+                .collect();
+            self.explosions.push(Explosion { centre: to, squares, progress: 0.0 });
+        }
+
+        if let Some(sounds) = self.sounds.as_mut().filter(|_| self.sound_on)
+        {
+            sounds.play(ctx, self.game.get_captured().len() > captured_before);
+        }
+
+        // moving instead of answering a draw offer declines it, an offer made by the mover still stands
+        if self.draw_offer == Some(mover == 1) { self.draw_offer = None; }
+
+        if let Some(network) = &self.network
+        {
+            let mut updates = vec![Message::Move(self.moves[self.moves.len() - 1].san.clone())];
+            // the host's clock counts, it keeps the other side's in step
+            if let Some(clock) = self.clock.as_ref().filter(|_| network.is_host())
+            {
+                updates.push(Message::Clock(clock.control(), clock.remaining()[0], clock.remaining()[1]));
+            }
+            for update in &updates
+            {
+                // the opponent knows their own moves, spectators hear of every move from the host
+                if self.players[mover] != Player::Remote { network.send(update); }
+                if network.is_host() { network.tell_spectators(update); }
+            }
+        }
+
+        true
+    }
+
+    /// Puts a move on the board, every move made in the GUI goes through here.
+    /// Unless the piece was dropped in place it slides over from its old square.
+    /// Returns false if the game rejected the move.
+    fn apply_move(&mut self, from: (u32, u32), to: (u32, u32), animate: bool) -> bool
+    {
+        let dropped = from.0 == POCKET_FILE;
+        let piece = if dropped { '*' } else { self.piece_at(from) };
+        let rank_before: Vec<char> = if dropped { vec![] } else { (0..8).map(|file| self.piece_at((file, from.1))).collect() };
+        let san = self.game.make_move_with_san(&transform_back(from.0, from.1), &transform_back(to.0, to.1));
+        let moved = san.is_some();
+
+        if let Some(san) = san
+        {
+            if let Some(clock) = self.clock.as_mut() { clock.moved(!self.game.is_white_turn()); }
+            self.moves.push(PlayedMove { from, to, san });
+            self.positions.push(self.game.clone());
+            self.move_list.follow(&self.layout, self.moves.len());
+        }
+
+        // a piece dropped from the pocket appears on its square, and one blown up in Atomic is gone at once
+        if moved && animate && !dropped
+        {
+            // a Chess960 king castles onto its rook but lands on the g or c file
+            let to = match piece
+            {
+                'K' | 'k' => (0..8).map(|file| (file, to.1)).find(|square| self.piece_at(*square) == piece).unwrap_or(to),
+                _ => to,
+            };
+            // promoted pawns slide as the piece they became
+            let landed = self.piece_at(to);
+            if landed != '*'
+            {
+                self.animations.push(Animation
+                {
+                    piece: landed,
+                    from: (from.0 as f32, from.1 as f32),
+                    to,
+                    progress: 0.0,
+                });
+            }
+
+            // castling moves the rook along the back rank too
+            if piece.eq_ignore_ascii_case(&'k')
+            {
+                let rook = if piece == 'K' { 'R' } else { 'r' };
+                let left = (0..8).find(|&file| rank_before[file as usize] == rook && self.piece_at((file, from.1)) != rook);
+                let arrived = (0..8).find(|&file| rank_before[file as usize] != rook && self.piece_at((file, from.1)) == rook);
+                if let (Some(left), Some(arrived)) = (left, arrived)
+                {
+                    self.animations.push(Animation
+                    {
+                        piece: rook,
+                        from: (left as f32, from.1 as f32),
+                        to: (arrived, from.1),
+                        progress: 0.0,
+                    });
+                }
+            }
+        }
+
+        self.clear_selection();
+        moved
+    }
+
+    /// How the game was decided, by checkmate or the variant's rules, by a side running out of time or off the board.
+    /// None while it goes on.
+    fn ending(&self) -> Option<Ending>
+    {
+        if self.ended.is_some() { return self.ended; }
+        if self.game.get_ending().is_some() { return self.game.get_ending(); }
+        self.clock.as_ref().and_then(|clock| clock.flagged()).map(|white| Ending::Timeout { white_wins: !white })
+    }
+
+    fn is_over(&self) -> bool
+    {
+        self.ending().is_some()
+    }
+
+    /// True if the side to move of the live game is played in this window with the mouse or keyboard.
+    fn human_to_move(&self) -> bool
+    {
+        let side = if self.game.is_white_turn() { 0 } else { 1 };
+        self.players[side] == Player::Human && self.editor.is_none() && self.saved_game.is_none() && !self.is_over()
+            // moves made while the opponent is away would never reach them
+            && self.network.as_ref().is_none_or(|network| network.is_connected())
+    }
+
+    /// True if the engine plays the side to move of the live game.
+    fn engine_to_move(&self) -> bool
+    {
+        let side = if self.game.is_white_turn() { 0 } else { 1 };
+        self.players[side] == Player::Engine && self.editor.is_none() && self.saved_game.is_none() && !self.is_over()
+    }
+
+    /// Starts the engine on the live game once it is its turn, and plays its move when it is found.
+    fn run_engine(&mut self, ctx: &Context)
+    {
+        // a search is stopped once its move can not be played any more, e.g. after a resignation or a new position
+        let stale = self.engine_search.as_ref().is_some_and(|search| !self.engine_to_move() || search.fen != self.game.to_fen());
+        if stale { self.engine_search = None; }
+
+        if self.engine_search.is_none() && self.engine_to_move() && self.animations.is_empty()
+        {
+            let (sender, receiver) = mpsc::channel();
+            let stop = Arc::new(AtomicBool::new(false));
+            let (game, depth, searching) = (self.game.clone(), self.engine_depth, stop.clone());
+            thread::spawn(move ||
+            {
+                // the receiver is gone if the search was stopped meanwhile
+                let _ = sender.send(game.best_move_until(depth, &searching));
+            });
+            self.engine_search = Some(EngineSearch { receiver, stop, fen: self.game.to_fen() });
+        }
+
+        let found = match self.engine_search.as_ref().map(|search| search.receiver.try_recv())
+        {
+            Some(Ok(found)) => found,
+            Some(Err(mpsc::TryRecvError::Empty)) | None => return,
+            Some(Err(mpsc::TryRecvError::Disconnected)) => None,
+        };
+        self.engine_search = None;
+
+        if let Some((from, to)) = found
+        {
+            self.play_move(ctx, transform_input(&from), transform_input(&to), true);
+        }
+    }
+
+    /// Plays the move typed into the move box, or says why it can not be played.
+    fn submit_typed_move(&mut self, ctx: &Context)
+    {
+        if self.move_input.text.trim().is_empty() { return; }
+
+        if self.move_input.text.starts_with('/')
+        {
+            let command = std::mem::take(&mut self.move_input.text);
+            self.run_command(&command);
+            return;
+        }
+
+        if !self.human_to_move()
+        {
+            let reason = if self.is_over() { "the game is over" }
+                else if self.engine_to_move() { "the engine is thinking" }
+                else { "waiting for the opponent" };
+            self.move_input.message = Some(reason.to_string());
+            return;
+        }
+
+        if self.viewing.is_some()
+        {
+            self.move_input.message = Some("return to live to move".to_string());
+            return;
+        }
+
+        match self.game.parse_move(&self.move_input.text)
+        {
+            Ok(parsed) =>
+            {
+                if let Some(piece) = parsed.promotion { self.game.set_promotion(&piece.to_string()); }
+                self.play_move(ctx, transform_input(&parsed.from), transform_input(&parsed.to), true);
+                // pieces moved with the mouse always promote to queens
+                self.game.set_promotion("Q");
+
+                self.move_input.text.clear();
+                self.move_input.message = None;
+            }
+            Err(error) => self.move_input.message = Some(error.to_string()),
+        }
+    }
+
+    /// Side the action buttons act for: the player in this window in a network game, otherwise
+    /// the human to move, or the human playing the engine while it thinks. True for white.
+    fn acting_side(&self) -> Option<bool>
+    {
+        if self.is_over() || self.editor.is_some() || self.saved_game.is_some() { return None; }
+        // what is done while the opponent is away would be undone by the host's game when they are back
+        if self.network.as_ref().is_some_and(|network| !network.is_connected()) { return None; }
+
+        let human = |white: bool| self.players[if white { 0 } else { 1 }] == Player::Human;
+        let to_move = self.game.is_white_turn();
+        if human(to_move) { Some(to_move) } else if human(!to_move) { Some(!to_move) } else { None }
+    }
+
+    /// The draw that can be claimed in the live game, by threefold repetition or the fifty-move rule.
+    fn claimable_draw(&self) -> Option<Ending>
+    {
+        // positions holds the live game as its last entry
+        let repetitions = self.positions.iter().filter(|position| position.is_same_position(&self.game)).count();
+        if repetitions >= 3 { return Some(Ending::Repetition); }
+        if self.game.can_claim_fifty_moves() { return Some(Ending::FiftyMoves); }
+        None
+    }
+
+    /// Text of an action button and whether it can be pressed.
+    fn action_label(&self, action: Action) -> (String, bool)
+    {
+        let side = self.acting_side();
+        let offered_to_us = side.is_some() && self.draw_offer == side.map(|white| !white);
+        match action
+        {
+            Action::Resign => ("Resign".to_string(), side.is_some()),
+            Action::Draw if offered_to_us => ("Accept".to_string(), true),
+            Action::Draw => ("Draw".to_string(), side.is_some() && self.draw_offer.is_none()),
+            Action::Decline => ("Decline".to_string(), offered_to_us),
+            Action::Claim => ("Claim".to_string(), side.is_some() && self.claimable_draw().is_some()),
+        }
+    }
+
+    /// Resigns, offers, accepts or declines a draw, or claims one, for the side the buttons act for.
+    fn act(&mut self, action: Action)
+    {
+        let side = match self.acting_side()
+        {
+            Some(side) => side,
+            None =>
+            {
+                let reason = if self.is_over() { "the game is over" } else { "there is nobody to act for" };
+                self.move_input.message = Some(reason.to_string());
+                return;
+            }
+        };
+        let name = if side { "white" } else { "black" };
+
+        let message = match action
+        {
+            Action::Resign =>
+            {
+                self.send(Message::Resign);
+                self.end_game(Ending::Resignation { white_wins: !side });
+                None
+            }
+            Action::Draw if self.draw_offer == Some(!side) =>
+            {
+                self.send(Message::DrawAccept);
+                self.end_game(Ending::Agreement);
+                None
+            }
+            Action::Draw if self.draw_offer == Some(side) => Some("the draw offer waits for an answer".to_string()),
+            // the engine plays every game out
+            Action::Draw if self.players[if side { 1 } else { 0 }] == Player::Engine => Some("the engine declines the draw".to_string()),
+            Action::Draw =>
+            {
+                self.send(Message::DrawOffer);
+                self.draw_offer = Some(side);
+                Some(format!("{} offers a draw", name))
+            }
+            Action::Decline if self.draw_offer == Some(!side) =>
+            {
+                self.send(Message::DrawDecline);
+                self.draw_offer = None;
+                Some(format!("{} declines the draw", name))
+            }
+            Action::Decline => Some("no draw was offered".to_string()),
+            Action::Claim => match self.claimable_draw()
+            {
+                Some(ending) =>
+                {
+                    self.send(Message::Over(ending));
+                    self.end_game(ending);
+                    None
+                }
+                None => Some("no draw to claim, that needs a threefold repetition or fifty moves without a capture or pawn move".to_string()),
+            },
+        };
+        self.move_input.message = message;
+    }
+
+    /// Runs a command typed into the move box: /resign, /draw to offer or accept a draw, /decline to turn an offer down,
+    /// /claim to claim a draw and in network games /chat followed by a message for the opponent.
+    fn run_command(&mut self, command: &str)
+    {
+        let (name, rest) = command.trim().split_once(' ').map_or((command.trim(), ""), |(name, rest)| (name, rest.trim()));
+        if self.network.as_ref().is_some_and(|network| network.is_spectator())
+        {
+            self.move_input.message = Some("spectators can only watch".to_string());
+            return;
+        }
+
+        match name
+        {
+            "/resign" => self.act(Action::Resign),
+            "/draw" => self.act(Action::Draw),
+            "/decline" => self.act(Action::Decline),
+            "/claim" => self.act(Action::Claim),
+            "/chat" if self.network.is_some() && !rest.is_empty() =>
+            {
+                self.send(Message::Chat(rest.to_string()));
+                self.move_input.message = Some(format!("you: {}", rest));
+            }
+            _ => self.move_input.message = Some("try /resign, /draw, /decline, /claim or /chat".to_string()),
+        }
+    }
+
+    /// Handles what arrived from the opponent since the last frame.
+    fn handle_network(&mut self, ctx: &Context)
+    {
+        let events = match &self.network
+        {
+            Some(network) => network.poll(),
+            None => return,
+        };
+
+        for event in events
+        {
+            match event
+            {
+                // the client speaks first, the host answers its hello
+                Event::Connected => self.move_input.message = Some("connected".to_string()),
+                Event::Received(message) => self.receive(ctx, message),
+                Event::Disconnected(reason) => self.move_input.message = Some(format!("opponent gone: {}", reason)),
+                Event::Spectator(id, message) => self.receive_from_spectator(id, message),
+            }
+        }
+    }
+
+    /// Acts on a message from the opponent. Moves are checked against the game like any other,
+    /// anything that does not fit the game here brings the two sides back in step.
+    fn receive(&mut self, ctx: &Context, message: Message)
+    {
+        let hosting = self.network.as_ref().is_some_and(|network| network.is_host());
+        let remote_white = self.players[0] == Player::Remote;
+
+        match message
+        {
+            Message::Hello(version) if hosting =>
+            {
+                if version != PROTOCOL_VERSION
+                {
+                    self.send(Message::Bye(format!("protocol version {} is needed", PROTOCOL_VERSION)));
+                    if let Some(network) = &self.network { network.disconnect(); }
+                    return;
+                }
+                self.send(Message::Welcome(PROTOCOL_VERSION, remote_white));
+                self.send_game(None);
+            }
+            Message::Welcome(version, white) if !hosting && version == PROTOCOL_VERSION =>
+            {
+                self.players = if white { [Player::Human, Player::Remote] } else { [Player::Remote, Player::Human] };
+                if self.orientation != Orientation::AutoFlip
+                {
+                    self.orientation = if white { Orientation::WhiteAtBottom } else { Orientation::BlackAtBottom };
+                }
+            }
+            Message::Game(variant, fen, san) if !hosting =>
+            {
+                let parsed = Game::from_variant_fen(variant, &fen).ok().and_then(|start|
+                {
+                    let mut game = start.clone();
+                    let mut moves = vec![];
+                    for text in &san
+                    {
+                        let parsed = game.parse_move(text).ok()?;
+                        game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+                        game.make_move(&parsed.from, &parsed.to);
+                        moves.push(parsed);
+                    }
+                    Some((start, moves))
+                });
+                match parsed
+                {
+                    Some((start, moves)) =>
+                    {
+                        let players = self.players;
+                        self.replay(start, &moves);
+                        self.players = players;
+                        // the host's clock comes with the next message
+                        self.clock = None;
+                    }
+                    None => self.move_input.message = Some("the host sent a game that can not be played".to_string()),
+                }
+            }
+            Message::Move(text) =>
+            {
+                let side = if self.game.is_white_turn() { 0 } else { 1 };
+                let parsed = self.game.parse_move(&text).ok().filter(|_| self.players[side] == Player::Remote && !self.is_over());
+                match parsed
+                {
+                    Some(parsed) =>
+                    {
+                        self.view(self.moves.len());
+                        self.game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+                        self.play_move(ctx, transform_input(&parsed.from), transform_input(&parsed.to), true);
+                        self.game.set_promotion("Q");
+                    }
+                    None => self.resync(),
+                }
+            }
+            Message::Clock(control, white, black) if !hosting => self.clock = Some(Clock::resume(control, [white, black])),
+            Message::Sync(fen) if fen != self.game.to_fen() =>
+            {
+                match Game::from_variant_fen(self.game.get_variant(), &fen)
+                {
+                    // the host's position counts, the history before it is lost
+                    Ok(game) if !hosting =>
+                    {
+                        let players = self.players;
+                        self.start_game(game);
+                        self.players = players;
+                    }
+                    _ => self.resync(),
+                }
+            }
+            Message::Resync if hosting => self.send_game(None),
+            Message::Resign if !self.is_over() => self.end_game(Ending::Resignation { white_wins: !remote_white }),
+            // the host says how the game ended, a draw claimed by the client is checked first
+            Message::Over(ending) if !hosting || self.claimable_draw() == Some(ending) => self.end_game(ending),
+            Message::Over(_) => self.resync(),
+            Message::DrawOffer if !self.is_over() =>
+            {
+                self.draw_offer = Some(remote_white);
+                self.move_input.message = Some("the opponent offers a draw".to_string());
+            }
+            Message::DrawAccept if self.draw_offer == Some(!remote_white) => self.end_game(Ending::Agreement),
+            Message::DrawDecline if self.draw_offer == Some(!remote_white) =>
+            {
+                self.draw_offer = None;
+                self.move_input.message = Some("the opponent declines the draw".to_string());
+            }
+            Message::Chat(text) => self.move_input.message = Some(format!("opponent: {}", text)),
+            Message::Bye(reason) =>
+            {
+                self.move_input.message = Some(format!("connection closed: {}", reason));
+                // a client told to leave stops trying to come back, a host waits for the next one
+                if !hosting { self.network = None; }
+            }
+            _ => (),
+        }
+    }
+
+    /// Acts on a message from a spectator of the game this window hosts, they may only ask for the game.
+    fn receive_from_spectator(&mut self, id: u64, message: Message)
+    {
+        let network = match &self.network
+        {
+            Some(network) => network,
+            None => return,
+        };
+
+        match message
+        {
+            Message::Watch(version) if version != PROTOCOL_VERSION =>
+            {
+                network.send_to(id, &Message::Bye(format!("protocol version {} is needed", PROTOCOL_VERSION)));
+            }
+            Message::Watch(_) =>
+            {
+                self.send_game(Some(id));
+                self.move_input.message = Some(format!("{} watching", self.network.as_ref().map_or(0, |network| network.spectator_count())));
+            }
+            Message::Resync => self.send_game(Some(id)),
+            _ => (),
+        }
+    }
+
+    fn send(&self, message: Message)
+    {
+        if let Some(network) = &self.network { network.send(&message); }
+    }
+
+    /// Sends the whole game, as the host, so the client or the spectator with the given number can take it over.
+    fn send_game(&self, spectator: Option<u64>)
+    {
+        let network = match &self.network
+        {
+            Some(network) => network,
+            None => return,
+        };
+        let send = |message: Message| match spectator
+        {
+            Some(id) => network.send_to(id, &message),
+            None => network.send(&message),
+        };
+
+        let san = self.moves.iter().map(|played| played.san.clone()).collect();
+        send(Message::Game(self.positions[0].get_variant(), self.positions[0].to_fen(), san));
+        if let Some(clock) = &self.clock
+        {
+            send(Message::Clock(clock.control(), clock.remaining()[0], clock.remaining()[1]));
+        }
+        send(Message::Sync(self.game.to_fen()));
+        if let Some(ended) = self.ended { send(Message::Over(ended)); }
+    }
+
+    /// Brings the two sides back in step after a message that did not fit the game, the host's game counts.
+    fn resync(&mut self)
+    {
+        let hosting = self.network.as_ref().is_some_and(|network| network.is_host());
+        if hosting { self.send_game(None); } else { self.send(Message::Resync); }
+    }
+
+    /// Ends the game off the board, the host tells its spectators how.
+    fn end_game(&mut self, ending: Ending)
+    {
+        if let Some(network) = self.network.as_ref().filter(|network| network.is_host())
+        {
+            network.tell_spectators(&Message::Over(ending));
+        }
+        self.ended = Some(ending);
+        self.draw_offer = None;
+    }
+
+    /// The game so far in PGN, with its result once it is decided.
+    fn to_pgn(&self) -> Pgn
+    {
+        let player_name = |player: Player| match player
+        {
+            Player::Human => "Player".to_string(),
+            Player::Engine => format!("Engine, depth {}", self.engine_depth),
+            Player::Remote => "Opponent".to_string(),
+        };
+        let ending = self.ending();
+
+        let mut tags = vec!
+        [
+            ("Event".to_string(), if self.network.is_some() { "Network game" } else { "Casual game" }.to_string()),
+            ("Site".to_string(), "chess-gui".to_string()),
+            ("Date".to_string(), "????.??.??".to_string()),
+            ("Round".to_string(), "-".to_string()),
+            ("White".to_string(), player_name(self.players[0])),
+            ("Black".to_string(), player_name(self.players[1])),
+            ("Result".to_string(), ending.map_or("*", |ending| ending.result()).to_string()),
+        ];
+        let variant = self.positions[0].get_variant();
+        if variant != Variant::Standard { tags.push(("Variant".to_string(), variant.to_string())); }
+        else if self.positions[0].is_chess960() { tags.push(("Variant".to_string(), "Chess960".to_string())); }
+        if self.positions[0].to_fen() != Game::new_variant(variant).to_fen()
+        {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), self.positions[0].to_fen()));
+        }
+        if let Some(ending) = ending { tags.push(("Termination".to_string(), ending.to_string())); }
+
+        Pgn
+        {
+            tags,
+            moves: self.moves.iter().map(|played| played.san.clone()).collect(),
+            result: Some(ending.map_or("*", |ending| ending.result()).to_string()),
+        }
+    }
+
+    /// Saves the game as a PGN file in the working directory, named after the time it was saved.
+    fn export_pgn(&mut self)
+    {
+        let seconds = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        let path = format!("game-{}.pgn", seconds);
+        self.move_input.message = Some(match std::fs::write(&path, self.to_pgn().to_string())
+        {
+            Ok(()) => format!("saved {}", path),
+            Err(error) => format!("could not save {}: {}", path, error),
+        });
+    }
+
+    /// True if moving the selected piece to a square takes something, including en passant.
+    fn is_capture(&self, to: (u32, u32)) -> bool
+    {
+        let from = match self.selected_piece
+        {
+            Some(from) => from,
+            None => return false,
+        };
+
+        if from.0 == POCKET_FILE { return false; }
+
+        // a Chess960 king castles onto its own rook
+        let (piece, target) = (self.piece_at(from), self.piece_at(to));
+        (target != '*' && target.is_uppercase() != piece.is_uppercase()) || (piece.eq_ignore_ascii_case(&'p') && from.0 != to.0)
+    }
+
+    /// Fills a grid cell with a (usually translucent) colour.
+    fn fill_cell(&self, ctx: &mut Context, cell: (u32, u32), color: graphics::Color) -> GameResult
+    {
+        let rectangle = graphics::Mesh::new_rectangle
+        (
+            ctx,
+            graphics::DrawMode::fill(),
+            self.layout.cell_rect(cell),
+            color,
+        )?;
+        graphics::draw(ctx, &rectangle, graphics::DrawParam::default())
+    }
+
+    /// Draws a piece sprite and its shadow with the shadow's top left corner at a window position.
+    fn draw_piece(&self, ctx: &mut Context, piece: char, position: (f32, f32)) -> GameResult
+    {
+        let sprite = self.pieces.sprites.get(&piece).unwrap();
+        // stretch the sprite, whatever its resolution, to cover one tile
+        let scale = self.layout.cell / sprite.width() as f32;
+
+        if let Some(shadow) = self.pieces.sprites.get(&'s')
+        {
+            graphics::draw
+            (
+                ctx,
+                shadow,
+                graphics::DrawParam::default()
+                    .scale([scale, scale]) 
+                    .dest([position.0, position.1]),
+            )?;
+        }
+        graphics::draw
+        (
+            ctx,
+            sprite,
+            graphics::DrawParam::default()
+                .scale([scale, scale]) 
+                .dest([position.0, position.1 - self.pieces.lift * scale]),
+        )
+    }
+
+    /// Draws a piece in a (fractional) grid cell.
+    fn draw_piece_in_cell(&self, ctx: &mut Context, piece: char, cell: (f32, f32)) -> GameResult
+    {
+        let position = self.layout.cell_position(cell);
+        self.draw_piece(ctx, piece, position)
+    }
+
+    /// Draws what each player has captured in the panel, on the same side of the board as their pieces.
+    /// In Crazyhouse the pieces each player holds to drop are shown instead.
+    fn draw_trays(&self, ctx: &mut Context) -> GameResult
+    {
+        let difference = self.position().material_difference();
+
+        for white in [true, false]
+        {
+            let top = self.tray_at_top(white);
+            // white captures black's lowercase pieces
+            let captured: Vec<char> = self.position().get_captured().iter().copied().filter(|piece| piece.is_lowercase() == white).collect();
+            let advantage = if white { difference } else { -difference };
+
+            // the clock takes the end of the tray, captured pieces wrap before it
+            let mut area = self.layout.tray_rect(top);
+            if let Some(clock) = &self.clock
+            {
+                let running = self.editor.is_none() && !self.moves.is_empty() && self.game.is_white_turn() == white;
+                clock.draw(ctx, &self.layout, self.theme(), area, white, running)?;
+                area.w -= (CLOCK_WIDTH + 0.1) * self.layout.cell;
+            }
+            // the three-check counter sits before the clock
+            if self.position().get_variant() == Variant::ThreeCheck
+            {
+                tray::draw_checks(ctx, &self.layout, self.theme(), area, self.position().get_checks()[if white { 0 } else { 1 }])?;
+            }
+
+            let area = self.tray_pieces_area(top);
+            if self.position().get_variant() == Variant::Crazyhouse
+            {
+                tray::draw_pocket(ctx, &self.layout, self.theme(), &self.pieces, area, &self.shown_pocket(white))?;
+            }
+            else
+            {
+                tray::draw(ctx, &self.layout, self.theme(), &self.pieces, area, &captured, advantage)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// True if a player's tray is drawn above the board, each side's tray is next to its pieces.
+    fn tray_at_top(&self, white: bool) -> bool
+    {
+        white == self.is_flipped()
+    }
+
+    /// Part of a tray left for pieces, before the clock and the Three-check counter at its end.
+    fn tray_pieces_area(&self, top: bool) -> graphics::Rect
+    {
+        let mut area = self.layout.tray_rect(top);
+        if self.clock.is_some() { area.w -= (CLOCK_WIDTH + 0.1) * self.layout.cell; }
+        if self.position().get_variant() == Variant::ThreeCheck { area.w -= (tray::CHECKS_WIDTH + 0.1) * self.layout.cell; }
+        area
+    }
+
+    /// The pieces a player holds to drop in the shown position, in their colour, without one being dragged out.
+    fn shown_pocket(&self, white: bool) -> Vec<(char, u32)>
+    {
+        let pocket = self.position().get_pocket(white);
+        let held = self.dragging.as_ref().filter(|drag| drag.moved && drag.square.0 == POCKET_FILE).map(|drag| drag.piece);
+        POCKET_LETTERS
+            .iter()
+            .zip(pocket)
+            .map(|(letter, count)|
+            {
+                let piece = if white { *letter } else { letter.to_ascii_lowercase() };
+                (piece, if held == Some(piece) { count.saturating_sub(1) } else { count })
+            })
+            .collect()
+    }
+
+    /// Draws file letters below the board and rank numbers on both sides of it,
+    /// centered in the margin cell next to the row or column they name.
+    fn draw_coordinates(&self, ctx: &mut Context) -> GameResult
+    {
+        let half_cell = self.layout.cell / 2.0;
+
+        for i in 0..8
+        {
+            let file = (b'a' + self.cell_to_square((i, 0)).0 as u8) as char;
+            let rank = (self.cell_to_square((0, i)).1 + 1).to_string();
+
+            let file_text = graphics::Text::new
+            (
+                graphics::TextFragment::from(file)
+                    .font(self.theme().font)
+                    .scale(self.layout.text_scale(LABEL_SCALE)),
+            );
+            let rank_text = graphics::Text::new
+            (
+                graphics::TextFragment::from(rank)
+                    .font(self.theme().font)
+                    .scale(self.layout.text_scale(LABEL_SCALE)),
+            );
+            let file_dimensions = file_text.dimensions(ctx);
+            let rank_dimensions = rank_text.dimensions(ctx);
+
+            // file letters go in the margin cell below column i
+            let below = self.layout.cell_position((i as f32, 8.0));
+            graphics::draw
+            (
+                ctx,
+                &file_text,
+                graphics::DrawParam::default()
+                    .color(self.theme().text)
+                    .dest
+                    ([
+                        below.0 + half_cell - file_dimensions.w / 2.0,
+                        below.1 + half_cell - file_dimensions.h / 2.0,
+                    ]),
+            )?;
+
+            // rank numbers go in the margin cells left and right of row i
+            for column in [-1.0, 8.0]
             {
-                (*piece, graphics::Image::new(ctx, path).unwrap())
-            })
-            .collect::<HashMap<char, graphics::Image>>()
+                let beside = self.layout.cell_position((column, i as f32));
+                graphics::draw
+                (
+                    ctx,
+                    &rank_text,
+                    graphics::DrawParam::default()
+                        .color(self.theme().text)
+                        .dest
+                        ([
+                            beside.0 + half_cell - rank_dimensions.w / 2.0,
+                            beside.1 + half_cell - rank_dimensions.h / 2.0,
+                        ]),
+                )?;
+            }
+        }
+
+        Ok(())
     }
 }
 
+/// A square like "E2" as (file, rank), a Crazyhouse drop like "N@" as the pocket square of that piece.
 fn transform_input(input_pos: &str) -> (u32, u32) 
 {
+    if let Some(index) = POCKET_LETTERS.iter().position(|letter| input_pos == format!("{}@", letter))
+    {
+        return (POCKET_FILE, index as u32);
+    }
     let mut chars_iter = input_pos.chars();
     (
         chars_iter
@@ -93,29 +1498,70 @@ fn transform_input(input_pos: &str) -> (u32, u32)
 
 fn transform_back(file_input: u32, rank_input: u32) -> String 
 {
-    return ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H']
+    if file_input == POCKET_FILE
+    {
+        return format!("{}@", POCKET_LETTERS[rank_input as usize]);
+    }
+    ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H']
         .get(file_input as usize)
         .expect("Error on file_rank")
         .to_string()
-        + &(rank_input + 1).to_string();
+        + &(rank_input + 1).to_string()
 }
 
 impl event::EventHandler<GameError> for AppState 
 {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult 
+    fn update(&mut self, ctx: &mut Context) -> GameResult 
     {
+        let delta = ggez::timer::delta(ctx).as_secs_f32();
+
+        for animation in self.animations.iter_mut()
+        {
+            animation.progress = (animation.progress + delta / ANIMATION_TIME).min(1.0);
+        }
+        self.animations.retain(|animation| animation.progress < 1.0);
+        for explosion in self.explosions.iter_mut()
+        {
+            explosion.progress = (explosion.progress + delta / EXPLOSION_TIME).min(1.0);
+        }
+        self.explosions.retain(|explosion| explosion.progress < 1.0);
+
+        self.handle_network(ctx);
+
+        // the clock starts with the first move and stops when the game is decided or the opponent is away
+        let running = self.editor.is_none() && !self.moves.is_empty() && !self.is_over()
+            && self.network.as_ref().is_none_or(|network| network.is_connected());
+        let white_to_move = self.game.is_white_turn();
+        if let Some(clock) = self.clock.as_mut().filter(|_| running) { clock.tick(white_to_move, delta); }
+
+        self.run_engine(ctx);
+
+        self.since_autosave += delta;
+        if self.since_autosave >= AUTOSAVE_INTERVAL { self.autosave(); }
+
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult 
     {
-        graphics::clear(ctx, [0.27, 0.20, 0.24, 1.0].into());
+        graphics::clear(ctx, self.theme().background);
 
         // create text representation
         let state_text = graphics::Text::new
         (
-            graphics::TextFragment::from(format!("Game is {:?}.", self.game.get_game_state()))
-                .scale(graphics::PxScale { x: 30.0, y: 30.0 }),
+            graphics::TextFragment::from(match (&self.editor, self.ending())
+            {
+                (Some(_), _) => "Setting up a position.".to_string(),
+                (None, Some(ending)) => ending.to_string(),
+                (None, None) => match self.network.as_ref().filter(|network| !network.is_connected())
+                {
+                    Some(network) if network.is_spectator() => "Waiting for the host.".to_string(),
+                    Some(_) => "Waiting for the opponent.".to_string(),
+                    None => format!("Game is {:?}.", self.position().get_game_state()),
+                },
+            })
+                .font(self.theme().font)
+                .scale(self.layout.text_scale(30.0)),
         );
 
         // get size of text
@@ -127,8 +1573,8 @@ impl event::EventHandler<GameError> for AppState
             graphics::DrawMode::fill(),
             graphics::Rect::new
             (
-                (SCREEN_SIZE.0 - text_dimensions.w as f32) / 2f32 as f32 - 8.0,
-                (SCREEN_SIZE.0 - text_dimensions.h as f32) / 2f32 as f32,
+                self.layout.board_center_x() - text_dimensions.w as f32 / 2.0 - 8.0,
+                (self.layout.screen.1 - text_dimensions.h as f32) / 2.0,
                 text_dimensions.w as f32 + 16.0,
                 text_dimensions.h as f32,
             ),
@@ -144,107 +1590,181 @@ impl event::EventHandler<GameError> for AppState
         {
             for col in 0..8 
             {
+                // colour by square rather than cell so A1 stays dark when the board is flipped
+                let square = self.cell_to_square((col, row));
+
                 // draw tile
                 let rectangle = graphics::Mesh::new_rectangle
                 (
                     ctx,
                     graphics::DrawMode::fill(),
-                    graphics::Rect::new_i32
-                    (
-                        (col + 1) * GRID_CELL_SIZE.0 as i32,
-                        (row + 1) * GRID_CELL_SIZE.1 as i32,
-                        GRID_CELL_SIZE.0 as i32,
-                        GRID_CELL_SIZE.1 as i32,
-                    ),
-                    match col % 2 
-                    {
-                        0 => {
-                            if row % 2 == 0 
-                            {
-                                WHITE
-                            } else {
-                                BLACK
-                            }
-                        }
-                        _ => {
-                            if row % 2 == 0 
-                            {
-                                BLACK
-                            } else {
-                                WHITE
-                            }
-                        }
-                    },
+                    self.layout.cell_rect((col, row)),
+                    if (square.0 + square.1).is_multiple_of(2) { self.theme().dark } else { self.theme().light },
                 )
                 .expect("Failed to create tile.");
                 graphics::draw(ctx, &rectangle, graphics::DrawParam::default())
                     .expect("Failed to draw tiles.");
             }
         }
+
+        self.draw_coordinates(ctx).expect("Failed to draw coordinates.");
         
-        for i in 0..self.possibleMoves.len()
+        if let Some((from, to)) = self.shown_last_move().filter(|_| self.editor.is_none())
+        {
+            // a drop came from the pocket, off the board
+            if from.0 != POCKET_FILE
+            {
+                self.fill_cell(ctx, self.square_to_cell(from), self.theme().last_move).expect("Failed to draw last move.");
+            }
+            self.fill_cell(ctx, self.square_to_cell(to), self.theme().last_move).expect("Failed to draw last move.");
+        }
+
+        if let Some(king) = self.position().checked_king().filter(|_| self.editor.is_none())
+        {
+            self.fill_cell(ctx, self.square_to_cell(transform_input(&king)), self.theme().check).expect("Failed to draw check.");
+        }
+
+        for i in 0..self.possible_moves.len()
+        {
+            let highlight = self.square_to_cell(self.possible_moves[i]);
+
+            if self.is_capture(self.possible_moves[i])
+            {
+                // ring the piece that can be taken
+                let ring = graphics::Mesh::new_circle
+                (
+                    ctx,
+                    graphics::DrawMode::stroke(self.layout.cell / 12.0),
+                    [
+                        self.layout.cell_rect(highlight).x + self.layout.cell / 2.0,
+                        self.layout.cell_rect(highlight).y + self.layout.cell / 2.0,
+                    ],
+                    self.layout.cell * 0.42,
+                    0.5,
+                    self.theme().highlight,
+                )
+                .expect("Failed to create ring.");
+                graphics::draw(ctx, &ring, graphics::DrawParam::default())
+                    .expect("Failed to draw ring.");
+            }
+            else
+            {
+                self.fill_cell(ctx, highlight, self.theme().highlight).expect("Failed to draw tiles.");
+            }
+        }
+
+        let board: Vec<char> = self.shown_board().chars().filter(|c| *c != '\n').collect();
+
+        // draw rows top to bottom so pieces overlap the ones behind them
+        for y in 0..8
+        {
+            for x in 0..8
+            {
+                let square = self.cell_to_square((x, y));
+                let c = board[((7 - square.1) * 8 + square.0) as usize];
+
+                // held and sliding pieces are drawn on top of the board instead
+                let held = self.dragging.as_ref().is_some_and(|drag| drag.moved && drag.square == square);
+                if held || self.animations.iter().any(|animation| animation.to == square) { continue; }
+
+                // draw piece
+                if self.pieces.sprites.contains_key(&c)
+                {
+                    self.draw_piece_in_cell(ctx, c, (x as f32, y as f32))
+                        .expect("Failed to draw piece.");
+                }
+            }
+        }
+
+        for animation in &self.animations
+        {
+            // ease in and out so the slide starts and lands softly
+            let t = animation.progress * animation.progress * (3.0 - 2.0 * animation.progress);
+            let from = self.board_point_to_cell(animation.from);
+            let to = self.square_to_cell(animation.to);
+            let cell = (from.0 + (to.0 as f32 - from.0) * t, from.1 + (to.1 as f32 - from.1) * t);
+
+            self.draw_piece_in_cell(ctx, animation.piece, cell)
+                .expect("Failed to draw moving piece.");
+        }
+
+        for explosion in &self.explosions
         {
-            let highlight = self.possibleMoves[i];
-            // draw tile
-            let rectangle = graphics::Mesh::new_rectangle
+            // the blown up squares flash and fade while a burst spreads from the capture
+            let mut flash = self.theme().check;
+            flash.a *= 1.0 - explosion.progress;
+            for square in &explosion.squares
+            {
+                self.fill_cell(ctx, self.square_to_cell(*square), flash).expect("Failed to draw explosion.");
+            }
+
+            let centre = self.layout.cell_rect(self.square_to_cell(explosion.centre));
+            let burst = graphics::Mesh::new_circle
             (
                 ctx,
                 graphics::DrawMode::fill(),
-                graphics::Rect::new_i32
+                [centre.x + centre.w / 2.0, centre.y + centre.h / 2.0],
+                self.layout.cell * (0.3 + 1.2 * explosion.progress),
+                0.5,
+                flash,
+            )
+            .expect("Failed to create explosion.");
+            graphics::draw(ctx, &burst, graphics::DrawParam::default())
+                .expect("Failed to draw explosion.");
+        }
+
+        if let Some(drag) = self.dragging.as_ref().filter(|drag| drag.moved)
+        {
+            // hold the piece by its center
+            self.draw_piece
+            (
+                ctx,
+                drag.piece,
                 (
-                    (highlight.0 + 1) as i32 * GRID_CELL_SIZE.0 as i32,
-                    (highlight.1 + 1) as i32 * GRID_CELL_SIZE.1 as i32,
-                    GRID_CELL_SIZE.0 as i32,
-                    GRID_CELL_SIZE.1 as i32,
+                    drag.position.0 - self.layout.cell / 2.0,
+                    drag.position.1 - self.layout.cell / 2.0,
                 ),
-                HIGHLIGHT,
             )
-            .expect("Failed to create tile.");
-            graphics::draw(ctx, &rectangle, graphics::DrawParam::default())
-                .expect("Failed to draw tiles.");
+            .expect("Failed to draw held piece.");
         }
 
-        let board = self.game.get_board();
+        // darken the side panel a little so it reads as one block
+        let panel = graphics::Mesh::new_rectangle
+        (
+            ctx,
+            graphics::DrawMode::fill(),
+            self.layout.panel_rect(),
+            [0.0, 0.0, 0.0, 0.2].into(),
+        )
+        .expect("Failed to create panel.");
+        graphics::draw(ctx, &panel, graphics::DrawParam::default())
+            .expect("Failed to draw panel.");
 
-        let mut i = 0;
-        for c in board.chars()
+        if let Some(editor) = &self.editor
         {
-            let x = i % 8;
-            let y = 7 - (i / 8);
-            if c != '\n' { i += 1; }
-            
-            // draw piece
-            if self.sprites.contains_key(&c)
-            {
-                graphics::draw
-                (
-                    ctx,
-                    self.sprites.get(&'s').unwrap(),
-                    graphics::DrawParam::default()
-                        .scale([4.0, 4.0]) 
-                        .dest
-                        ([
-                            (x + 1) as f32 * GRID_CELL_SIZE.0 as f32,
-                            (y + 1) as f32 * GRID_CELL_SIZE.1 as f32,
-                        ]),
-                )
-                .expect("Failed to draw shadow.");
-                graphics::draw
-                (
-                    ctx,
-                    self.sprites.get(&c).unwrap(),
-                    graphics::DrawParam::default()
-                        .scale([4.0, 4.0]) 
-                        .dest
-                        ([
-                            (x + 1) as f32 * GRID_CELL_SIZE.0 as f32,
-                            ((y + 1) as f32 * GRID_CELL_SIZE.1 as f32) - 14.0 * 4.0,
-                        ]),
-                )
-                .expect("Failed to draw piece.");
-            }
+            // the editor takes the whole panel
+            editor.draw(ctx, &self.layout, self.theme(), &self.pieces).expect("Failed to draw editor.");
         }
+        else if let Some(saved) = &self.saved_game
+        {
+            autosave::draw_offer(ctx, &self.layout, self.theme(), saved).expect("Failed to draw resume offer.");
+        }
+        else if self.settings_open
+        {
+            settings_screen::draw(ctx, &self.layout, self.theme(), |button| self.settings_label(button))
+                .expect("Failed to draw settings.");
+        }
+        else
+        {
+            self.draw_trays(ctx).expect("Failed to draw captured pieces.");
 
+            let san: Vec<String> = self.moves.iter().map(|played| played.san.clone()).collect();
+            self.move_list.draw(ctx, &self.layout, self.theme(), &san, self.current_ply(), self.viewing.is_none())
+                .expect("Failed to draw move list.");
+            actions::draw(ctx, &self.layout, self.theme(), |action| self.action_label(action))
+                .expect("Failed to draw game actions.");
+            self.move_input.draw(ctx, &self.layout, self.theme()).expect("Failed to draw move box.");
+        }
 
         // draw text with dark gray colouring and center position
         graphics::draw
@@ -252,10 +1772,10 @@ impl event::EventHandler<GameError> for AppState
             ctx,
             &state_text,
             graphics::DrawParam::default()
-                .color([1.0, 0.95, 0.85, 1.0].into())
+                .color(self.theme().text)
                 .dest(ggez::mint::Point2 {
-                    x: (SCREEN_SIZE.0 - text_dimensions.w as f32) / 2f32 as f32,
-                    y: (28.0 as f32) / 2f32 as f32,
+                    x: self.layout.board_center_x() - text_dimensions.w as f32 / 2.0,
+                    y: self.layout.origin.1 + (28.0 * self.layout.scale()) / 2.0,
                 }),
         )
         .expect("Failed to draw text.");
@@ -266,8 +1786,9 @@ impl event::EventHandler<GameError> for AppState
         Ok(())
     }
 
-    /// Update game on mouse click
-    fn mouse_button_up_event
+    /// Pick up a piece, or finish a move started by clicking a piece, or jump to a move in the list,
+    /// or place pieces while setting up a position, or change a setting
+    fn mouse_button_down_event
     (
         &mut self,
         ctx: &mut Context,
@@ -276,108 +1797,405 @@ impl event::EventHandler<GameError> for AppState
         y: f32,
     ) 
     {
-        if button == event::MouseButton::Left 
+        if self.editor.is_some()
         {
-            let anySquare = (x / GRID_CELL_SIZE.0 as f32, y / GRID_CELL_SIZE.1 as f32);
-            if anySquare.0 <= 0.0 || anySquare.0 >= 9.0 || anySquare.1 <= 0.0 || anySquare.1 >= 9.0 { return; };
+            self.edit_click(button, x, y);
+            return;
+        }
 
-            let square = ((anySquare.0 - 1.0) as u32, (anySquare.1 - 1.0) as u32);
+        if button != event::MouseButton::Left { return; }
 
-            for i in 0..self.possibleMoves.len()
+        if self.saved_game.is_some()
+        {
+            // nothing else can be clicked until the player has decided about the saved game
+            match autosave::button_at(&self.layout, x, y)
             {
-                let mv = self.possibleMoves[i];
-                if mv.0 == square.0 && mv.1 == square.1
-                {
-                    self.game.make_move
-                    (
-                        &transform_back(self.selectedPiece.unwrap().0, self.selectedPiece.unwrap().1),
-                        &transform_back(square.0, square.1)
-                    );
-                    self.selectedPiece = None;
-                    self.possibleMoves.clear();
-                    return;
-                }
+                Some(ResumeButton::Resume) => self.resume_saved_game(),
+                Some(ResumeButton::NewGame) => self.saved_game = None,
+                None => (),
+            }
+            return;
+        }
+
+        if self.settings_open
+        {
+            // the board stays playable, only the panel shows the settings
+            if self.layout.panel_rect().contains([x, y])
+            {
+                self.settings_click(ctx, x, y);
+                return;
             }
+        }
+
+        // clicking the move box starts typing, clicking anywhere else stops
+        self.move_input.focused = MoveInput::contains(&self.layout, x, y);
+        if self.move_input.focused { return; }
+
+        if let Some(action) = actions::button_at(&self.layout, x, y)
+        {
+            // spectators see the buttons, but never anyone to act for
+            if self.action_label(action).1 { self.act(action); }
+            return;
+        }
+
+        if let Some(ply) = self.move_list.ply_at(&self.layout, self.moves.len(), x, y)
+        {
+            self.view(ply);
+            return;
+        }
+
+        if self.viewing.is_some()
+        {
+            // the history is read only, only the button leads back to the game
+            if MoveList::live_button_rect(&self.layout).contains([x, y]) { self.view(self.moves.len()); }
+            return;
+        }
 
-            let clickedPiece: char = self.game.get_board().as_bytes()[(square.0 + (7 - square.1) * 9) as usize] as char;
+        // nothing can be picked up while the engine or the opponent is to move or once the game is decided
+        if !self.human_to_move()
+        {
+            self.clear_selection();
+            return;
+        }
 
-            //println!("{}", clickedPiece);
+        // in Crazyhouse a piece is picked out of the pocket of the side to move like off a square
+        let white = self.game.is_white_turn();
+        let pocket_piece = Some(self.tray_pieces_area(self.tray_at_top(white)))
+            .filter(|_| self.game.get_variant() == Variant::Crazyhouse)
+            .and_then(|area| tray::pocket_piece_at(&self.layout, area, &self.shown_pocket(white), x, y));
+        if let Some(piece) = pocket_piece
+        {
+            let index = POCKET_LETTERS.iter().position(|letter| *letter == piece.to_ascii_uppercase()).unwrap() as u32;
+            self.pick_up((POCKET_FILE, index), piece, (x, y));
+            return;
+        }
 
-            if clickedPiece == '*'
+        let square = match self.layout.cell_at(x, y)
+        {
+            Some(cell) => self.cell_to_square(cell),
+            None =>
             {
-                self.selectedPiece = None;
-                self.possibleMoves.clear();
+                self.clear_selection();
                 return;
             }
+        };
 
-            if clickedPiece.is_uppercase()
+        if let Some(from) = self.selected_piece
+        {
+            if self.possible_moves.contains(&square)
             {
-                if !self.game.is_white_turn()
-                { 
-                    self.selectedPiece = None;
-                    self.possibleMoves.clear();
-                    return; 
-                }
+                self.play_move(ctx, from, square, true);
+                return;
             }
-            else 
+        }
+
+        let clicked_piece = self.piece_at(square);
+
+        if clicked_piece == '*' || clicked_piece.is_uppercase() != self.game.is_white_turn()
+        {
+            self.clear_selection();
+            return;
+        }
+
+        self.pick_up(square, clicked_piece, (x, y));
+    }
+
+    /// Move the held piece with the cursor
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32)
+    {
+        if let Some(drag) = self.dragging.as_mut()
+        {
+            drag.position = (x, y);
+            if (x - drag.start.0).abs() > DRAG_THRESHOLD || (y - drag.start.1).abs() > DRAG_THRESHOLD
             {
-                if self.game.is_white_turn()
-                { 
-                    self.selectedPiece = None;
-                    self.possibleMoves.clear();
-                    return; 
-                }
+                drag.moved = true;
             }
-            
-            if self.selectedPiece.is_some()
+        }
+    }
+
+    /// Drop the held piece, it snaps back to its square if the drop is illegal
+    fn mouse_button_up_event
+    (
+        &mut self,
+        ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
+    ) 
+    {
+        if button != event::MouseButton::Left { return; }
+
+        let drag = match self.dragging.take()
+        {
+            Some(drag) => drag,
+            None => return,
+        };
+
+        if !drag.moved
+        {
+            // clicking a selected piece again puts it down
+            if drag.was_selected { self.clear_selection(); }
+            return;
+        }
+
+        let target = self.layout.cell_at(x, y).map(|cell| self.cell_to_square(cell));
+        match target
+        {
+            Some(to) if self.possible_moves.contains(&to) =>
             {
-                if self.selectedPiece.unwrap().0 == square.0 && self.selectedPiece.unwrap().1 == square.1 
-                { 
-                    self.selectedPiece = None;
-                    self.possibleMoves.clear();
-                    return;
-                }
+                self.play_move(ctx, drag.square, to, false);
+            }
+            // a piece taken out of the pocket goes straight back
+            _ if drag.square.0 == POCKET_FILE => (),
+            _ =>
+            {
+                // slide back from where the held piece was let go
+                let released = self.layout.position_to_cell
+                (
+                    x - self.layout.cell / 2.0,
+                    y - self.layout.cell / 2.0,
+                );
+                self.animations.push(Animation
+                {
+                    piece: drag.piece,
+                    from: self.board_point_to_cell(released),
+                    to: drag.square,
+                    progress: 0.0,
+                });
+            }
+        }
+    }
+
+    /// Scroll the move list
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32)
+    {
+        // wheel up shows earlier moves
+        let rows = if y > 0.0 { -1 } else if y < 0.0 { 1 } else { 0 };
+        self.move_list.scroll(&self.layout, self.moves.len(), rows);
+    }
+
+    /// Cycle board orientation, theme and piece set with their keys, step through the moves with the arrows,
+    /// open and close the board editor or the settings, or edit the move box while it is focused
+    fn key_down_event
+    (
+        &mut self,
+        ctx: &mut Context,
+        keycode: event::KeyCode,
+        _keymods: event::KeyMods,
+        _repeat: bool,
+    )
+    {
+        if self.move_input.focused
+        {
+            match keycode
+            {
+                event::KeyCode::Return | event::KeyCode::NumpadEnter => self.submit_typed_move(ctx),
+                event::KeyCode::Back => self.move_input.backspace(),
+                event::KeyCode::Escape => self.move_input.focused = false,
+                _ => (),
             }
+            return;
+        }
 
-            self.selectedPiece = Some(square);
+        if self.saved_game.is_some()
+        {
+            match keycode
+            {
+                event::KeyCode::Return | event::KeyCode::NumpadEnter => self.resume_saved_game(),
+                event::KeyCode::Escape => self.saved_game = None,
+                _ => (),
+            }
+            return;
+        }
 
-            self.possibleMoves.clear();
+        if self.editor.is_some()
+        {
+            match keycode
+            {
+                FLIP_KEY => self.next_orientation(),
+                THEME_KEY => self.next_theme(),
+                PIECE_SET_KEY => self.next_piece_set(ctx).expect("Failed to load any piece set."),
+                event::KeyCode::Return | event::KeyCode::NumpadEnter => self.play_edited_position(),
+                EDIT_KEY | event::KeyCode::Escape => self.editor = None,
+                _ => (),
+            }
+            return;
+        }
 
-            let moves = self.game.get_possible_moves(&transform_back(square.0, square.1));
-            if moves.is_some()
+        match keycode
+        {
+            event::KeyCode::Return | event::KeyCode::NumpadEnter => self.move_input.focused = true,
+            FLIP_KEY => self.next_orientation(),
+            THEME_KEY => self.next_theme(),
+            PIECE_SET_KEY => self.next_piece_set(ctx).expect("Failed to load any piece set."),
+            // a position set up here would leave the opponent behind
+            EDIT_KEY if self.network.is_some() => self.move_input.message = Some("no editing in a network game".to_string()),
+            EDIT_KEY =>
             {
-                let m = moves.unwrap();
-                for i in m
-                {
-                    self.possibleMoves.push(transform_input(&i));
-                }
+                self.settings_open = false;
+                self.open_editor();
             }
+            SETTINGS_KEY => self.settings_open = !self.settings_open,
+            EXPORT_KEY => self.export_pgn(),
+            event::KeyCode::Escape if self.settings_open => self.settings_open = false,
+            event::KeyCode::Left => self.view(self.current_ply().saturating_sub(1)),
+            event::KeyCode::Right => self.view(self.current_ply() + 1),
+            event::KeyCode::Home => self.view(0),
+            event::KeyCode::End => self.view(self.moves.len()),
+            // keep ggez's default of quitting on escape
+            event::KeyCode::Escape => event::quit(ctx),
+            _ => (),
         }
     }
+
+    /// Type into the move box
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char)
+    {
+        self.move_input.push(character);
+    }
+
+    /// Fit the board to the new window size
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32)
+    {
+        // keep one unit per pixel instead of stretching the old coordinates
+        graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))
+            .expect("Failed to resize screen.");
+        self.layout = Layout::new(width, height);
+        self.move_list.follow(&self.layout, self.current_ply());
+        // written when the window closes rather than for every step of the resize
+        self.settings.window_size = (width, height);
+    }
+
+    /// Save the settings, including the window size, and the game before the window closes
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool
+    {
+        self.save_settings();
+        self.autosave();
+        self.send(Message::Bye("the opponent left".to_string()));
+        if let Some(network) = &self.network { network.tell_spectators(&Message::Bye("the host left".to_string())); }
+        false
+    }
 }
 
 pub fn main() -> GameResult 
 {
-    let resource_dir = path::PathBuf::from("./res");
+    // the command line is read on top of the saved settings
+    let settings = Settings::load();
 
-    let context_builder = ContextBuilder::new("schack", "melvin")
-        .add_resource_path(resource_dir) 
-        .window_setup
-        (
-            conf::WindowSetup::default()
-                .title("Schack med gulliga svampar") // Set window title "Schack"
-                .icon("/icon.png"), // Set application icon
-        )
+    // everything on the command line is checked before a window is opened
+    let options = match Options::parse(env::args().skip(1), &settings)
+    {
+        Ok(options) => options,
+        Err(error) =>
+        {
+            eprintln!("{}\n\n{}", error, cli::USAGE);
+            // the usage error status of sysexits.h, apart from the headless results
+            std::process::exit(64);
+        }
+    };
+    if options.help
+    {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    // playing from stdin never opens a window, so scripts can run where there is no display
+    if options.headless
+    {
+        let format = if options.print_fen { headless::Output::Fen } else { headless::Output::Board };
+        let stdin = std::io::stdin();
+        match headless::run(options.current_game(), stdin.lock(), &mut std::io::stdout(), format)
+        {
+            Ok(outcome) => std::process::exit(outcome.exit_code()),
+            Err(error) =>
+            {
+                eprintln!("Failed to play from stdin: {}", error);
+                std::process::exit(5);
+            }
+        }
+    }
+
+    // a position from the command line is played instead of the saved game
+    let saved_game = if options.start_given || options.network.is_some()
+    {
+        None
+    }
+    else
+    {
+        SavedGame::load().unwrap_or_else(|error|
+        {
+            eprintln!("Ignoring saved game in {}", error);
+            None
+        })
+    };
+
+    // the network is set up before the window, a port that is taken stops the game from starting
+    let network = match &options.network
+    {
+        Some(Role::Host(port)) => match Network::host(*port)
+        {
+            Ok(network) =>
+            {
+                let port = network.local_address().map_or(*port, |address| address.port());
+                println!("Waiting for an opponent, they join with --join <this computer's address>:{}", port);
+                Some(network)
+            }
+            Err(error) =>
+            {
+                eprintln!("Failed to listen on port {}: {}", port, error);
+                std::process::exit(1);
+            }
+        },
+        Some(Role::Join(address)) => Some(Network::join(address.clone(), false)),
+        Some(Role::Watch(address)) => Some(Network::join(address.clone(), true)),
+        None => None,
+    };
+
+    let resource_dir = resources::find_resource_dir();
+    let mut window_setup = conf::WindowSetup::default().title("Schack med gulliga svampar"); // Set window title "Schack"
+    if resource_dir.as_ref().is_some_and(|dir| dir.join("icon.png").is_file())
+    {
+        window_setup = window_setup.icon("/icon.png"); // Set application icon
+    }
+
+    // a window smaller than the minimum would be enlarged anyway
+    let size = (options.window_size.0.max(MIN_SCREEN_SIZE.0), options.window_size.1.max(MIN_SCREEN_SIZE.1));
+
+    let mut context_builder = ContextBuilder::new("schack", "melvin")
+        .window_setup(window_setup)
         .window_mode
         (
             conf::WindowMode::default()
-                .dimensions(SCREEN_SIZE.0, SCREEN_SIZE.1) // Set window dimensions
-                .resizable(false), // Fixate window size
+                .dimensions(size.0, size.1) // Set window dimensions
+                .min_dimensions(MIN_SCREEN_SIZE.0, MIN_SCREEN_SIZE.1) // Keep pieces readable
+                .resizable(true), // Layout follows the window size
         );
-    let (mut contex, mut event_loop) = context_builder.build().expect("Failed to build context.");
+    if let Some(dir) = &resource_dir
+    {
+        context_builder = context_builder.add_resource_path(dir);
+    }
+
+    let (mut contex, event_loop) = match context_builder.build()
+    {
+        Ok(built) => built,
+        Err(error) =>
+        {
+            eprintln!("Failed to open the game window: {}", resources::describe(&error));
+            std::process::exit(1);
+        }
+    };
 
     graphics::set_default_filter(&mut contex, graphics::FilterMode::Nearest);
 
-    let state = AppState::new(&mut contex).expect("Failed to create state.");
-    event::run(contex, event_loop, state) // Run window event loop
+    match AppState::new(&mut contex, &options, settings, saved_game, network)
+    {
+        Ok(state) => event::run(contex, event_loop, state), // Run window event loop
+        Err(error) =>
+        {
+            // show what is wrong in the window as well, the terminal is often hidden
+            let report = resources::failure_report(&error, resource_dir.as_deref());
+            eprintln!("{}", report.join("\n"));
+            event::run(contex, event_loop, ErrorScreen::new(report))
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use crate::layout::Layout;
+use crate::theme::Theme;
+use crate::ui::INPUT_SCALE;
+
+use ggez::{graphics, Context, GameResult};
+
+/// Longest text accepted, more than any move notation needs and enough for a line of chat.
+const MAX_LENGTH: usize = 60;
+
+/// Text box below the move list for typing moves instead of dragging pieces.
+/// It takes the keyboard while focused, so the single key shortcuts do not fire while typing.
+pub struct MoveInput
+{
+    pub text: String,
+    pub focused: bool,
+    // Why the last typed move was not played.
+    pub message: Option<String>,
+}
+
+impl MoveInput
+{
+    pub fn new() -> MoveInput
+    {
+        MoveInput { text: String::new(), focused: false, message: None }
+    }
+
+    /// The box the text is typed in, the message line is above it.
+    fn field_rect(layout: &Layout) -> graphics::Rect
+    {
+        let area = layout.input_rect();
+        let height = area.h / 2.0;
+        graphics::Rect::new(area.x, area.y + area.h - height, area.w, height)
+    }
+
+    pub fn contains(layout: &Layout, x: f32, y: f32) -> bool
+    {
+        MoveInput::field_rect(layout).contains([x, y])
+    }
+
+    pub fn push(&mut self, character: char)
+    {
+        if !self.focused || character.is_control() || self.text.chars().count() >= MAX_LENGTH { return; }
+
+        self.text.push(character);
+        self.message = None;
+    }
+
+    pub fn backspace(&mut self)
+    {
+        self.text.pop();
+        self.message = None;
+    }
+
+    pub fn draw(&self, ctx: &mut Context, layout: &Layout, theme: &Theme) -> GameResult
+    {
+        let area = layout.input_rect();
+        let field = MoveInput::field_rect(layout);
+        let padding = field.h / 4.0;
+        let text = |content: &str|
+        {
+            graphics::Text::new
+            (
+                graphics::TextFragment::from(content)
+                    .font(theme.font)
+                    .scale(layout.text_scale(INPUT_SCALE)),
+            )
+        };
+
+        if let Some(message) = &self.message
+        {
+            let message_text = text(message);
+            let dimensions = message_text.dimensions(ctx);
+            // long messages shrink to fit the panel rather than run under the window edge
+            let fit = (field.w / dimensions.w).min(1.0);
+            graphics::draw
+            (
+                ctx,
+                &message_text,
+                graphics::DrawParam::default()
+                    .color(theme.check)
+                    .scale([fit, fit])
+                    .dest([area.x, area.y + (area.h / 2.0 - dimensions.h * fit) / 2.0]),
+            )?;
+        }
+
+        let background = graphics::Mesh::new_rectangle
+        (
+            ctx,
+            graphics::DrawMode::fill(),
+            field,
+            if self.focused { [0.0, 0.0, 0.0, 0.45].into() } else { [0.0, 0.0, 0.0, 0.25].into() },
+        )?;
+        graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+
+        let (content, color) = match (self.text.is_empty(), self.focused)
+        {
+            (true, false) => ("Enter: type a move".to_string(), graphics::Color::new(theme.text.r, theme.text.g, theme.text.b, 0.5)),
+            // a bar stands in for the cursor, the text is always edited at its end
+            (_, true) => (format!("{}|", self.text), theme.text),
+            (false, false) => (self.text.clone(), theme.text),
+        };
+        let field_text = text(&content);
+        let dimensions = field_text.dimensions(ctx);
+        // chat lines are longer than moves and shrink like the messages
+        let fit = ((field.w - 2.0 * padding) / dimensions.w).min(1.0);
+        graphics::draw
+        (
+            ctx,
+            &field_text,
+            graphics::DrawParam::default()
+                .color(color)
+                .scale([fit, fit])
+                .dest([field.x + padding, field.y + (field.h - dimensions.h * fit) / 2.0]),
+        )
+    }
+}
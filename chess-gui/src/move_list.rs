@@ -0,0 +1,217 @@
+use crate::layout::Layout;
+use crate::theme::Theme;
+use crate::ui::MOVE_SCALE;
+
+use ggez::{graphics, Context, GameResult};
+
+/// Height of one row of the list, in tiles.
+const ROW_HEIGHT: f32 = 0.4;
+/// Share of the panel width taken by the move numbers.
+const NUMBER_WIDTH: f32 = 0.24;
+
+/// Part of the side panel listing the moves in SAN, one numbered row per white and black move.
+/// Plies are counted from 1, ply 0 is the starting position.
+pub struct MoveList
+{
+    // First row shown at the top of the list.
+    scroll: usize,
+    // Number of the first row, games set up in the editor may start later than move 1.
+    first_move: u32,
+    // A game starting with black to move leaves white's half of the first row empty.
+    black_first: bool,
+}
+
+impl MoveList
+{
+    pub fn new() -> MoveList
+    {
+        MoveList { scroll: 0, first_move: 1, black_first: false }
+    }
+
+    /// Starts over for a new game beginning at the given move.
+    pub fn reset(&mut self, first_move: u32, black_first: bool)
+    {
+        *self = MoveList { scroll: 0, first_move, black_first };
+    }
+
+    /// Position of a ply counted in half rows, white's moves are even and black's odd.
+    fn slot(&self, ply: usize) -> usize
+    {
+        ply - 1 + self.black_first as usize
+    }
+
+    fn row_height(layout: &Layout) -> f32
+    {
+        layout.cell * ROW_HEIGHT
+    }
+
+    /// Area the rows are drawn in, below the title and above the return to live button.
+    fn rows_rect(layout: &Layout) -> graphics::Rect
+    {
+        let area = layout.move_list_rect();
+        let row_height = MoveList::row_height(layout);
+        graphics::Rect::new(area.x, area.y + row_height, area.w, area.h - 2.5 * row_height)
+    }
+
+    fn visible_rows(layout: &Layout) -> usize
+    {
+        ((MoveList::rows_rect(layout).h / MoveList::row_height(layout)).floor() as usize).max(1)
+    }
+
+    /// The button that leaves the history and goes back to the game being played.
+    pub fn live_button_rect(layout: &Layout) -> graphics::Rect
+    {
+        let area = layout.move_list_rect();
+        let row_height = MoveList::row_height(layout);
+        graphics::Rect::new(area.x, area.y + area.h - 1.25 * row_height, area.w, 1.25 * row_height)
+    }
+
+    /// Where a ply is written in the list, None if it is scrolled out of view.
+    fn ply_rect(&self, layout: &Layout, ply: usize) -> Option<graphics::Rect>
+    {
+        let row = self.slot(ply) / 2;
+        if row < self.scroll || row >= self.scroll + MoveList::visible_rows(layout) { return None; }
+
+        let rows = MoveList::rows_rect(layout);
+        let row_height = MoveList::row_height(layout);
+        let number_width = rows.w * NUMBER_WIDTH;
+        let column_width = (rows.w - number_width) / 2.0;
+
+        Some(graphics::Rect::new
+        (
+            rows.x + number_width + (self.slot(ply) % 2) as f32 * column_width,
+            rows.y + (row - self.scroll) as f32 * row_height,
+            column_width,
+            row_height,
+        ))
+    }
+
+    /// The ply whose move is under a window position.
+    pub fn ply_at(&self, layout: &Layout, plies: usize, x: f32, y: f32) -> Option<usize>
+    {
+        (1..=plies).find(|ply| self.ply_rect(layout, *ply).is_some_and(|rect| rect.contains([x, y])))
+    }
+
+    /// Scrolls by a number of rows, negative numbers scroll towards the first move.
+    pub fn scroll(&mut self, layout: &Layout, plies: usize, rows: i32)
+    {
+        let rows_used = (plies + self.black_first as usize).div_ceil(2);
+        let last_scroll = rows_used.saturating_sub(MoveList::visible_rows(layout));
+        self.scroll = (self.scroll as i32 + rows).clamp(0, last_scroll as i32) as usize;
+    }
+
+    /// Scrolls just far enough for a ply to be shown.
+    pub fn follow(&mut self, layout: &Layout, ply: usize)
+    {
+        if ply == 0
+        {
+            self.scroll = 0;
+            return;
+        }
+
+        let row = self.slot(ply) / 2;
+        let visible = MoveList::visible_rows(layout);
+        if row < self.scroll { self.scroll = row; }
+        if row >= self.scroll + visible { self.scroll = row + 1 - visible; }
+    }
+
+    /// Draws the list with the current ply highlighted, and the return button while looking at the history.
+    pub fn draw
+    (
+        &self,
+        ctx: &mut Context,
+        layout: &Layout,
+        theme: &Theme,
+        moves: &[String],
+        current: usize,
+        live: bool,
+    ) -> GameResult
+    {
+        let area = layout.move_list_rect();
+        let row_height = MoveList::row_height(layout);
+        let text = |content: String|
+        {
+            graphics::Text::new
+            (
+                graphics::TextFragment::from(content)
+                    .font(theme.font)
+                    .scale(layout.text_scale(MOVE_SCALE)),
+            )
+        };
+
+        let title = text("Moves".to_string());
+        let title_dimensions = title.dimensions(ctx);
+        graphics::draw
+        (
+            ctx,
+            &title,
+            graphics::DrawParam::default()
+                .color(theme.text)
+                .dest([area.x + (area.w - title_dimensions.w) / 2.0, area.y + (row_height - title_dimensions.h) / 2.0]),
+        )?;
+
+        let numbers_end = MoveList::rows_rect(layout).x + MoveList::rows_rect(layout).w * NUMBER_WIDTH;
+
+        for (index, san) in moves.iter().enumerate()
+        {
+            let ply = index + 1;
+            let rect = match self.ply_rect(layout, ply)
+            {
+                Some(rect) => rect,
+                None => continue,
+            };
+
+            if ply == current
+            {
+                let highlight = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, theme.highlight)?;
+                graphics::draw(ctx, &highlight, graphics::DrawParam::default())?;
+            }
+
+            // the first move of a row gets the move number, black's only if it starts the game
+            if self.slot(ply).is_multiple_of(2) || ply == 1
+            {
+                let number = text(format!("{}.", self.first_move as usize + self.slot(ply) / 2));
+                let number_dimensions = number.dimensions(ctx);
+                graphics::draw
+                (
+                    ctx,
+                    &number,
+                    graphics::DrawParam::default()
+                        .color(theme.text)
+                        .dest([numbers_end - number_dimensions.w - row_height / 4.0, rect.y + (rect.h - number_dimensions.h) / 2.0]),
+                )?;
+            }
+
+            let san_text = text(san.clone());
+            let san_dimensions = san_text.dimensions(ctx);
+            graphics::draw
+            (
+                ctx,
+                &san_text,
+                graphics::DrawParam::default()
+                    .color(theme.text)
+                    .dest([rect.x + row_height / 4.0, rect.y + (rect.h - san_dimensions.h) / 2.0]),
+            )?;
+        }
+
+        if !live
+        {
+            let button = MoveList::live_button_rect(layout);
+            let button_background = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), button, theme.last_move)?;
+            graphics::draw(ctx, &button_background, graphics::DrawParam::default())?;
+
+            let label = text("Return to live".to_string());
+            let label_dimensions = label.dimensions(ctx);
+            graphics::draw
+            (
+                ctx,
+                &label,
+                graphics::DrawParam::default()
+                    .color(theme.text)
+                    .dest([button.x + (button.w - label_dimensions.w) / 2.0, button.y + (button.h - label_dimensions.h) / 2.0]),
+            )?;
+        }
+
+        Ok(())
+    }
+}
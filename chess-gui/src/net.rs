@@ -0,0 +1,686 @@
+use crate::clock::TimeControl;
+
+use chess_gui::{Ending, Variant};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{fmt, io, thread, time::Duration};
+
+/// Version of the line protocol, a host only plays with clients speaking the same one.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Port hosts listen on unless another is given.
+pub const DEFAULT_PORT: u16 = 7878;
+/// Time between attempts to reach the host after the connection was lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Time between checks for new connections and for the window having closed.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// Time a single attempt to reach the host may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Time the other end may take to accept a line before its connection is closed.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which end of a network game this window is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Role
+{
+    /// Waits for the opponent on a port.
+    Host(u16),
+    /// Connects to a host, holds its address as "host:port".
+    Join(String),
+    /// Connects to a host to follow the game without playing, holds its address like `Join`.
+    Watch(String),
+}
+
+/// One line of the protocol. Every message is a keyword, a space and its arguments:
+///
+/// ```text
+/// HELLO 2                  client to host, the first line after connecting to play, with the protocol version
+/// WATCH 2                  client to host, the first line after connecting to follow the game
+/// WELCOME 2 black          host to client, the colour the client plays
+/// GAME standard <fen>; e4  the variant, the position the game started from and the moves since
+/// MOVE Nf3                 a move in SAN, coordinates are read as well
+/// CLOCK 5+3 287.5 291      time control and the seconds white and black have left
+/// SYNC <fen>               the sender's position, the receiver catches up if it differs
+/// RESYNC                   asks the host to send GAME again
+/// RESIGN
+/// DRAW offer|accept|decline
+/// OVER 0-1 resignation     how the game ended off the board, from the host or with a claimed draw
+/// CHAT <text>
+/// BYE <reason>             the connection is closed on purpose
+/// ```
+///
+/// Spectators get GAME, CLOCK and SYNC when they start watching, then MOVE, CLOCK and OVER as the game goes on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message
+{
+    Hello(u32),
+    Watch(u32),
+    /// Protocol version and true if the client plays white.
+    Welcome(u32, bool),
+    Game(Variant, String, Vec<String>),
+    Move(String),
+    Clock(TimeControl, f32, f32),
+    Sync(String),
+    Resync,
+    Resign,
+    DrawOffer,
+    DrawAccept,
+    DrawDecline,
+    Over(Ending),
+    Chat(String),
+    Bye(String),
+}
+
+impl Message
+{
+    pub fn parse(line: &str) -> Result<Message, String>
+    {
+        let line = line.trim();
+        let (keyword, rest) = line.split_once(' ').map_or((line, ""), |(keyword, rest)| (keyword, rest.trim()));
+        let bad = || format!("bad {} message: {}", keyword, rest);
+        let number = |text: &str| text.parse::<u32>().map_err(|_| bad());
+
+        Ok(match keyword
+        {
+            "HELLO" => Message::Hello(number(rest)?),
+            "WATCH" => Message::Watch(number(rest)?),
+            "WELCOME" => match rest.split_once(' ')
+            {
+                Some((version, "white")) => Message::Welcome(number(version)?, true),
+                Some((version, "black")) => Message::Welcome(number(version)?, false),
+                _ => return Err(bad()),
+            },
+            "GAME" =>
+            {
+                let (variant, rest) = rest.split_once(' ').ok_or_else(bad)?;
+                let variant = Variant::parse(variant).ok_or_else(bad)?;
+                let (fen, moves) = rest.split_once(';').unwrap_or((rest, ""));
+                Message::Game(variant, fen.trim().to_string(), moves.split_whitespace().map(str::to_string).collect())
+            }
+            "MOVE" if !rest.is_empty() => Message::Move(rest.to_string()),
+            "CLOCK" =>
+            {
+                let parts: Vec<&str> = rest.split_whitespace().collect();
+                let seconds = |text: &str| text.parse::<f32>().ok().filter(|seconds| seconds.is_finite() && *seconds >= 0.0);
+                match (parts.len(), parts.first().and_then(|text| TimeControl::parse(text)))
+                {
+                    (3, Some(control)) => Message::Clock(control, seconds(parts[1]).ok_or_else(bad)?, seconds(parts[2]).ok_or_else(bad)?),
+                    _ => return Err(bad()),
+                }
+            }
+            "SYNC" if !rest.is_empty() => Message::Sync(rest.to_string()),
+            "RESYNC" => Message::Resync,
+            "RESIGN" => Message::Resign,
+            "DRAW" => match rest
+            {
+                "offer" => Message::DrawOffer,
+                "accept" => Message::DrawAccept,
+                "decline" => Message::DrawDecline,
+                _ => return Err(bad()),
+            },
+            "OVER" => match rest.split_once(' ').and_then(|(result, reason)| Ending::from_result(result, reason))
+            {
+                Some(ending) => Message::Over(ending),
+                None => return Err(bad()),
+            },
+            "CHAT" => Message::Chat(rest.to_string()),
+            "BYE" => Message::Bye(rest.to_string()),
+            _ => return Err(format!("unknown message: {}", line)),
+        })
+    }
+}
+
+/// Writes the message as one line without the line break, the way `parse` reads it.
+impl fmt::Display for Message
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Message::Hello(version) => write!(f, "HELLO {}", version),
+            Message::Watch(version) => write!(f, "WATCH {}", version),
+            Message::Welcome(version, white) => write!(f, "WELCOME {} {}", version, if *white { "white" } else { "black" }),
+            Message::Game(variant, fen, moves) => write!(f, "GAME {} {}; {}", variant.key(), fen, moves.join(" ")),
+            Message::Move(san) => write!(f, "MOVE {}", san),
+            Message::Clock(control, white, black) => write!(f, "CLOCK {} {} {}", control, white, black),
+            Message::Sync(fen) => write!(f, "SYNC {}", fen),
+            Message::Resync => write!(f, "RESYNC"),
+            Message::Resign => write!(f, "RESIGN"),
+            Message::DrawOffer => write!(f, "DRAW offer"),
+            Message::DrawAccept => write!(f, "DRAW accept"),
+            Message::DrawDecline => write!(f, "DRAW decline"),
+            // a line break in the text would end the message early
+            Message::Over(ending) => write!(f, "OVER {} {}", ending.result(), ending.reason()),
+            Message::Chat(text) => write!(f, "CHAT {}", text.replace(|c: char| c.is_control(), " ")),
+            Message::Bye(reason) => write!(f, "BYE {}", reason.replace(|c: char| c.is_control(), " ")),
+        }
+    }
+}
+
+/// What happened on the connection since it was last looked at.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event
+{
+    Connected,
+    Received(Message),
+    /// The connection was lost or could not be made, holds why.
+    Disconnected(String),
+    /// A line from a spectator of the host's game, the first one is its WATCH.
+    Spectator(u64, Message),
+}
+
+/// The connection to the opponent currently in use.
+type Connection = Arc<Mutex<Option<Peer>>>;
+/// Connections of everyone watching the host's game.
+type Spectators = Arc<Mutex<Vec<Peer>>>;
+
+/// The opponent or someone watching the host's game, numbered so a reader that ended does not clear a newer connection.
+/// Lines for it are written by a thread of its own, so a slow connection never holds up the window.
+struct Peer
+{
+    id: u64,
+    lines: mpsc::Sender<String>,
+    // Kept to close the connection when the window closes.
+    stream: TcpStream,
+}
+
+impl Peer
+{
+    /// Starts the thread writing to a new connection.
+    fn new(id: u64, stream: TcpStream) -> Option<Peer>
+    {
+        let writer = stream.try_clone().ok()?;
+        let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+        let (lines, queue) = mpsc::channel();
+        thread::spawn(move || write_lines(writer, queue));
+        Some(Peer { id, lines, stream })
+    }
+}
+
+/// The connection to the other player. Reading happens on threads of its own that hand every line over
+/// as an `Event` and writing on another, so the window keeps responding while waiting. A host keeps listening after losing
+/// its opponent and a client keeps trying to reach the host until it is back.
+/// Hosts also take any number of spectators, who only get told about the game.
+pub struct Network
+{
+    hosting: bool,
+    watching: bool,
+    connection: Connection,
+    spectators: Spectators,
+    events: mpsc::Receiver<Event>,
+    // Tells the threads to stop once the window is closed.
+    closed: Arc<AtomicBool>,
+    local_address: Option<SocketAddr>,
+}
+
+impl Network
+{
+    /// Listens for an opponent on a port of every interface, port 0 picks any free one.
+    pub fn host(port: u16) -> io::Result<Network>
+    {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let local_address = listener.local_addr().ok();
+
+        let (sender, events) = mpsc::channel();
+        let connection: Connection = Arc::new(Mutex::new(None));
+        let spectators: Spectators = Arc::new(Mutex::new(vec![]));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let (shared, watchers, stop) = (connection.clone(), spectators.clone(), closed.clone());
+        thread::spawn(move ||
+        {
+            let mut count = 0;
+            while !stop.load(Ordering::Relaxed)
+            {
+                let stream = match listener.accept()
+                {
+                    Ok((stream, _)) => stream,
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock =>
+                    {
+                        thread::sleep(ACCEPT_INTERVAL);
+                        continue;
+                    }
+                    Err(_) => continue,
+                };
+
+                count += 1;
+                let (shared, watchers, sender) = (shared.clone(), watchers.clone(), sender.clone());
+                thread::spawn(move || greet(stream, count, &shared, &watchers, &sender));
+            }
+        });
+
+        Ok(Network { hosting: true, watching: false, connection, spectators, events, closed, local_address })
+    }
+
+    /// Connects to a host given as "host:port", trying again whenever the connection is lost.
+    /// With `watch` the game is only followed, the host does not take moves from spectators.
+    pub fn join(address: String, watch: bool) -> Network
+    {
+        let (sender, events) = mpsc::channel();
+        let connection: Connection = Arc::new(Mutex::new(None));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let (shared, stop) = (connection.clone(), closed.clone());
+        thread::spawn(move ||
+        {
+            let mut count = 0;
+            // a host that can not be reached is reported once, not on every attempt
+            let mut reported = false;
+            while !stop.load(Ordering::Relaxed)
+            {
+                match connect(&address)
+                {
+                    Ok(stream) =>
+                    {
+                        count += 1;
+                        reported = false;
+                        let hello = if watch { Message::Watch(PROTOCOL_VERSION) } else { Message::Hello(PROTOCOL_VERSION) };
+                        let _ = writeln!(&stream, "{}", hello);
+                        if let Some(reader) = attach(&shared, count, stream)
+                        {
+                            if sender.send(Event::Connected).is_err() { return; }
+                            read_lines(BufReader::new(reader), &shared, count, &sender);
+                        }
+                    }
+                    Err(error) if !reported =>
+                    {
+                        reported = true;
+                        if sender.send(Event::Disconnected(format!("can not reach {}: {}", address, error))).is_err() { return; }
+                    }
+                    Err(_) => (),
+                }
+                thread::sleep(RECONNECT_DELAY);
+            }
+        });
+
+        let spectators = Arc::new(Mutex::new(vec![]));
+        Network { hosting: false, watching: watch, connection, spectators, events, closed, local_address: None }
+    }
+
+    pub fn is_host(&self) -> bool
+    {
+        self.hosting
+    }
+
+    pub fn is_spectator(&self) -> bool
+    {
+        self.watching
+    }
+
+    pub fn spectator_count(&self) -> usize
+    {
+        self.spectators.lock().unwrap().len()
+    }
+
+    pub fn is_connected(&self) -> bool
+    {
+        self.connection.lock().unwrap().is_some()
+    }
+
+    /// The address a host listens on.
+    pub fn local_address(&self) -> Option<SocketAddr>
+    {
+        self.local_address
+    }
+
+    /// Sends a message to the other player, it is lost if nobody is connected.
+    pub fn send(&self, message: &Message)
+    {
+        if let Some(peer) = self.connection.lock().unwrap().as_ref()
+        {
+            // a broken connection is noticed and reported by the reading thread
+            let _ = peer.lines.send(message.to_string());
+        }
+    }
+
+    /// Sends a message to the spectator numbered as in `Event::Spectator`.
+    /// A spectator whose writing thread gave up is let go.
+    pub fn send_to(&self, spectator: u64, message: &Message)
+    {
+        self.spectators.lock().unwrap().retain(|watcher| watcher.id != spectator || watcher.lines.send(message.to_string()).is_ok());
+    }
+
+    /// Sends a message to everyone watching, like `send_to`.
+    pub fn tell_spectators(&self, message: &Message)
+    {
+        let line = message.to_string();
+        self.spectators.lock().unwrap().retain(|watcher| watcher.lines.send(line.clone()).is_ok());
+    }
+
+    /// Closes the current connection, a client connects again by itself.
+    pub fn disconnect(&self)
+    {
+        if let Some(peer) = self.connection.lock().unwrap().as_ref()
+        {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Everything that happened since the last call.
+    pub fn poll(&self) -> Vec<Event>
+    {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for Network
+{
+    fn drop(&mut self)
+    {
+        self.closed.store(true, Ordering::Relaxed);
+        self.disconnect();
+        for watcher in self.spectators.lock().unwrap().iter()
+        {
+            let _ = watcher.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+fn connect(address: &str) -> io::Result<TcpStream>
+{
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no address found");
+    for resolved in address.to_socket_addrs()?
+    {
+        match TcpStream::connect_timeout(&resolved, CONNECT_TIMEOUT)
+        {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = error,
+        }
+    }
+    Err(last_error)
+}
+
+/// Reads the first line of a connection to the host, then lets it play, watch or go.
+fn greet(stream: TcpStream, count: u64, shared: &Connection, spectators: &Spectators, sender: &mpsc::Sender<Event>)
+{
+    // accepted streams may take over the listener's non-blocking mode on some systems
+    if stream.set_nonblocking(false).is_err() { return; }
+    let _ = stream.set_nodelay(true);
+    let mut reader = match stream.try_clone()
+    {
+        Ok(reader) => BufReader::new(reader),
+        Err(_) => return,
+    };
+
+    // whatever does not say who it is in time is not waited for
+    let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() { return; }
+    let _ = stream.set_read_timeout(None);
+
+    match Message::parse(&line)
+    {
+        Ok(Message::Hello(version)) =>
+        {
+            let mut current = shared.lock().unwrap();
+            // a game has two players, anyone else only gets to watch
+            if current.is_some()
+            {
+                drop(current);
+                turn_away(stream, "a game is already being played, --watch follows it");
+                return;
+            }
+            *current = match Peer::new(count, stream)
+            {
+                Some(peer) => Some(peer),
+                None => return,
+            };
+            drop(current);
+
+            if sender.send(Event::Connected).is_err() || sender.send(Event::Received(Message::Hello(version))).is_err() { return; }
+            read_lines(reader, shared, count, sender);
+        }
+        Ok(Message::Watch(version)) =>
+        {
+            let peer = match Peer::new(count, stream)
+            {
+                Some(peer) => peer,
+                None => return,
+            };
+            spectators.lock().unwrap().push(peer);
+            if sender.send(Event::Spectator(count, Message::Watch(version))).is_ok()
+            {
+                read_messages(reader, |message| sender.send(Event::Spectator(count, message)).is_ok());
+            }
+            spectators.lock().unwrap().retain(|watcher| watcher.id != count);
+        }
+        _ => turn_away(stream, "connections start with HELLO or WATCH"),
+    }
+}
+
+/// Writes the lines queued for a connection until it is let go or a write fails or takes too long,
+/// then closes the connection so its reading thread ends too.
+fn write_lines(stream: TcpStream, queue: mpsc::Receiver<String>)
+{
+    for line in queue
+    {
+        if writeln!(&stream, "{}", line).is_err() { break; }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Says goodbye to a connection that can not join, reading what it sent meanwhile
+/// so closing does not reset the connection before the goodbye arrives.
+fn turn_away(mut stream: TcpStream, reason: &str)
+{
+    let _ = writeln!(&stream, "{}", Message::Bye(reason.to_string()));
+    let _ = stream.shutdown(Shutdown::Write);
+    let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+    let _ = io::copy(&mut stream, &mut io::sink());
+}
+
+/// Makes a new stream the current connection, returns the half to read from.
+fn attach(shared: &Connection, count: u64, stream: TcpStream) -> Option<TcpStream>
+{
+    let _ = stream.set_nodelay(true);
+    let reader = stream.try_clone().ok()?;
+    *shared.lock().unwrap() = Some(Peer::new(count, stream)?);
+    Some(reader)
+}
+
+/// Hands every line of the opponent over until the connection ends, then lets go of it.
+fn read_lines(reader: BufReader<TcpStream>, shared: &Connection, count: u64, sender: &mpsc::Sender<Event>)
+{
+    let reason = read_messages(reader, |message| sender.send(Event::Received(message)).is_ok());
+
+    let mut current = shared.lock().unwrap();
+    if current.as_ref().is_some_and(|peer| peer.id == count) { *current = None; }
+    drop(current);
+    let _ = sender.send(Event::Disconnected(reason));
+}
+
+/// Passes every message on until the connection ends or `pass` returns false, returns why it ended.
+fn read_messages(reader: BufReader<TcpStream>, mut pass: impl FnMut(Message) -> bool) -> String
+{
+    let mut reason = "the connection was closed".to_string();
+    for line in reader.lines()
+    {
+        let line = match line
+        {
+            Ok(line) => line,
+            Err(error) =>
+            {
+                reason = error.to_string();
+                break;
+            }
+        };
+        if line.trim().is_empty() { continue; }
+
+        match Message::parse(&line)
+        {
+            Ok(message) => if !pass(message) { break; },
+            // a newer program may send more than this one knows, the rest of the game can still be played
+            Err(error) => eprintln!("Ignoring network message: {}", error),
+        }
+    }
+    reason
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Event, Message, Network, PROTOCOL_VERSION, WRITE_TIMEOUT};
+    use crate::clock::TimeControl;
+    use chess_gui::{Ending, Variant};
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+
+    /// Waits for the next event, failing the test if none comes.
+    fn next_event(network: &Network) -> Event
+    {
+        let start = Instant::now();
+        loop
+        {
+            if let Ok(event) = network.events.try_recv() { return event; }
+            assert!(start.elapsed() < Duration::from_secs(10), "no network event arrived");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn messages_read_what_they_write()
+    {
+        let messages = vec!
+        [
+            Message::Hello(PROTOCOL_VERSION),
+            Message::Watch(PROTOCOL_VERSION),
+            Message::Welcome(1, false),
+            Message::Game(Variant::Standard, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(), vec!["e4".to_string(), "e5".to_string()]),
+            Message::Game(Variant::ThreeCheck, "8/8/8/8/8/8/8/K6k w - - 2+3 0 1".to_string(), vec![]),
+            Message::Move("exd8=Q+".to_string()),
+            Message::Clock(TimeControl { initial: 300.0, increment: 3.0 }, 287.5, 291.0),
+            Message::Sync("8/8/8/8/8/8/8/K6k b - - 0 1".to_string()),
+            Message::Resync,
+            Message::Resign,
+            Message::DrawOffer,
+            Message::DrawAccept,
+            Message::DrawDecline,
+            Message::Over(Ending::Resignation { white_wins: false }),
+            Message::Over(Ending::Repetition),
+            Message::Chat("good luck!".to_string()),
+            Message::Bye("leaving".to_string()),
+        ];
+        for message in messages
+        {
+            assert_eq!(Message::parse(&message.to_string()), Ok(message));
+        }
+
+        assert_eq!(Message::Chat("two\nlines".to_string()).to_string(), "CHAT two lines");
+        assert!(Message::parse("MOVE").is_err());
+        assert!(Message::parse("GAME 8/8/8/8/8/8/8/K6k w - - 0 1; Kb1").is_err());
+        assert!(Message::parse("WELCOME 1 green").is_err());
+        assert!(Message::parse("CLOCK 5+3 -1 10").is_err());
+        assert!(Message::parse("OVER 1-0 repetition").is_err());
+        assert!(Message::parse("CASTLE now").is_err());
+    }
+
+    #[test]
+    fn plays_over_localhost_and_reconnects()
+    {
+        let host = Network::host(0).unwrap();
+        let port = host.local_address().unwrap().port();
+        let client = Network::join(format!("127.0.0.1:{}", port), false);
+
+        assert_eq!(next_event(&host), Event::Connected);
+        assert_eq!(next_event(&host), Event::Received(Message::Hello(PROTOCOL_VERSION)));
+        assert_eq!(next_event(&client), Event::Connected);
+
+        host.send(&Message::Welcome(PROTOCOL_VERSION, false));
+        assert_eq!(next_event(&client), Event::Received(Message::Welcome(PROTOCOL_VERSION, false)));
+        client.send(&Message::Move("e4".to_string()));
+        assert_eq!(next_event(&host), Event::Received(Message::Move("e4".to_string())));
+
+        // a third program is turned away while the game is on
+        let intruder = Network::join(format!("127.0.0.1:{}", port), false);
+        assert_eq!(next_event(&intruder), Event::Connected);
+        assert_eq!(next_event(&intruder), Event::Received(Message::Bye("a game is already being played, --watch follows it".to_string())));
+        drop(intruder);
+
+        // the client comes back by itself after losing the connection
+        host.disconnect();
+        assert!(matches!(next_event(&host), Event::Disconnected(_)));
+        assert!(matches!(next_event(&client), Event::Disconnected(_)));
+        assert_eq!(next_event(&host), Event::Connected);
+        assert_eq!(next_event(&host), Event::Received(Message::Hello(PROTOCOL_VERSION)));
+        assert_eq!(next_event(&client), Event::Connected);
+        assert!(host.is_connected() && client.is_connected());
+    }
+
+    #[test]
+    fn an_opponent_that_stops_reading_does_not_hold_up_the_host()
+    {
+        let host = Network::host(0).unwrap();
+        let port = host.local_address().unwrap().port();
+        let stuck = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        writeln!(&stuck, "{}", Message::Hello(PROTOCOL_VERSION)).unwrap();
+        assert_eq!(next_event(&host), Event::Connected);
+        assert_eq!(next_event(&host), Event::Received(Message::Hello(PROTOCOL_VERSION)));
+
+        let line = Message::Chat("a long line nobody reads ".repeat(100));
+        let start = Instant::now();
+        for _ in 0..5000
+        {
+            host.send(&line);
+        }
+        assert!(start.elapsed() < WRITE_TIMEOUT, "writing to the opponent blocked");
+        drop(stuck);
+    }
+
+    #[test]
+    fn spectators_follow_the_game()
+    {
+        let host = Network::host(0).unwrap();
+        let port = host.local_address().unwrap().port();
+        let client = Network::join(format!("127.0.0.1:{}", port), false);
+        assert_eq!(next_event(&host), Event::Connected);
+        assert_eq!(next_event(&host), Event::Received(Message::Hello(PROTOCOL_VERSION)));
+        assert_eq!(next_event(&client), Event::Connected);
+
+        // spectators come in while the game is on, the opponent keeps playing
+        let spectator = Network::join(format!("127.0.0.1:{}", port), true);
+        assert!(spectator.is_spectator());
+        assert_eq!(next_event(&spectator), Event::Connected);
+        let id = match next_event(&host)
+        {
+            Event::Spectator(id, Message::Watch(PROTOCOL_VERSION)) => id,
+            event => panic!("expected a spectator, got {:?}", event),
+        };
+        assert_eq!(host.spectator_count(), 1);
+
+        let game = Message::Game(Variant::Standard, "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(), vec![]);
+        host.send_to(id, &game);
+        assert_eq!(next_event(&spectator), Event::Received(game));
+
+        host.send(&Message::Chat("only for the opponent".to_string()));
+        host.tell_spectators(&Message::Move("Kb1".to_string()));
+        assert_eq!(next_event(&spectator), Event::Received(Message::Move("Kb1".to_string())));
+        assert_eq!(next_event(&client), Event::Received(Message::Chat("only for the opponent".to_string())));
+
+        spectator.send(&Message::Resync);
+        assert_eq!(next_event(&host), Event::Spectator(id, Message::Resync));
+
+        // a spectator that stops reading does not hold up the host
+        let stuck = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        writeln!(&stuck, "{}", Message::Watch(PROTOCOL_VERSION)).unwrap();
+        assert!(matches!(next_event(&host), Event::Spectator(_, Message::Watch(PROTOCOL_VERSION))));
+        let line = Message::Chat("a long line nobody reads ".repeat(100));
+        let start = Instant::now();
+        for _ in 0..5000
+        {
+            host.tell_spectators(&line);
+        }
+        assert!(start.elapsed() < WRITE_TIMEOUT, "writing to spectators blocked");
+        drop(stuck);
+
+        drop(spectator);
+        let start = Instant::now();
+        while host.spectator_count() > 0
+        {
+            assert!(start.elapsed() < Duration::from_secs(10), "the spectator was not let go");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(host.is_connected());
+    }
+}
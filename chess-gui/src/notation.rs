@@ -0,0 +1,497 @@
+use super::{crazyhouse, Game, Piece};
+use std::fmt;
+
+/// Letters used for the pieces in algebraic notation, indexed like `Game::pieces`.
+const PIECE_LETTERS: [char; 6] = ['P', 'Q', 'R', 'B', 'N', 'K'];
+
+/// A move read from text by `parse_move`, squares are written like for `make_move`.
+/// A Crazyhouse drop comes from the pocket, written as the piece letter and '@', e.g. "N@".
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParsedMove {
+    pub from: String,
+    pub to: String,
+    /// The piece a pawn becomes, 'Q', 'R', 'B' or 'N', if the move is a promotion. Queen unless another was asked for.
+    pub promotion: Option<char>,
+}
+
+/// Why text could not be read as a move by `parse_move`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MoveInputError {
+    /// The text is not written in any of the accepted notations.
+    Unreadable,
+    /// The text names a move that can not be played in this position.
+    Illegal,
+    /// More than one piece can make the move, holds the squares they stand on.
+    Ambiguous(Vec<String>),
+}
+
+impl fmt::Display for MoveInputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveInputError::Unreadable => write!(f, "not a move, try e.g. Nf3, e2e4 or e7e8n"),
+            MoveInputError::Illegal => write!(f, "that move is not legal here"),
+            MoveInputError::Ambiguous(squares) => write!(
+                f,
+                "ambiguous, the piece on {} can move there",
+                squares
+                    .iter()
+                    .map(|square| square.to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ),
+        }
+    }
+}
+
+impl Game {
+    /// Plays a move like `make_move` and returns it in standard algebraic notation, see `move_to_san`.
+    /// Returns None and leaves the game as it was if the move is not legal.
+    pub fn make_move_with_san(&mut self, _from: &str, _to: &str) -> Option<String> {
+        // the notation depends on the position before the move
+        let san = self.move_to_san(_from, _to)?;
+        if !self.make_move(_from, _to) {
+            return None;
+        }
+        Some(san)
+    }
+
+    /// Returns a move in standard algebraic notation, e.g. "Nf3", "exd5", "Nbd2", "O-O", "e8=Q+" or "Qxf7#".
+    /// Crazyhouse drops are written with the piece letter and '@', e.g. "N@f3" or "P@e6".
+    /// Squares are given like for `make_move`, a pawn reaching the last rank becomes the piece set with `set_promotion`.
+    /// Returns None if the move is not legal in this position.
+    pub fn move_to_san(&self, _from: &str, _to: &str) -> Option<String> {
+        let mut after = self.clone();
+        // make_move expects a piece on the from square
+        after.get_possible_moves(_from)?;
+        if !after.make_move(_from, _to) {
+            return None;
+        }
+
+        let mut san = String::new();
+        if crazyhouse::dropped_piece(_from).is_some() {
+            san += _from;
+            san += &_to.to_lowercase();
+            after.write_check(&mut san);
+            return Some(san);
+        }
+
+        let (from_file, from_rank) = self.transform_input(_from);
+        let (to_file, to_rank) = self.transform_input(_to);
+        let bit_pos_to = 2_u64.pow(to_file * 8 + to_rank);
+        let is_capture = (self.colour_of_piece[0] | self.colour_of_piece[1]) & bit_pos_to == bit_pos_to;
+        let piece = self.get_that_piece_type(2_u64.pow(from_file * 8 + from_rank));
+
+        if let (Piece::King, Some(right)) = (piece, self.castling_right_of_move(from_file, from_rank, to_file, to_rank)) {
+            san += if right % 2 == 0 { "O-O" } else { "O-O-O" };
+        } else if piece == Piece::Pawn {
+            // pawns only move sideways when they capture, en passant included
+            if from_file != to_file {
+                san.push(file_letter(from_file));
+                san.push('x');
+            }
+            san += &square_name(to_file, to_rank);
+            if (to_rank == 0) | (to_rank == 7) {
+                san.push('=');
+                san.push(PIECE_LETTERS[self.to_promote_to as usize]);
+            }
+        } else {
+            san.push(PIECE_LETTERS[piece as usize]);
+            san += &self.disambiguation(piece, from_file, from_rank, _to);
+            if is_capture {
+                san.push('x');
+            }
+            san += &square_name(to_file, to_rank);
+        }
+
+        after.write_check(&mut san);
+        Some(san)
+    }
+
+    /// Ends a SAN move with '+' or '#' if it leaves the side to move in check or checkmate.
+    fn write_check(&mut self, san: &mut String) {
+        match self.colour_in_check_or_mate(self.is_white_turn()) {
+            2 => san.push('#'),
+            1 => san.push('+'),
+            _ => (),
+        }
+    }
+
+    /// Reads a move typed by a player and checks that it is legal for the side to move.
+    /// Accepted are SAN ("Nf3", "exd5", "e8=Q+"), coordinates ("e2e4", "E2 E4", "e2-e4")
+    /// and UCI with a promotion suffix ("e7e8n"). Castling is written "O-O" or "O-O-O", or as the king's move.
+    /// Crazyhouse drops are written "N@f3", a pawn drop also as "@e6".
+    pub fn parse_move(&self, _input: &str) -> Result<ParsedMove, MoveInputError> {
+        let text: String = _input
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .trim_end_matches(['+', '#', '!', '?'])
+            .to_string();
+
+        if let Some(parsed) = self.parse_drop(&text) {
+            return parsed;
+        }
+        if let Some(parsed) = self.parse_coordinates(&text) {
+            return parsed;
+        }
+        self.parse_san(&text)
+    }
+
+    /// A drop from the pocket, None if the text is not written that way.
+    fn parse_drop(&self, text: &str) -> Option<Result<ParsedMove, MoveInputError>> {
+        let (letter, square) = text.split_once('@')?;
+        let from = match letter.to_uppercase().as_str() {
+            "" => "P@".to_string(),
+            letter => format!("{}@", letter),
+        };
+        let chars: Vec<char> = square.to_lowercase().chars().collect();
+        let to = match chars[..] {
+            [file, rank] => square_from_chars(file, rank),
+            _ => None,
+        };
+        let to = match (to, crazyhouse::dropped_piece(&from)) {
+            (Some(to), Some(_)) => to,
+            _ => return Some(Err(MoveInputError::Unreadable)),
+        };
+
+        let to = self.transform_back(to.0, to.1);
+        let is_legal = self.clone().get_possible_moves(&from).is_some_and(|drops| drops.contains(&to));
+        if !is_legal {
+            return Some(Err(MoveInputError::Illegal));
+        }
+        Some(Ok(ParsedMove { from, to, promotion: None }))
+    }
+
+    /// Coordinate and UCI notation, None if the text is not written that way.
+    fn parse_coordinates(&self, text: &str) -> Option<Result<ParsedMove, MoveInputError>> {
+        let chars: Vec<char> = text.replace('-', "").to_lowercase().chars().collect();
+        if !(chars.len() == 4 || chars.len() == 5) {
+            return None;
+        }
+        let from = square_from_chars(chars[0], chars[1])?;
+        let to = square_from_chars(chars[2], chars[3])?;
+        let promotion = match chars.get(4) {
+            Some(c) => Some(promotion_letter(*c)?),
+            None => None,
+        };
+
+        let mut search = self.clone();
+        let is_legal = self.is_own_piece(from)
+            && search
+                .get_possible_moves(&self.transform_back(from.0, from.1))
+                .is_some_and(|moves| moves.contains(&self.transform_back(to.0, to.1)));
+        if !is_legal {
+            return Some(Err(MoveInputError::Illegal));
+        }
+        Some(self.finish_parsed_move(from, to, promotion))
+    }
+
+    /// Standard algebraic notation.
+    fn parse_san(&self, text: &str) -> Result<ParsedMove, MoveInputError> {
+        let castling = text.replace('0', "O").to_uppercase();
+        if castling == "O-O" || castling == "O-O-O" {
+            let rank = if self.white_turn { 0 } else { 7 };
+            let right = if self.white_turn { 0 } else { 2 } + if castling == "O-O" { 0 } else { 1 };
+            // the king's move is looked up, in Chess960 it may start on any file
+            let king_file = (0..8)
+                .find(|file| self.get_that_piece_type(2_u64.pow(file * 8 + rank)) == Piece::King && self.is_own_piece((*file, rank)))
+                .ok_or(MoveInputError::Illegal)?;
+            let mut search = self.clone();
+            let to_file = search
+                .get_possible_moves(&self.transform_back(king_file, rank))
+                .unwrap_or_default()
+                .iter()
+                .map(|to| self.transform_input(to))
+                .find(|(to_file, to_rank)| self.castling_right_of_move(king_file, rank, *to_file, *to_rank) == Some(right))
+                .map(|(to_file, _)| to_file)
+                .ok_or(MoveInputError::Illegal)?;
+            return self.finish_parsed_move((king_file, rank), (to_file, rank), None);
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+
+        // the promotion goes last, with or without '='
+        let mut promotion = None;
+        if let Some(last) = chars.last().copied() {
+            if last.is_ascii_alphabetic() && chars.len() >= 3 && chars[chars.len() - 2].is_ascii_digit() {
+                promotion = Some(promotion_letter(last).ok_or(MoveInputError::Unreadable)?);
+                chars.pop();
+            } else if chars.len() >= 2 && chars[chars.len() - 2] == '=' {
+                promotion = Some(promotion_letter(last).ok_or(MoveInputError::Unreadable)?);
+                chars.truncate(chars.len() - 2);
+            }
+        }
+
+        // a lowercase b is a pawn on the b-file, the other piece letters can not be mistaken for files
+        let piece = match chars.first() {
+            Some('K') | Some('k') => Piece::King,
+            Some('Q') | Some('q') => Piece::Queen,
+            Some('R') | Some('r') => Piece::Rook,
+            Some('B') => Piece::Bishop,
+            Some('N') | Some('n') => Piece::Knight,
+            Some(_) => Piece::Pawn,
+            None => return Err(MoveInputError::Unreadable),
+        };
+        let rest = if piece == Piece::Pawn { &chars[..] } else { &chars[1..] };
+        if rest.len() < 2 {
+            return Err(MoveInputError::Unreadable);
+        }
+
+        let to = square_from_chars(rest[rest.len() - 2].to_ascii_lowercase(), rest[rest.len() - 1])
+            .ok_or(MoveInputError::Unreadable)?;
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in &rest[..rest.len() - 2] {
+            match c.to_ascii_lowercase() {
+                'x' | ':' => (),
+                file @ 'a'..='h' => from_file = Some(file as u32 - 'a' as u32),
+                rank @ '1'..='8' => from_rank = Some(rank as u32 - '1' as u32),
+                _ => return Err(MoveInputError::Unreadable),
+            }
+        }
+
+        let own_pieces = self.pieces[piece as usize] & self.colour_of_piece[if self.white_turn { 0 } else { 1 }];
+        let to_name = self.transform_back(to.0, to.1);
+        let mut search = self.clone();
+        let candidates: Vec<(u32, u32)> = (0..64)
+            .filter(|index| own_pieces & 2_u64.pow(*index) != 0)
+            .map(|index| (index / 8, index % 8))
+            .filter(|(file, _)| from_file.is_none_or(|wanted| wanted == *file))
+            .filter(|(_, rank)| from_rank.is_none_or(|wanted| wanted == *rank))
+            .filter(|(file, rank)| {
+                search
+                    .get_possible_moves(&self.transform_back(*file, *rank))
+                    .is_some_and(|moves| moves.contains(&to_name))
+            })
+            .collect();
+
+        match candidates.len() {
+            0 => Err(MoveInputError::Illegal),
+            1 => self.finish_parsed_move(candidates[0], to, promotion),
+            _ => Err(MoveInputError::Ambiguous(
+                candidates.iter().map(|(file, rank)| self.transform_back(*file, *rank)).collect(),
+            )),
+        }
+    }
+
+    /// Checks the promotion of a legal move, a pawn reaching the last rank becomes a queen unless told otherwise.
+    fn finish_parsed_move(
+        &self,
+        from: (u32, u32),
+        to: (u32, u32),
+        promotion: Option<char>,
+    ) -> Result<ParsedMove, MoveInputError> {
+        let is_promotion = self.get_that_piece_type(2_u64.pow(from.0 * 8 + from.1)) == Piece::Pawn
+            && ((to.1 == 0) | (to.1 == 7));
+        if promotion.is_some() && !is_promotion {
+            return Err(MoveInputError::Illegal);
+        }
+
+        Ok(ParsedMove {
+            from: self.transform_back(from.0, from.1),
+            to: self.transform_back(to.0, to.1),
+            promotion: if is_promotion { Some(promotion.unwrap_or('Q')) } else { None },
+        })
+    }
+
+    fn is_own_piece(&self, square: (u32, u32)) -> bool {
+        if self.white_turn {
+            self.is_white(square.0, square.1)
+        } else {
+            self.is_black(square.0, square.1)
+        }
+    }
+
+    /// The part of a SAN move telling apart pieces of the same type that can all reach the target square.
+    /// The file is preferred, then the rank, and both only if neither is enough.
+    fn disambiguation(&self, piece: Piece, _from_file: u32, _from_rank: u32, _to: &str) -> String {
+        let own_pieces =
+            self.pieces[piece as usize] & self.colour_of_piece[if self.white_turn { 0 } else { 1 }];
+        let mut search = self.clone();
+        let rivals: Vec<(u32, u32)> = (0..64)
+            .filter(|index| own_pieces & 2_u64.pow(*index) != 0)
+            .map(|index| (index / 8, index % 8))
+            .filter(|square| *square != (_from_file, _from_rank))
+            .filter(|(file, rank)| {
+                search
+                    .get_possible_moves(&self.transform_back(*file, *rank))
+                    .is_some_and(|moves| moves.iter().any(|square| square == _to))
+            })
+            .collect();
+
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|(file, _)| *file != _from_file) {
+            file_letter(_from_file).to_string()
+        } else if rivals.iter().all(|(_, rank)| *rank != _from_rank) {
+            (_from_rank + 1).to_string()
+        } else {
+            square_name(_from_file, _from_rank)
+        }
+    }
+}
+
+fn file_letter(file: u32) -> char {
+    (b'a' + file as u8) as char
+}
+
+/// The (file, rank) of a square written as a lowercase file and a rank.
+fn square_from_chars(file: char, rank: char) -> Option<(u32, u32)> {
+    if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some((file as u32 - 'a' as u32, rank as u32 - '1' as u32))
+}
+
+/// The uppercase letter of a piece a pawn can promote to, in either case.
+fn promotion_letter(c: char) -> Option<char> {
+    match c.to_ascii_uppercase() {
+        letter @ ('Q' | 'R' | 'B' | 'N') => Some(letter),
+        _ => None,
+    }
+}
+
+/// Lowercase square name as used in SAN, e.g. "e4".
+fn square_name(file: u32, rank: u32) -> String {
+    format!("{}{}", file_letter(file), rank + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Game, MoveInputError, ParsedMove};
+
+    fn parsed(from: &str, to: &str, promotion: Option<char>) -> Result<ParsedMove, MoveInputError> {
+        Ok(ParsedMove { from: from.to_string(), to: to.to_string(), promotion })
+    }
+
+    /// Plays moves given as pairs of squares and returns them in SAN.
+    fn play(game: &mut Game, moves: &[(&str, &str)]) -> Vec<String> {
+        moves
+            .iter()
+            .map(|(from, to)| {
+                let san = game.move_to_san(from, to).expect("move should be legal");
+                assert!(game.make_move(from, to));
+                san
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pawn_and_piece_moves() {
+        let mut game = Game::new();
+        assert_eq!(
+            play(&mut game, &[("E2", "E4"), ("D7", "D5"), ("E4", "D5"), ("G8", "F6"), ("F1", "B5")]),
+            vec!["e4", "d5", "exd5", "Nf6", "Bb5+"]
+        );
+    }
+
+    #[test]
+    fn illegal_moves_have_no_san() {
+        let game = Game::new();
+        assert_eq!(game.move_to_san("E2", "E5"), None);
+        assert_eq!(game.move_to_san("E7", "E5"), None);
+    }
+
+    #[test]
+    fn moves_are_played_with_their_san() {
+        let mut game = Game::new();
+        assert_eq!(game.make_move_with_san("E2", "E5"), None);
+        assert_eq!(game.is_white_turn(), true);
+        assert_eq!(game.make_move_with_san("E2", "E4"), Some("e4".to_string()));
+        assert_eq!(game.make_move_with_san("F7", "F6"), Some("f6".to_string()));
+        // the check is written from the position after the move
+        assert_eq!(game.make_move_with_san("D1", "H5"), Some("Qh5+".to_string()));
+        assert_eq!(game.is_white_turn(), false);
+    }
+
+    #[test]
+    fn knights_are_told_apart_by_file() {
+        let mut game = Game::new();
+        play(&mut game, &[("D2", "D4"), ("D7", "D5"), ("G1", "F3"), ("G8", "F6")]);
+        assert_eq!(game.move_to_san("B1", "D2"), Some("Nbd2".to_string()));
+        assert_eq!(game.move_to_san("F3", "D2"), Some("Nfd2".to_string()));
+    }
+
+    #[test]
+    fn scholars_mate() {
+        let mut game = Game::new();
+        let moves = play(
+            &mut game,
+            &[
+                ("E2", "E4"),
+                ("E7", "E5"),
+                ("F1", "C4"),
+                ("B8", "C6"),
+                ("D1", "H5"),
+                ("G8", "F6"),
+                ("H5", "F7"),
+            ],
+        );
+        assert_eq!(moves.last().unwrap(), "Qxf7#");
+    }
+
+    #[test]
+    fn reads_all_notations() {
+        let game = Game::new();
+        assert_eq!(game.parse_move("Nf3"), parsed("G1", "F3", None));
+        assert_eq!(game.parse_move("e4"), parsed("E2", "E4", None));
+        assert_eq!(game.parse_move("e2e4"), parsed("E2", "E4", None));
+        assert_eq!(game.parse_move("E2 E4"), parsed("E2", "E4", None));
+        assert_eq!(game.parse_move("g1-f3"), parsed("G1", "F3", None));
+        assert_eq!(game.parse_move("nf3"), parsed("G1", "F3", None));
+    }
+
+    #[test]
+    fn reports_bad_input() {
+        let mut game = Game::new();
+        assert_eq!(game.parse_move("hello"), Err(MoveInputError::Unreadable));
+        assert_eq!(game.parse_move(""), Err(MoveInputError::Unreadable));
+        assert_eq!(game.parse_move("e5"), Err(MoveInputError::Illegal));
+        assert_eq!(game.parse_move("e7e5"), Err(MoveInputError::Illegal));
+        assert_eq!(game.parse_move("e2e4q"), Err(MoveInputError::Illegal));
+
+        play(&mut game, &[("D2", "D4"), ("D7", "D5"), ("G1", "F3"), ("G8", "F6")]);
+        assert_eq!(
+            game.parse_move("Nd2"),
+            Err(MoveInputError::Ambiguous(vec!["B1".to_string(), "F3".to_string()]))
+        );
+        assert_eq!(game.parse_move("Nbd2"), parsed("B1", "D2", None));
+        assert_eq!(game.parse_move("N3d2"), parsed("F3", "D2", None));
+    }
+
+    #[test]
+    fn reads_captures_and_promotions() {
+        let mut game = Game::new();
+        play(&mut game, &[("E2", "E4"), ("D7", "D5")]);
+        assert_eq!(game.parse_move("exd5"), parsed("E4", "D5", None));
+        assert_eq!(game.parse_move("ed5"), parsed("E4", "D5", None));
+
+        let mut game = Game::new();
+        game.pieces = [2_u64.pow(48 + 6), 0, 0, 0, 0, 1 + 2_u64.pow(32 + 7)];
+        game.colour_of_piece = [1 + 2_u64.pow(48 + 6), 2_u64.pow(32 + 7)];
+        assert_eq!(game.parse_move("g8=N"), parsed("G7", "G8", Some('N')));
+        assert_eq!(game.parse_move("g8r+"), parsed("G7", "G8", Some('R')));
+        assert_eq!(game.parse_move("g7g8n"), parsed("G7", "G8", Some('N')));
+        assert_eq!(game.parse_move("g7g8"), parsed("G7", "G8", Some('Q')));
+    }
+
+    #[test]
+    fn castling_is_written_and_read() {
+        let mut game = Game::new();
+        assert_eq!(game.parse_move("O-O"), Err(MoveInputError::Illegal));
+
+        play(&mut game, &[("E2", "E4"), ("E7", "E5"), ("G1", "F3"), ("B8", "C6"), ("F1", "C4"), ("G8", "F6")]);
+        assert_eq!(game.parse_move("O-O"), parsed("E1", "G1", None));
+        assert_eq!(game.parse_move("0-0"), parsed("E1", "G1", None));
+        assert_eq!(game.parse_move("e1g1"), parsed("E1", "G1", None));
+        assert_eq!(game.move_to_san("E1", "G1"), Some("O-O".to_string()));
+        assert_eq!(game.parse_move("O-O-O"), Err(MoveInputError::Illegal));
+
+        // in Chess960 the king moves onto its rook
+        let game = Game::from_fen("1r4k1/8/8/8/8/8/8/1R4K1 w Bb - 0 1").unwrap();
+        assert_eq!(game.parse_move("O-O-O"), parsed("G1", "B1", None));
+        assert_eq!(game.move_to_san("G1", "B1"), Some("O-O-O".to_string()));
+        assert_eq!(game.parse_move("O-O"), Err(MoveInputError::Illegal));
+    }
+}
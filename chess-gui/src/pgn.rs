@@ -0,0 +1,266 @@
+use super::{FenError, Game, MoveInputError, ParsedMove, Variant};
+use std::fmt;
+
+/// Results a PGN game can end with, "*" means the game was not finished.
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+/// Movetext lines are broken before they grow longer than this, as the export format asks.
+const LINE_LENGTH: usize = 79;
+
+/// Why a PGN game could not be read or replayed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PgnError {
+    /// The text does not follow the PGN format, says which part is wrong.
+    Malformed(String),
+    /// The FEN tag holds a position that can not be played from.
+    Fen(FenError),
+    /// A move can not be played, holds its ply counted from 1 and how it was written.
+    Move(usize, String, MoveInputError),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::Malformed(message) => write!(f, "not a valid PGN game, {}", message),
+            PgnError::Fen(error) => write!(f, "bad FEN tag: {}", error),
+            PgnError::Move(ply, san, error) => write!(f, "move {} ({}): {}", ply.div_ceil(2), san, error),
+        }
+    }
+}
+
+/// A game in Portable Game Notation: the tag pairs and the moves in SAN as they were written.
+/// Comments, variations and annotations are skipped when reading.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pgn {
+    /// Tag pairs like ("White", "Carlsen"), in the order they were written.
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    /// "1-0", "0-1", "1/2-1/2" or "*", None if the movetext does not end with a result.
+    pub result: Option<String>,
+}
+
+impl Pgn {
+    /// Reads the first game in a PGN text.
+    pub fn parse(_text: &str) -> Result<Pgn, PgnError> {
+        let mut pgn = Pgn::default();
+        let mut chars = _text.chars().peekable();
+        let mut token = String::new();
+
+        while let Some(c) = chars.next() {
+            let ends_token = c.is_whitespace() || matches!(c, '[' | '{' | ';' | '(' | '$');
+            if !ends_token {
+                token.push(c);
+                continue;
+            }
+            if pgn.read_token(&std::mem::take(&mut token)) {
+                return Ok(pgn);
+            }
+
+            match c {
+                '[' => {
+                    let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                    pgn.tags.push(parse_tag(&tag)?);
+                }
+                '{' if !chars.by_ref().any(|c| c == '}') => {
+                    return Err(PgnError::Malformed("unclosed comment".to_string()));
+                }
+                ';' => while chars.next().is_some_and(|c| c != '\n') {},
+                '(' => {
+                    // variations may hold variations of their own
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match chars.next() {
+                            Some('(') => depth += 1,
+                            Some(')') => depth -= 1,
+                            Some(_) => (),
+                            None => return Err(PgnError::Malformed("unclosed variation".to_string())),
+                        }
+                    }
+                }
+                '$' => while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                },
+                _ => (),
+            }
+        }
+        pgn.read_token(&token);
+        Ok(pgn)
+    }
+
+    /// Adds a token of the movetext, returns true once the result ends the game.
+    fn read_token(&mut self, token: &str) -> bool {
+        if RESULTS.contains(&token) {
+            self.result = Some(token.to_string());
+            return true;
+        }
+
+        // move numbers may be glued to the move, as in "1.e4" or "3...Nf6", castling as "0-0" has no dot
+        let san = match token.rfind('.') {
+            Some(dot) if token[..dot].chars().all(|c| c.is_ascii_digit() || c == '.') => &token[dot + 1..],
+            _ => token,
+        };
+        let san = san.trim_end_matches(['!', '?']);
+        if !san.is_empty() {
+            self.moves.push(san.to_string());
+        }
+        false
+    }
+
+    /// The value of a tag, e.g. `tag("Event")`.
+    pub fn tag(&self, _name: &str) -> Option<&str> {
+        self.tags.iter().find(|(name, _)| name == _name).map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from, the one in the FEN tag if there is one, played by the rules
+    /// of the Variant tag. Variants that only change the start, like Chess960, are left to the FEN tag.
+    pub fn start_position(&self) -> Result<Game, PgnError> {
+        let variant = self.tag("Variant").and_then(Variant::parse).unwrap_or_default();
+        match self.tag("FEN") {
+            Some(fen) => Game::from_variant_fen(variant, fen).map_err(PgnError::Fen),
+            None => Ok(Game::new_variant(variant)),
+        }
+    }
+
+    /// Checks that every move can be played and returns the start position with the moves,
+    /// ready to be played one by one with `set_promotion` and `make_move`.
+    pub fn replay(&self) -> Result<(Game, Vec<ParsedMove>), PgnError> {
+        let start = self.start_position()?;
+        let mut game = start.clone();
+        let mut parsed_moves = vec![];
+
+        for (index, san) in self.moves.iter().enumerate() {
+            let parsed = game
+                .parse_move(san)
+                .map_err(|error| PgnError::Move(index + 1, san.clone(), error))?;
+            game.set_promotion(&parsed.promotion.unwrap_or('Q').to_string());
+            game.make_move(&parsed.from, &parsed.to);
+            parsed_moves.push(parsed);
+        }
+        Ok((start, parsed_moves))
+    }
+}
+
+/// Writes the game in the PGN export format: the tags, an empty line and the numbered moves
+/// broken into lines, ending with the result. A game without a result is written as unfinished.
+impl fmt::Display for Pgn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        // numbering goes on from the FEN tag, a game black starts gets "1..." before its first move
+        let start = self.start_position().unwrap_or_else(|_| Game::new());
+        let (mut number, mut white) = (start.get_fullmove_number(), start.is_white_turn());
+        let mut tokens = vec![];
+        if !white && !self.moves.is_empty() {
+            tokens.push(format!("{}...", number));
+        }
+        for san in &self.moves {
+            if white {
+                tokens.push(format!("{}.", number));
+            } else {
+                number += 1;
+            }
+            tokens.push(san.clone());
+            white = !white;
+        }
+        tokens.push(self.result.clone().unwrap_or_else(|| "*".to_string()));
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > LINE_LENGTH {
+                writeln!(f)?;
+                line_length = 0;
+            } else if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{}", token)?;
+            line_length += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Reads the inside of a tag pair like `Event "Casual game"`.
+fn parse_tag(_tag: &str) -> Result<(String, String), PgnError> {
+    let malformed = || PgnError::Malformed(format!("bad tag [{}]", _tag));
+    let (name, value) = _tag.trim().split_once(char::is_whitespace).ok_or_else(malformed)?;
+    let value = value.trim();
+    if !(value.len() >= 2 && value.starts_with('"') && value.ends_with('"')) {
+        return Err(malformed());
+    }
+    let value = value[1..value.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\");
+    Ok((name.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pgn, PgnError};
+    use crate::{MoveInputError, Variant};
+
+    #[test]
+    fn reads_tags_moves_and_result() {
+        let pgn = Pgn::parse(
+            "[Event \"Casual \\\"blitz\\\"\"]\n[White \"A\"]\n\n\
+             1. e4 e5 {the open game} 2.Nf3 (2. f4 exf4 (2... d5)) Nc6 $1 3. Bb5 a6?! 4. 0-0 1/2-1/2\n\
+             [Event \"next game\"]",
+        )
+        .unwrap();
+        assert_eq!(pgn.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(pgn.tag("White"), Some("A"));
+        assert_eq!(pgn.moves, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "0-0"]);
+        assert_eq!(pgn.result, Some("1/2-1/2".to_string()));
+        assert_eq!(pgn.replay().unwrap().1.len(), 7);
+    }
+
+    #[test]
+    fn starts_from_the_fen_tag() {
+        let pgn = Pgn::parse("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 Kd7 *").unwrap();
+        let (start, moves) = pgn.replay().unwrap();
+        assert_eq!(start.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(moves[1].from, "E8");
+    }
+
+    #[test]
+    fn follows_the_variant_tag() {
+        let pgn = Pgn::parse("[Variant \"King of the Hill\"]\n\n1. d3 e6 2. Kd2 Ke7 3. Kc3 Kf6 4. Kd4 1-0").unwrap();
+        let (start, moves) = pgn.replay().unwrap();
+        assert_eq!(start.get_variant(), Variant::KingOfTheHill);
+        assert_eq!(moves.len(), 7);
+
+        // the king may be taken in antichess
+        let pgn = Pgn::parse("[Variant \"Antichess\"]\n[FEN \"8/8/8/8/8/8/3q4/4K3 w - - 0 1\"]\n\n1. Kxd2 0-1").unwrap();
+        assert_eq!(pgn.replay().unwrap().0.get_variant(), Variant::Antichess);
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        assert!(matches!(Pgn::parse("1. e4 {no end"), Err(PgnError::Malformed(_))));
+        assert!(matches!(Pgn::parse("[Event]"), Err(PgnError::Malformed(_))));
+        assert_eq!(
+            Pgn::parse("1. e4 e5 2. Ke3").unwrap().replay().err(),
+            Some(PgnError::Move(3, "Ke3".to_string(), MoveInputError::Illegal))
+        );
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let text = "[Event \"Casual \\\"blitz\\\"\"]\n[White \"A\"]\n\n1. e4 e5 2. Nf3 Nc6 1/2-1/2\n";
+        let pgn = Pgn::parse(text).unwrap();
+        assert_eq!(pgn.to_string(), text);
+
+        // black to move in the FEN tag, and no result yet
+        let pgn = Pgn::parse("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]\n\n7... Kd7 8. e4").unwrap();
+        let pgn = Pgn { result: None, ..pgn };
+        assert_eq!(pgn.to_string(), "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]\n\n7... Kd7 8. e4 *\n");
+
+        // long games are broken into lines
+        let moves = ["Nf3", "Nf6", "Ng1", "Ng8"].repeat(10);
+        let long = Pgn { tags: vec![], moves: moves.iter().map(|san| san.to_string()).collect(), result: None };
+        let written = long.to_string();
+        assert!(written.lines().all(|line| line.len() <= 79));
+        assert_eq!(Pgn::parse(&written).unwrap().moves, long.moves);
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(feature = "embed-resources")]
+use ggez::filesystem;
+use ggez::{event, graphics, Context, GameError, GameResult};
+use std::{env, path::{Path, PathBuf}};
+
+/// Name of the directory the images and config files live in.
+const RESOURCE_DIR_NAME: &str = "res";
+/// A file every complete resource directory contains, used to tell it apart from unrelated `res` folders.
+const MARKER_FILE: &str = "shadow.png";
+
+/// Bundled images compiled into the binary, so it runs without a resource directory.
+#[cfg(feature = "embed-resources")]
+#[rustfmt::skip]
+const EMBEDDED_IMAGES: [(&str, &[u8]); 13] =
+[
+    ("/black_king.png", include_bytes!("../res/black_king.png")),
+    ("/black_queen.png", include_bytes!("../res/black_queen.png")),
+    ("/black_rook.png", include_bytes!("../res/black_rook.png")),
+    ("/black_pawn.png", include_bytes!("../res/black_pawn.png")),
+    ("/black_bishop.png", include_bytes!("../res/black_bishop.png")),
+    ("/black_knight.png", include_bytes!("../res/black_knight.png")),
+    ("/white_king.png", include_bytes!("../res/white_king.png")),
+    ("/white_queen.png", include_bytes!("../res/white_queen.png")),
+    ("/white_rook.png", include_bytes!("../res/white_rook.png")),
+    ("/white_pawn.png", include_bytes!("../res/white_pawn.png")),
+    ("/white_bishop.png", include_bytes!("../res/white_bishop.png")),
+    ("/white_knight.png", include_bytes!("../res/white_knight.png")),
+    ("/shadow.png", include_bytes!("../res/shadow.png")),
+];
+
+/// Places the resource directory is looked for, most specific first:
+/// the `CHESS_GUI_RESOURCES` environment variable, next to the executable,
+/// the crate root when running from `target/`, the crate it was built from and the working directory.
+pub fn candidate_dirs() -> Vec<PathBuf>
+{
+    let mut dirs = vec![];
+
+    if let Some(dir) = env::var_os("CHESS_GUI_RESOURCES")
+    {
+        dirs.push(PathBuf::from(dir));
+    }
+
+    if let Some(exe_dir) = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(exe_dir.join(RESOURCE_DIR_NAME));
+        // target/debug/chess-gui -> crate root
+        if let Some(crate_dir) = exe_dir.parent().and_then(Path::parent)
+        {
+            dirs.push(crate_dir.join(RESOURCE_DIR_NAME));
+        }
+    }
+
+    dirs.push(Path::new(env!("CARGO_MANIFEST_DIR")).join(RESOURCE_DIR_NAME));
+
+    if let Ok(working_dir) = env::current_dir()
+    {
+        dirs.push(working_dir.join(RESOURCE_DIR_NAME));
+    }
+
+    dirs
+}
+
+/// The first complete resource directory among the candidates.
+pub fn find_resource_dir() -> Option<PathBuf>
+{
+    candidate_dirs().into_iter().find(|dir| dir.join(MARKER_FILE).is_file())
+}
+
+/// Loads an image by resource path. Files in the resource directory win,
+/// so a compiled in image is only used when the file is not there.
+pub fn load_image(ctx: &mut Context, path: &str) -> GameResult<graphics::Image>
+{
+    #[cfg(feature = "embed-resources")]
+    if !filesystem::is_file(ctx, path)
+    {
+        if let Some((_, bytes)) = EMBEDDED_IMAGES.iter().find(|(name, _)| *name == path)
+        {
+            let decoded = image::load_from_memory(bytes)
+                .map_err(|error| GameError::ResourceLoadError(format!("{} (embedded): {}", path, error)))?
+                .to_rgba8();
+            return graphics::Image::from_rgba8(ctx, decoded.width() as u16, decoded.height() as u16, &decoded);
+        }
+    }
+
+    graphics::Image::new(ctx, path).map_err(|error| match error
+    {
+        // ggez reports undecodable images without saying which file it was
+        GameError::ResourceNotFound(..) => error,
+        other => GameError::ResourceLoadError(format!("{} is corrupt: {}", path, describe(&other))),
+    })
+}
+
+/// Loads several images, collecting every failure instead of stopping at the first one.
+pub fn load_images<K: Copy>(ctx: &mut Context, paths: &[(K, &str)]) -> Result<Vec<(K, graphics::Image)>, GameError>
+{
+    let mut images = vec![];
+    let mut problems = vec![];
+
+    for (key, path) in paths
+    {
+        match load_image(ctx, path)
+        {
+            Ok(image) => images.push((*key, image)),
+            Err(error) => problems.push(describe(&error)),
+        }
+    }
+
+    if problems.is_empty() { Ok(images) } else { Err(GameError::ResourceLoadError(problems.join("\n"))) }
+}
+
+/// One line explanation of a resource error, without the debug formatting ggez uses.
+pub fn describe(error: &GameError) -> String
+{
+    match error
+    {
+        GameError::ResourceNotFound(path, _) => format!("{} is missing", path),
+        GameError::ResourceLoadError(message) => message.clone(),
+        GameError::ConfigError(message) => message.clone(),
+        GameError::FilesystemError(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Full explanation of why the game could not start, listing where resources were looked for.
+pub fn failure_report(error: &GameError, resource_dir: Option<&Path>) -> Vec<String>
+{
+    let mut lines = vec!["The game could not load its resources.".to_string(), String::new()];
+    lines.extend(describe(error).lines().map(|line| format!("  {}", line)));
+    lines.push(String::new());
+
+    match resource_dir
+    {
+        Some(dir) => lines.push(format!("Resources were read from {}", dir.display())),
+        None =>
+        {
+            lines.push(format!("No `{}` directory containing {} was found. Looked in:", RESOURCE_DIR_NAME, MARKER_FILE));
+            lines.extend(candidate_dirs().iter().map(|dir| format!("  {}", dir.display())));
+            lines.push("Set CHESS_GUI_RESOURCES to the resource directory, or build with --features embed-resources.".to_string());
+        }
+    }
+
+    lines
+}
+
+/// Shown instead of the board when the game can not start, so the player sees why.
+pub struct ErrorScreen
+{
+    lines: Vec<String>,
+}
+
+impl ErrorScreen
+{
+    pub fn new(lines: Vec<String>) -> ErrorScreen
+    {
+        ErrorScreen { lines }
+    }
+}
+
+impl event::EventHandler<GameError> for ErrorScreen
+{
+    fn update(&mut self, _ctx: &mut Context) -> GameResult
+    {
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult
+    {
+        graphics::clear(ctx, [0.15, 0.1, 0.12, 1.0].into());
+
+        let text = graphics::Text::new
+        (
+            graphics::TextFragment::from(self.lines.join("\n"))
+                .scale(graphics::PxScale { x: 20.0, y: 20.0 }),
+        );
+        graphics::draw
+        (
+            ctx,
+            &text,
+            graphics::DrawParam::default()
+                .color([1.0, 0.85, 0.8, 1.0].into())
+                .dest([20.0, 20.0]),
+        )?;
+
+        graphics::present(ctx)
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32)
+    {
+        graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))
+            .expect("Failed to resize screen.");
+    }
+}
@@ -0,0 +1,525 @@
+//! The serde form of a game, written with the `serde` feature.
+//!
+//! The schema does not follow the bitboards `Game` keeps internally, so the stored form stays the same
+//! when they change. In JSON the starting position looks like
+//!
+//! ```text
+//! {
+//!   "version": 2,
+//!   "pieces": { "a1": "R", "a2": "P", ..., "h8": "r" },
+//!   "side_to_move": "white",
+//!   "castling": { "white_king_side": true, "white_queen_side": true, "black_king_side": true, "black_queen_side": true },
+//!   "en_passant": null,
+//!   "halfmove_clock": 0,
+//!   "fullmove_number": 1,
+//!   "captured": [],
+//!   "promotion": "Q",
+//!   "state": "in_progress",
+//!   "chess960_rooks": null,
+//!   "variant": "standard",
+//!   "checks": [0, 0],
+//!   "pockets": [],
+//!   "promoted": []
+//! }
+//! ```
+//!
+//! Pieces are written with the letters of `get_board`. `state` is only written for readers,
+//! it is worked out again from the position when a game is read.
+//! A new version number is only used for changes that older readers can not follow.
+//!
+//! Version 2 added the fields from `chess960_rooks` on for Chess960 and the variants, a reader of version 1
+//! would lose them. They come after the fields of version 1, so formats without field names like bincode
+//! still read version 1 games, which get the standard rules.
+
+use super::{piece_char, Game, GameState, Variant, POCKET_KINDS};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Version of the schema written by this build. Games of older versions are read too, newer ones are rejected.
+pub const SCHEMA_VERSION: u32 = 2;
+/// Number of fields in version 1 of the schema, the ones that follow were added in version 2.
+const VERSION_1_FIELDS: usize = 10;
+/// Number of fields in the current version.
+const FIELDS: usize = 15;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Colour {
+    White,
+    Black,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Castling {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+/// A game as it is serialized, see the module documentation.
+/// New fields go at the end, and take a default when a game of an older version is read.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+// the derived code is called by the impls below, which read older versions in compact formats
+#[serde(remote = "Self")]
+pub struct GameData {
+    pub version: u32,
+    /// The piece on every occupied square, keyed by square names like "e4".
+    pub pieces: BTreeMap<String, char>,
+    pub side_to_move: Colour,
+    pub castling: Castling,
+    /// The square a pawn can be taken on en passant, like "e3".
+    pub en_passant: Option<String>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    /// Every piece taken so far in order, like `get_captured`.
+    pub captured: Vec<char>,
+    /// The piece pawns are promoted to, 'Q', 'R', 'B' or 'N'.
+    pub promotion: char,
+    #[serde(default)]
+    pub state: Option<GameState>,
+    /// Only set in Chess960 games: the file of the rook of each castling right, ordered like
+    /// the rights and written for lost rights too, e.g. "hbhb".
+    #[serde(default)]
+    pub chess960_rooks: Option<String>,
+    #[serde(default)]
+    pub variant: Variant,
+    /// The checks given by white and black, only counted in Three-check.
+    #[serde(default)]
+    pub checks: [u32; 2],
+    /// The pieces white and black hold to drop, only used in Crazyhouse, e.g. ['N', 'p'].
+    #[serde(default)]
+    pub pockets: Vec<char>,
+    /// The squares of pieces that were pawns, only kept in Crazyhouse.
+    #[serde(default)]
+    pub promoted: Vec<String>,
+}
+
+impl Serialize for GameData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GameData::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<GameData, D::Error> {
+        // fields are found by name in formats like JSON, the missing ones take their defaults
+        if deserializer.is_human_readable() {
+            return GameData::deserialize(deserializer);
+        }
+        deserializer.deserialize_tuple(FIELDS, CompactVisitor)
+    }
+}
+
+/// Reads the fields one after the other, as formats without field names write them.
+/// Only games of version 2 and later have the fields after the first `VERSION_1_FIELDS`.
+struct CompactVisitor;
+
+impl<'de> Visitor<'de> for CompactVisitor {
+    type Value = GameData;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a game")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<GameData, A::Error> {
+        let mut data = GameData {
+            version: next_field(&mut seq, 0)?,
+            pieces: next_field(&mut seq, 1)?,
+            side_to_move: next_field(&mut seq, 2)?,
+            castling: next_field(&mut seq, 3)?,
+            en_passant: next_field(&mut seq, 4)?,
+            halfmove_clock: next_field(&mut seq, 5)?,
+            fullmove_number: next_field(&mut seq, 6)?,
+            captured: next_field(&mut seq, 7)?,
+            promotion: next_field(&mut seq, 8)?,
+            state: next_field(&mut seq, 9)?,
+            chess960_rooks: None,
+            variant: Variant::default(),
+            checks: [0, 0],
+            pockets: vec![],
+            promoted: vec![],
+        };
+        if data.version >= 2 {
+            data.chess960_rooks = next_field(&mut seq, VERSION_1_FIELDS)?;
+            data.variant = next_field(&mut seq, VERSION_1_FIELDS + 1)?;
+            data.checks = next_field(&mut seq, VERSION_1_FIELDS + 2)?;
+            data.pockets = next_field(&mut seq, VERSION_1_FIELDS + 3)?;
+            data.promoted = next_field(&mut seq, VERSION_1_FIELDS + 4)?;
+        }
+        Ok(data)
+    }
+}
+
+/// The field at `index`, an error if the game ends before it.
+fn next_field<'de, A: SeqAccess<'de>, T: Deserialize<'de>>(seq: &mut A, index: usize) -> Result<T, A::Error> {
+    seq.next_element()?.ok_or_else(|| de::Error::invalid_length(index, &"a game"))
+}
+
+impl From<Game> for GameData {
+    fn from(game: Game) -> GameData {
+        let mut pieces = BTreeMap::new();
+        for (row, rank_text) in game.get_board().split('\n').enumerate() {
+            for (file, c) in rank_text.chars().enumerate() {
+                if c != '*' {
+                    pieces.insert(format!("{}{}", (b'a' + file as u8) as char, 8 - row), c);
+                }
+            }
+        }
+
+        let en_passant = if game.en_passant_at < 16 {
+            let file = (b'a' + game.en_passant_at % 8) as char;
+            Some(format!("{}{}", file, if game.en_passant_at < 8 { 3 } else { 6 }))
+        } else {
+            None
+        };
+
+        GameData {
+            version: SCHEMA_VERSION,
+            pieces,
+            side_to_move: if game.white_turn { Colour::White } else { Colour::Black },
+            castling: Castling {
+                white_king_side: game.castling[0],
+                white_queen_side: game.castling[1],
+                black_king_side: game.castling[2],
+                black_queen_side: game.castling[3],
+            },
+            en_passant,
+            halfmove_clock: game.halfmove_clock,
+            fullmove_number: game.fullmove_number,
+            captured: game.captured.clone(),
+            promotion: piece_char(game.to_promote_to as usize, true),
+            state: Some(game.state),
+            chess960_rooks: if game.chess960 {
+                Some(game.castling_rooks.iter().map(|file| (b'a' + *file as u8) as char).collect())
+            } else {
+                None
+            },
+            variant: game.variant,
+            checks: game.checks,
+            pockets: (0..2)
+                .flat_map(|colour| {
+                    (0..POCKET_KINDS).flat_map(move |index| vec![piece_char(index, colour == 0); game.pockets[colour][index] as usize])
+                })
+                .collect(),
+            promoted: (0..64)
+                .filter(|index| game.promoted & 2_u64.pow(*index) != 0)
+                .map(|index| format!("{}{}", (b'a' + (index / 8) as u8) as char, index % 8 + 1))
+                .collect(),
+        }
+    }
+}
+
+/// Reading goes through `Game::from_variant_fen`, so only positions that can be played from are accepted.
+impl TryFrom<GameData> for Game {
+    type Error = String;
+
+    fn try_from(data: GameData) -> Result<Game, String> {
+        if !(1..=SCHEMA_VERSION).contains(&data.version) {
+            return Err(format!("unsupported schema version {}", data.version));
+        }
+
+        let mut board = [['*'; 8]; 8];
+        for (square, piece) in &data.pieces {
+            let bytes = square.as_bytes();
+            if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1]) {
+                return Err(format!("'{}' is not a square", square));
+            }
+            if !"PQRBNKpqrbnk".contains(*piece) {
+                return Err(format!("'{}' is not a piece", piece));
+            }
+            board[(b'8' - bytes[1]) as usize][(bytes[0] - b'a') as usize] = *piece;
+        }
+
+        let placement: Vec<String> = board
+            .iter()
+            .map(|rank| {
+                let mut text = String::new();
+                let mut empty = 0;
+                for c in rank {
+                    if *c == '*' {
+                        empty += 1;
+                        continue;
+                    }
+                    if empty > 0 {
+                        text += &empty.to_string();
+                        empty = 0;
+                    }
+                    text.push(*c);
+                }
+                if empty > 0 {
+                    text += &empty.to_string();
+                }
+                text
+            })
+            .collect();
+
+        let rights = [
+            (data.castling.white_king_side, 'K'),
+            (data.castling.white_queen_side, 'Q'),
+            (data.castling.black_king_side, 'k'),
+            (data.castling.black_queen_side, 'q'),
+        ];
+        let rook_files: Option<Vec<u32>> = match &data.chess960_rooks {
+            Some(files) if files.len() == 4 && files.bytes().all(|file| (b'a'..=b'h').contains(&file)) => {
+                Some(files.bytes().map(|file| (file - b'a') as u32).collect())
+            }
+            Some(files) => return Err(format!("'{}' are not the files of four castling rooks", files)),
+            None => None,
+        };
+        // Chess960 rights are written like Shredder-FEN, naming the rook's file
+        let castling: String = rights
+            .iter()
+            .enumerate()
+            .filter(|(_, (right, _))| *right)
+            .map(|(index, (_, letter))| match &rook_files {
+                Some(files) if index < 2 => (b'A' + files[index] as u8) as char,
+                Some(files) => (b'a' + files[index] as u8) as char,
+                None => *letter,
+            })
+            .collect();
+
+        let fen = format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            if data.side_to_move == Colour::White { "w" } else { "b" },
+            if castling.is_empty() { "-" } else { &castling },
+            data.en_passant.as_deref().unwrap_or("-"),
+            data.halfmove_clock,
+            data.fullmove_number
+        );
+        let mut game = Game::from_variant_fen(data.variant, &fen).map_err(|error| error.to_string())?;
+        if let Some(files) = rook_files {
+            game.chess960 = true;
+            game.castling_rooks.copy_from_slice(&files);
+        }
+        if data.checks != game.checks {
+            game.checks = data.checks;
+            game.update_state();
+        }
+        // pieces in hand may stop a mate, so the state is worked out again with them
+        if data.variant == Variant::Crazyhouse {
+            for piece in &data.pockets {
+                let index = (0..POCKET_KINDS)
+                    .find(|index| piece_char(*index, piece.is_uppercase()) == *piece)
+                    .ok_or_else(|| format!("'{}' can not be held in a pocket", piece))?;
+                game.pockets[if piece.is_uppercase() { 0 } else { 1 }][index] += 1;
+            }
+            for square in &data.promoted {
+                let bytes = square.as_bytes();
+                if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1]) {
+                    return Err(format!("'{}' is not a square", square));
+                }
+                game.promoted |= 2_u64.pow((bytes[0] - b'a') as u32 * 8 + (bytes[1] - b'1') as u32);
+            }
+            game.update_state();
+        }
+
+        if let Some(piece) = data.captured.iter().find(|piece| !"PQRBNKpqrbnk".contains(**piece)) {
+            return Err(format!("'{}' is not a piece", piece));
+        }
+        game.captured = data.captured;
+
+        if !"QRBN".contains(data.promotion) {
+            return Err(format!("pawns can not be promoted to '{}'", data.promotion));
+        }
+        game.set_promotion(&data.promotion.to_string());
+
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Castling, Colour, GameData};
+    use crate::{Ending, Game, GameState, ParsedMove, Pgn, Variant};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    /// A game as version 1 of the schema wrote it, before Chess960 and the variants.
+    #[derive(Serialize)]
+    struct GameDataV1 {
+        version: u32,
+        pieces: BTreeMap<String, char>,
+        side_to_move: Colour,
+        castling: Castling,
+        en_passant: Option<String>,
+        halfmove_clock: u32,
+        fullmove_number: u32,
+        captured: Vec<char>,
+        promotion: char,
+        state: Option<GameState>,
+    }
+
+    impl From<GameData> for GameDataV1 {
+        fn from(data: GameData) -> GameDataV1 {
+            GameDataV1 {
+                version: 1,
+                pieces: data.pieces,
+                side_to_move: data.side_to_move,
+                castling: data.castling,
+                en_passant: data.en_passant,
+                halfmove_clock: data.halfmove_clock,
+                fullmove_number: data.fullmove_number,
+                captured: data.captured,
+                promotion: data.promotion,
+                state: data.state,
+            }
+        }
+    }
+
+    /// A game using most of the schema: captures, one of them en passant, and lost castling rights.
+    fn played_game() -> Game {
+        let mut game = Game::new();
+        for (from, to) in [("E2", "E4"), ("D7", "D5"), ("E4", "D5"), ("E7", "E5"), ("D5", "E6"), ("E8", "E7")] {
+            assert!(game.make_move(from, to));
+        }
+        game.set_promotion("N");
+        game
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let game = played_game();
+        let json = serde_json::to_string(&game).unwrap();
+        let read: Game = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read.to_fen(), game.to_fen());
+        assert_eq!(read.get_captured(), game.get_captured());
+        assert_eq!(GameData::from(read), GameData::from(game));
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let game = played_game();
+        let bytes = bincode::serialize(&game).unwrap();
+        let read: Game = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(GameData::from(read), GameData::from(game));
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let game = played_game();
+        let old = GameDataV1::from(GameData::from(game.clone()));
+
+        let read: Game = bincode::deserialize(&bincode::serialize(&old).unwrap()).unwrap();
+        assert_eq!(GameData::from(read), GameData::from(game.clone()));
+
+        let read: Game = serde_json::from_value(serde_json::to_value(&old).unwrap()).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+        assert_eq!(read.get_variant(), Variant::Standard);
+
+        // a version 1 game written into the middle of a longer stream leaves the rest to be read
+        let bytes = bincode::serialize(&(old, 7_u8)).unwrap();
+        let (read, after): (Game, u8) = bincode::deserialize(&bytes).unwrap();
+        assert_eq!((read.to_fen(), after), (game.to_fen(), 7));
+    }
+
+    #[test]
+    fn schema_is_stable() {
+        let value = serde_json::to_value(Game::new()).unwrap();
+        assert_eq!(value["version"], 2);
+        assert_eq!(value["pieces"]["e1"], "K");
+        assert_eq!(value["pieces"]["d8"], "q");
+        assert_eq!(value["pieces"].as_object().unwrap().len(), 32);
+        assert_eq!(value["side_to_move"], "white");
+        assert_eq!(value["castling"]["black_queen_side"], true);
+        assert_eq!(value["chess960_rooks"], serde_json::Value::Null);
+        assert_eq!(value["variant"], "standard");
+        assert_eq!(value["checks"], serde_json::json!([0, 0]));
+        assert_eq!(value["pockets"], serde_json::json!([]));
+        assert_eq!(value["en_passant"], serde_json::Value::Null);
+        assert_eq!(value["fullmove_number"], 1);
+        assert_eq!(value["promotion"], "Q");
+        assert_eq!(value["state"], "in_progress");
+
+        let played = serde_json::to_value(played_game()).unwrap();
+        assert_eq!(played["side_to_move"], "white");
+        assert_eq!(played["castling"]["black_king_side"], false);
+        assert_eq!(played["captured"], serde_json::json!(["p", "p"]));
+        assert_eq!(played["promotion"], "N");
+
+        let mut double_step = Game::new();
+        double_step.make_move("E2", "E4");
+        assert_eq!(serde_json::to_value(double_step).unwrap()["en_passant"], "e3");
+    }
+
+    #[test]
+    fn chess960_round_trip() {
+        let mut game = Game::new_chess960(0).unwrap();
+        assert!(game.make_move("D1", "C3"));
+        let value = serde_json::to_value(&game).unwrap();
+        assert_eq!(value["chess960_rooks"], "hfhf");
+
+        let read: Game = serde_json::from_value(value).unwrap();
+        assert!(read.is_chess960());
+        assert_eq!(read.to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn variant_round_trip() {
+        let mut game = Game::new_variant(Variant::ThreeCheck);
+        for (from, to) in [("E2", "E4"), ("F7", "F6"), ("D1", "H5")] {
+            assert!(game.make_move(from, to));
+        }
+        let value = serde_json::to_value(&game).unwrap();
+        assert_eq!(value["variant"], "three_check");
+        assert_eq!(value["checks"], serde_json::json!([1, 0]));
+
+        let read: Game = serde_json::from_value(value).unwrap();
+        assert_eq!(read.get_variant(), Variant::ThreeCheck);
+        assert_eq!(read.get_checks(), [1, 0]);
+
+        // the win is worked out again from the checks
+        let mut value = serde_json::to_value(&game).unwrap();
+        value["checks"] = serde_json::json!([3, 0]);
+        let read: Game = serde_json::from_value(value).unwrap();
+        assert_eq!(read.get_ending(), Some(Ending::ThreeChecks { white_wins: true }));
+    }
+
+    #[test]
+    fn pockets_round_trip() {
+        let game = Game::from_variant_fen(Variant::Crazyhouse, "4k3/1Q~6/8/8/8/8/8/4K3[Nqp] w - - 0 1").unwrap();
+        let value = serde_json::to_value(&game).unwrap();
+        assert_eq!(value["pockets"], serde_json::json!(["N", "p", "q"]));
+        assert_eq!(value["promoted"], serde_json::json!(["b7"]));
+
+        let read: Game = serde_json::from_value(value).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn rejects_impossible_games() {
+        let mut value = serde_json::to_value(Game::new()).unwrap();
+        value["version"] = 3.into();
+        assert!(serde_json::from_value::<Game>(value).is_err());
+
+        let mut value = serde_json::to_value(Game::new()).unwrap();
+        value["pieces"]["i9"] = "Q".into();
+        assert!(serde_json::from_value::<Game>(value).is_err());
+
+        let mut value = serde_json::to_value(Game::new()).unwrap();
+        value["pieces"].as_object_mut().unwrap().remove("e8");
+        assert!(serde_json::from_value::<Game>(value).is_err());
+
+        let mut value = serde_json::to_value(Game::new()).unwrap();
+        value["promotion"] = "K".into();
+        assert!(serde_json::from_value::<Game>(value).is_err());
+    }
+
+    #[test]
+    fn moves_and_states_round_trip() {
+        let promotion = ParsedMove { from: "E7".to_string(), to: "E8".to_string(), promotion: Some('N') };
+        let json = serde_json::to_string(&promotion).unwrap();
+        assert_eq!(json, r#"{"from":"E7","to":"E8","promotion":"N"}"#);
+        assert_eq!(serde_json::from_str::<ParsedMove>(&json).unwrap(), promotion);
+
+        assert_eq!(serde_json::to_string(&GameState::GameOver).unwrap(), r#""game_over""#);
+
+        let pgn = Pgn::parse("[White \"A\"]\n\n1. e4 e5 2. Nf3 1-0").unwrap();
+        let read: Pgn = serde_json::from_str(&serde_json::to_string(&pgn).unwrap()).unwrap();
+        assert_eq!(read, pgn);
+    }
+}
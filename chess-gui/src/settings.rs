@@ -0,0 +1,224 @@
+use crate::clock::TimeControl;
+use crate::config::{Config, Value};
+use crate::layout::SCREEN_SIZE;
+use crate::theme;
+use crate::Orientation;
+
+use std::{env, fs, io, path::PathBuf};
+
+/// Directory created in the platform config directory.
+const APP_DIR_NAME: &str = "schack";
+const SETTINGS_FILE: &str = "settings.toml";
+/// Section all settings are written in.
+const SECTION: &str = "settings";
+
+/// Engine depth used until the player picks another.
+pub const DEFAULT_DEPTH: u32 = 3;
+/// Deepest search allowed, the engine gets very slow beyond it.
+pub const MAX_DEPTH: u32 = 6;
+
+/// Preferences kept between launches. The command line overrides them for a single session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings
+{
+    pub theme: String,
+    pub piece_set: String,
+    pub orientation: Orientation,
+    pub sound: bool,
+    pub time_control: Option<TimeControl>,
+    pub engine_depth: u32,
+    pub window_size: (f32, f32),
+}
+
+impl Default for Settings
+{
+    fn default() -> Settings
+    {
+        Settings
+        {
+            theme: "mushroom".to_string(),
+            piece_set: theme::BUNDLED_PIECE_SET.to_string(),
+            orientation: Orientation::WhiteAtBottom,
+            sound: true,
+            time_control: None,
+            engine_depth: DEFAULT_DEPTH,
+            window_size: SCREEN_SIZE,
+        }
+    }
+}
+
+impl Settings
+{
+    /// Where the settings file lives, in `config_dir`.
+    pub fn path() -> Option<PathBuf>
+    {
+        config_dir().map(|dir| dir.join(SETTINGS_FILE))
+    }
+
+    /// Reads the settings file, anything missing or unreadable keeps its default.
+    /// A broken file is reported but does not stop the game from starting.
+    pub fn load() -> Settings
+    {
+        let path = match Settings::path()
+        {
+            Some(path) if path.is_file() => path,
+            _ => return Settings::default(),
+        };
+
+        let parsed = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| Config::parse(&text).map_err(|error| error.to_string()));
+        match parsed
+        {
+            Ok(config) => Settings::from_config(&config),
+            Err(error) =>
+            {
+                eprintln!("Ignoring settings in {}: {}", path.display(), error);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()>
+    {
+        let path = Settings::path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+        fs::write(path, self.to_config().to_string())
+    }
+
+    fn from_config(config: &Config) -> Settings
+    {
+        let defaults = Settings::default();
+        let number = |key: &str| config.get_number(SECTION, key);
+
+        Settings
+        {
+            theme: config.get_str(SECTION, "theme").map_or(defaults.theme, str::to_string),
+            piece_set: config.get_str(SECTION, "piece_set").map_or(defaults.piece_set, str::to_string),
+            orientation: config.get_str(SECTION, "orientation").and_then(parse_orientation).unwrap_or(defaults.orientation),
+            sound: match config.get(SECTION, "sound")
+            {
+                Some(Value::Bool(sound)) => *sound,
+                _ => defaults.sound,
+            },
+            // an empty string turns the clock off
+            time_control: match config.get_str(SECTION, "time_control")
+            {
+                Some("") => None,
+                Some(text) => TimeControl::parse(text).or(defaults.time_control),
+                None => defaults.time_control,
+            },
+            engine_depth: number("engine_depth")
+                .filter(|depth| *depth >= 1.0 && *depth <= MAX_DEPTH as f64)
+                .map_or(defaults.engine_depth, |depth| depth as u32),
+            window_size: match (number("window_width"), number("window_height"))
+            {
+                (Some(width), Some(height)) if width > 0.0 && height > 0.0 => (width as f32, height as f32),
+                _ => defaults.window_size,
+            },
+        }
+    }
+
+    fn to_config(&self) -> Config
+    {
+        let mut config = Config::default();
+        let mut set = |key: &str, value: Value| config.set(SECTION, key, value);
+
+        set("theme", Value::String(self.theme.clone()));
+        set("piece_set", Value::String(self.piece_set.clone()));
+        set("orientation", Value::String(orientation_name(self.orientation).to_string()));
+        set("sound", Value::Bool(self.sound));
+        set("time_control", Value::String(self.time_control.map_or(String::new(), |control| control.to_string())));
+        set("engine_depth", Value::Number(self.engine_depth as f64));
+        set("window_width", Value::Number(self.window_size.0.round() as f64));
+        set("window_height", Value::Number(self.window_size.1.round() as f64));
+
+        config
+    }
+}
+
+/// Directory of the settings and the saved game: `$XDG_CONFIG_HOME` or `~/.config` on Linux,
+/// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+/// `CHESS_GUI_CONFIG` points somewhere else, mainly for tests and portable installs.
+pub fn config_dir() -> Option<PathBuf>
+{
+    if let Some(dir) = env::var_os("CHESS_GUI_CONFIG") { return Some(PathBuf::from(dir)); }
+
+    let config_dir = if cfg!(target_os = "windows")
+    {
+        env::var_os("APPDATA").map(PathBuf::from)
+    }
+    else if cfg!(target_os = "macos")
+    {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    }
+    else
+    {
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    config_dir.map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// Orientation as written in the settings file and on the command line.
+pub fn parse_orientation(text: &str) -> Option<Orientation>
+{
+    match text
+    {
+        "white" => Some(Orientation::WhiteAtBottom),
+        "black" => Some(Orientation::BlackAtBottom),
+        "auto" => Some(Orientation::AutoFlip),
+        _ => None,
+    }
+}
+
+pub fn orientation_name(orientation: Orientation) -> &'static str
+{
+    match orientation
+    {
+        Orientation::WhiteAtBottom => "white",
+        Orientation::BlackAtBottom => "black",
+        Orientation::AutoFlip => "auto",
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Settings, SECTION};
+    use crate::clock::TimeControl;
+    use crate::config::{Config, Value};
+    use crate::Orientation;
+
+    #[test]
+    fn writes_what_it_reads()
+    {
+        let settings = Settings
+        {
+            theme: "ocean".to_string(),
+            piece_set: "classic".to_string(),
+            orientation: Orientation::AutoFlip,
+            sound: false,
+            time_control: Some(TimeControl { initial: 180.0, increment: 2.0 }),
+            engine_depth: 4,
+            window_size: (1200.0, 700.0),
+        };
+        let text = settings.to_config().to_string();
+        assert_eq!(Settings::from_config(&Config::parse(&text).unwrap()), settings);
+    }
+
+    #[test]
+    fn keeps_defaults_for_bad_values()
+    {
+        let mut config = Config::default();
+        config.set(SECTION, "orientation", Value::String("sideways".to_string()));
+        config.set(SECTION, "engine_depth", Value::Number(40.0));
+        config.set(SECTION, "sound", Value::String("loud".to_string()));
+        config.set(SECTION, "theme", Value::String("ocean".to_string()));
+
+        let settings = Settings::from_config(&config);
+        assert_eq!(settings, Settings { theme: "ocean".to_string(), ..Settings::default() });
+    }
+}
@@ -0,0 +1,139 @@
+use crate::clock::TimeControl;
+use crate::layout::Layout;
+use crate::theme::Theme;
+use crate::ui::{self, SETTING_SCALE};
+
+use ggez::{graphics, Context, GameResult};
+
+/// Height of one setting row, in tiles.
+const ROW_HEIGHT: f32 = 0.6;
+/// Time controls the clock setting steps through, the first plays without a clock.
+pub const TIME_CONTROLS: [Option<(f32, f32)>; 7] =
+[
+    None,
+    Some((1.0, 0.0)),
+    Some((3.0, 2.0)),
+    Some((5.0, 3.0)),
+    Some((10.0, 0.0)),
+    Some((15.0, 10.0)),
+    Some((30.0, 0.0)),
+];
+
+/// A row of the settings screen, clicking it steps the setting to its next value.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SettingsButton
+{
+    Theme,
+    PieceSet,
+    Orientation,
+    Sound,
+    TimeControl,
+    EngineDepth,
+    Close,
+}
+
+const BUTTONS: [SettingsButton; 7] =
+[
+    SettingsButton::Theme,
+    SettingsButton::PieceSet,
+    SettingsButton::Orientation,
+    SettingsButton::Sound,
+    SettingsButton::TimeControl,
+    SettingsButton::EngineDepth,
+    SettingsButton::Close,
+];
+
+/// The time control after this one in `TIME_CONTROLS`, custom ones from the command line go back to the start.
+pub fn next_time_control(current: Option<TimeControl>) -> Option<TimeControl>
+{
+    let presets: Vec<Option<TimeControl>> = TIME_CONTROLS.iter()
+        .map(|preset| preset.map(|(minutes, increment)| TimeControl { initial: minutes * 60.0, increment }))
+        .collect();
+    let index = presets.iter().position(|preset| *preset == current).map_or(0, |index| index + 1);
+
+    presets[index % presets.len()]
+}
+
+/// Rows below the title, in the side panel where the move list usually is.
+fn buttons(layout: &Layout) -> Vec<(SettingsButton, graphics::Rect)>
+{
+    let panel = layout.panel_rect();
+    let row_height = ROW_HEIGHT * layout.cell;
+
+    BUTTONS.iter().enumerate().flat_map(|(index, button)|
+    {
+        // the close button keeps a row free above it
+        let row = if *button == SettingsButton::Close { index + 2 } else { index + 1 };
+        ui::button_row(&[*button], graphics::Rect::new(panel.x, panel.y + row as f32 * row_height, panel.w, row_height))
+    })
+    .collect()
+}
+
+pub fn button_at(layout: &Layout, x: f32, y: f32) -> Option<SettingsButton>
+{
+    ui::button_at(&buttons(layout), x, y)
+}
+
+/// Draws the title and one row per setting, `label` gives the text of each row.
+pub fn draw(ctx: &mut Context, layout: &Layout, theme: &Theme, label: impl Fn(SettingsButton) -> String) -> GameResult
+{
+    let text = |content: String|
+    {
+        graphics::Text::new
+        (
+            graphics::TextFragment::from(content)
+                .font(theme.font)
+                .scale(layout.text_scale(SETTING_SCALE)),
+        )
+    };
+
+    let panel = layout.panel_rect();
+    let title = text("Settings".to_string());
+    let title_dimensions = title.dimensions(ctx);
+    graphics::draw
+    (
+        ctx,
+        &title,
+        graphics::DrawParam::default()
+            .color(theme.text)
+            .dest([panel.x + (panel.w - title_dimensions.w) / 2.0, panel.y + (ROW_HEIGHT * layout.cell - title_dimensions.h) / 2.0]),
+    )?;
+
+    for (button, rect) in buttons(layout)
+    {
+        let background = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, [0.0, 0.0, 0.0, 0.25].into())?;
+        graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+
+        let row_text = text(label(button));
+        let dimensions = row_text.dimensions(ctx);
+        // long names shrink to fit the row
+        let fit = ((rect.w - rect.h / 2.0) / dimensions.w).min(1.0);
+        graphics::draw
+        (
+            ctx,
+            &row_text,
+            graphics::DrawParam::default()
+                .color(theme.text)
+                .scale([fit, fit])
+                .dest([rect.x + rect.h / 4.0, rect.y + (rect.h - dimensions.h * fit) / 2.0]),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::next_time_control;
+    use crate::clock::TimeControl;
+
+    #[test]
+    fn steps_through_the_time_controls()
+    {
+        assert_eq!(next_time_control(None), Some(TimeControl { initial: 60.0, increment: 0.0 }));
+        assert_eq!(next_time_control(Some(TimeControl { initial: 1800.0, increment: 0.0 })), None);
+        // a time control from the command line starts the presets over
+        assert_eq!(next_time_control(Some(TimeControl { initial: 420.0, increment: 7.0 })), None);
+    }
+}
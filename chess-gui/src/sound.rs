@@ -0,0 +1,70 @@
+use ggez::audio::{self, SoundSource};
+use ggez::{Context, GameResult};
+
+/// Samples per second of the generated sounds.
+const SAMPLE_RATE: u32 = 22050;
+
+/// Short clicks played when a piece lands, made up on startup so no sound files have to be shipped.
+pub struct Sounds
+{
+    move_sound: audio::Source,
+    capture_sound: audio::Source,
+    // A failure is reported once, the next move would most likely fail the same way.
+    failed: bool,
+}
+
+impl Sounds
+{
+    pub fn new(ctx: &mut Context) -> GameResult<Sounds>
+    {
+        Ok(Sounds
+        {
+            move_sound: audio::Source::from_data(ctx, audio::SoundData::from_bytes(&tone(660.0, 0.06)))?,
+            // captures sound lower and a little longer
+            capture_sound: audio::Source::from_data(ctx, audio::SoundData::from_bytes(&tone(330.0, 0.12)))?,
+            failed: false,
+        })
+    }
+
+    pub fn play(&mut self, ctx: &Context, capture: bool)
+    {
+        let source = if capture { &mut self.capture_sound } else { &mut self.move_sound };
+        // a missing sound is not worth interrupting the game for
+        if let Err(error) = source.play_detached(ctx)
+        {
+            if !self.failed { eprintln!("Failed to play sound, later failures are not reported: {}", error); }
+            self.failed = true;
+        }
+    }
+}
+
+/// A fading sine wave as a 16 bit mono WAV file.
+fn tone(frequency: f32, seconds: f32) -> Vec<u8>
+{
+    let count = (SAMPLE_RATE as f32 * seconds) as u32;
+    let data_size = count * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // size of the format block
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // bytes per second
+    wav.extend_from_slice(&2u16.to_le_bytes()); // bytes per sample
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+
+    for index in 0..count
+    {
+        let time = index as f32 / SAMPLE_RATE as f32;
+        let fade = (1.0 - index as f32 / count as f32).powi(3);
+        let sample = (2.0 * std::f32::consts::PI * frequency * time).sin() * fade * 0.4;
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    wav
+}
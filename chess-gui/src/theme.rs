@@ -0,0 +1,288 @@
+use crate::config::Config;
+use crate::resources;
+
+use ggez::{filesystem, graphics, Context, GameError, GameResult};
+use std::{collections::HashMap, io::Read};
+
+/// Resource file listing the selectable board themes, one section per theme.
+const THEMES_PATH: &str = "/themes.toml";
+/// Resource directory holding one sub directory per piece set. Each has a `manifest.toml` like
+///
+/// ```toml
+/// lift = 14 # pixels the piece stands above its shadow
+///
+/// [sprites]
+/// K = "white_king.png"
+/// k = "black_king.png"
+/// # ... one line per piece, s = "shadow.png" is optional
+/// ```
+const PIECE_SETS_DIR: &str = "/pieces";
+
+/// Name of the piece set that ships with the game and is used whenever another set fails to load.
+pub const BUNDLED_PIECE_SET: &str = "mushroom";
+
+/// Characters every piece set has to provide a sprite for, 's' (the shadow) is optional.
+const PIECES: [char; 12] = ['k', 'q', 'r', 'p', 'b', 'n', 'K', 'Q', 'R', 'P', 'B', 'N'];
+
+/// Colours and font the board is drawn with.
+#[derive(Clone, Debug)]
+pub struct Theme
+{
+    pub name: String,
+    pub light: graphics::Color,
+    pub dark: graphics::Color,
+    pub highlight: graphics::Color,
+    pub last_move: graphics::Color,
+    pub check: graphics::Color,
+    pub background: graphics::Color,
+    pub text: graphics::Color,
+    pub font: graphics::Font,
+}
+
+impl Theme
+{
+    /// The original mushroom colours, used for anything a theme file leaves out.
+    pub fn mushroom() -> Theme
+    {
+        Theme
+        {
+            name: "mushroom".to_string(),
+            light: graphics::Color::new(228.0 / 255.0, 196.0 / 255.0, 108.0 / 255.0, 1.0),
+            dark: graphics::Color::new(188.0 / 255.0, 140.0 / 255.0, 76.0 / 255.0, 1.0),
+            highlight: graphics::Color::new(230.0 / 255.0, 200.0 / 255.0, 50.0 / 255.0, 0.5),
+            last_move: graphics::Color::new(160.0 / 255.0, 190.0 / 255.0, 60.0 / 255.0, 0.45),
+            check: graphics::Color::new(220.0 / 255.0, 40.0 / 255.0, 30.0 / 255.0, 0.6),
+            background: graphics::Color::new(0.27, 0.20, 0.24, 1.0),
+            text: graphics::Color::new(1.0, 0.95, 0.85, 1.0),
+            font: graphics::Font::default(),
+        }
+    }
+
+    /// Loads every theme in the themes file, the bundled mushroom theme always comes first.
+    /// A missing file just leaves the bundled theme, one that can not be read is reported.
+    /// A bad colour or font only loses that entry, with a warning.
+    pub fn load_all(ctx: &mut Context) -> GameResult<Vec<Theme>>
+    {
+        let mut themes = vec![Theme::mushroom()];
+
+        if !filesystem::exists(ctx, THEMES_PATH) { return Ok(themes); }
+
+        let config = read_config(ctx, THEMES_PATH)?;
+        let names: Vec<String> = config.sections().map(|name| name.to_string()).collect();
+
+        for name in names
+        {
+            let mut theme = Theme::from_config(&config, &name);
+
+            if let Some(path) = config.get_str(&name, "font")
+            {
+                match graphics::Font::new(ctx, path)
+                {
+                    Ok(font) => theme.font = font,
+                    Err(error) => eprintln!("{}: theme `{}` keeps the default font, `{}` did not load: {}", THEMES_PATH, name, path, error),
+                }
+            }
+
+            // a theme named like the bundled one replaces it
+            match themes.iter().position(|existing| existing.name == theme.name)
+            {
+                Some(index) => themes[index] = theme,
+                None => themes.push(theme),
+            }
+        }
+
+        Ok(themes)
+    }
+
+    /// The colours of one section of the themes file over the mushroom ones.
+    /// A colour that does not parse is left at the mushroom colour.
+    fn from_config(config: &Config, name: &str) -> Theme
+    {
+        let mut theme = Theme::mushroom();
+        theme.name = name.to_string();
+
+        for (key, target) in
+        [
+            ("light", &mut theme.light),
+            ("dark", &mut theme.dark),
+            ("highlight", &mut theme.highlight),
+            ("last_move", &mut theme.last_move),
+            ("check", &mut theme.check),
+            ("background", &mut theme.background),
+            ("text", &mut theme.text),
+        ]
+        {
+            if let Some(value) = config.get_str(name, key)
+            {
+                match parse_color(value)
+                {
+                    Some(color) => *target = color,
+                    None => eprintln!("{}: theme `{}` has an invalid {} colour `{}`, skipping it", THEMES_PATH, name, key, value),
+                }
+            }
+        }
+
+        theme
+    }
+}
+
+/// Piece sprites and how they are placed on a tile.
+pub struct PieceSet
+{
+    pub sprites: HashMap<char, graphics::Image>,
+    /// How far the piece is drawn above its shadow, in sprite pixels.
+    pub lift: f32,
+}
+
+impl PieceSet
+{
+    /// Directory names of the installed piece sets, the bundled set first.
+    pub fn available(ctx: &mut Context) -> Vec<String>
+    {
+        let mut names = vec![BUNDLED_PIECE_SET.to_string()];
+
+        if let Ok(entries) = filesystem::read_dir(ctx, PIECE_SETS_DIR)
+        {
+            let mut found: Vec<String> = entries
+                .filter(|path| filesystem::is_file(ctx, path.join("manifest.toml")))
+                .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+                .filter(|name| name != BUNDLED_PIECE_SET)
+                .collect();
+            found.sort();
+            names.append(&mut found);
+        }
+
+        names
+    }
+
+    /// Loads a piece set by directory name, see `available`.
+    pub fn load(ctx: &mut Context, name: &str) -> GameResult<PieceSet>
+    {
+        if name == BUNDLED_PIECE_SET { return PieceSet::bundled(ctx); }
+
+        let dir = format!("{}/{}", PIECE_SETS_DIR, name);
+        let manifest_path = format!("{}/manifest.toml", dir);
+        let manifest = read_config(ctx, &manifest_path)?;
+
+        let mut sprites = HashMap::new();
+        for (key, value) in manifest.entries("sprites")
+        {
+            let piece = match (key.chars().next(), key.chars().count())
+            {
+                (Some(piece), 1) if PIECES.contains(&piece) || piece == 's' => piece,
+                _ => return Err(GameError::ConfigError(format!("{}: unknown piece `{}`", manifest_path, key))),
+            };
+            let file = match value
+            {
+                crate::config::Value::String(file) => file,
+                _ => return Err(GameError::ConfigError(format!("{}: sprite for `{}` is not a file name", manifest_path, key))),
+            };
+            sprites.insert(piece, resources::load_image(ctx, &format!("{}/{}", dir, file))?);
+        }
+
+        if let Some(missing) = PIECES.iter().find(|piece| !sprites.contains_key(piece))
+        {
+            return Err(GameError::ConfigError(format!("{}: no sprite for `{}`", manifest_path, missing)));
+        }
+
+        Ok(PieceSet
+        {
+            sprites,
+            lift: manifest.get_number("", "lift").unwrap_or(0.0) as f32,
+        })
+    }
+
+    /// Loads a piece set, falling back to the bundled one if it can not be used.
+    pub fn load_or_bundled(ctx: &mut Context, name: &str) -> GameResult<PieceSet>
+    {
+        PieceSet::load(ctx, name).or_else(|error|
+        {
+            eprintln!("Could not load piece set `{}`, using {} instead: {}", name, BUNDLED_PIECE_SET, error);
+            PieceSet::bundled(ctx)
+        })
+    }
+
+    #[rustfmt::skip] // Skips formatting on this function (not recommended)
+    /// The mushroom pieces that ship in the resource directory.
+    fn bundled(ctx: &mut Context) -> GameResult<PieceSet>
+    {
+        let sprites = resources::load_images(ctx, &[
+            ('k', "/black_king.png"),
+            ('q', "/black_queen.png"),
+            ('r', "/black_rook.png"),
+            ('p', "/black_pawn.png"),
+            ('b', "/black_bishop.png"),
+            ('n', "/black_knight.png"),
+            ('K', "/white_king.png"),
+            ('Q', "/white_queen.png"),
+            ('R', "/white_rook.png"),
+            ('P', "/white_pawn.png"),
+            ('B', "/white_bishop.png"),
+            ('N', "/white_knight.png"),
+            ('s', "/shadow.png")
+        ])?
+            .into_iter()
+            .collect();
+
+        Ok(PieceSet { sprites, lift: 14.0 })
+    }
+}
+
+/// Reads and parses a config file from the resource directories.
+fn read_config(ctx: &mut Context, path: &str) -> GameResult<Config>
+{
+    let mut text = String::new();
+    filesystem::open(ctx, path)?
+        .read_to_string(&mut text)
+        .map_err(|error| GameError::ResourceLoadError(format!("{}: {}", path, error)))?;
+
+    Config::parse(&text).map_err(|error| GameError::ConfigError(format!("{}: {}", path, error)))
+}
+
+/// Parses a `#rrggbb` or `#rrggbbaa` hex colour.
+pub fn parse_color(text: &str) -> Option<graphics::Color>
+{
+    let hex = text.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) { return None; }
+
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+    Some(graphics::Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{parse_color, Theme};
+    use crate::config::Config;
+    use ggez::graphics::Color;
+
+    #[test]
+    fn colours_are_read_from_hex()
+    {
+        assert_eq!(parse_color("#ff8000"), Some(Color::from_rgba(255, 128, 0, 255)));
+        assert_eq!(parse_color("#FF800040"), Some(Color::from_rgba(255, 128, 0, 64)));
+
+        assert_eq!(parse_color("ff8000"), None);
+        assert_eq!(parse_color("#ff800"), None);
+        assert_eq!(parse_color("#ff80004"), None);
+        assert_eq!(parse_color("#ff8000400"), None);
+        assert_eq!(parse_color("#gg8000"), None);
+        assert_eq!(parse_color("#ff80é"), None);
+        assert_eq!(parse_color(""), None);
+    }
+
+    #[test]
+    fn a_bad_colour_only_loses_that_entry()
+    {
+        let config = Config::parse("[night]\nlight = \"#102030\"\ndark = \"102030\"\ncheck = \"#12\"\n").unwrap();
+        let theme = Theme::from_config(&config, "night");
+        let mushroom = Theme::mushroom();
+
+        assert_eq!(theme.name, "night");
+        assert_eq!(theme.light, Color::from_rgba(16, 32, 48, 255));
+        assert_eq!(theme.dark, mushroom.dark);
+        assert_eq!(theme.check, mushroom.check);
+    }
+}
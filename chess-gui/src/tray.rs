@@ -0,0 +1,192 @@
+use crate::layout::Layout;
+use crate::theme::{PieceSet, Theme};
+use crate::ui::ADVANTAGE_SCALE;
+
+use chess_gui::CHECKS_TO_WIN;
+use ggez::{graphics, Context, GameResult};
+
+/// Side of a captured piece, in tiles.
+const PIECE_SIZE: f32 = 0.4;
+/// Distance between captured pieces of the same type, they overlap to save room.
+const SAME_PIECE_STEP: f32 = 0.2;
+/// Width of the Three-check counter at the end of a tray, in tiles.
+pub const CHECKS_WIDTH: f32 = 1.6;
+/// Order the captured pieces are shown in, cheapest first.
+const ORDER: [char; 5] = ['p', 'n', 'b', 'r', 'q'];
+/// Side of a piece in a Crazyhouse pocket, in tiles, large enough to be picked up.
+const POCKET_PIECE_SIZE: f32 = 0.7;
+/// Width given to each kind of piece in a pocket, the piece followed by how many there are.
+const POCKET_SLOT: f32 = 1.1;
+
+/// Draws the pieces one player has captured, grouped by type and wrapping onto a second row,
+/// followed by "+N" if that player is ahead in material.
+pub fn draw
+(
+    ctx: &mut Context,
+    layout: &Layout,
+    theme: &Theme,
+    pieces: &PieceSet,
+    area: graphics::Rect,
+    captured: &[char],
+    advantage: i32,
+) -> GameResult
+{
+    let size = PIECE_SIZE * layout.cell;
+    let row_height = area.h / 2.0;
+    let mut position = (area.x, area.y + (row_height - size) / 2.0);
+
+    let mut place = |width: f32|
+    {
+        if position.0 + width > area.x + area.w
+        {
+            position = (area.x, position.1 + row_height);
+        }
+        let placed = position;
+        position.0 += width;
+        placed
+    };
+
+    for kind in ORDER
+    {
+        let taken: Vec<char> = captured.iter().copied().filter(|piece| piece.to_ascii_lowercase() == kind).collect();
+
+        for (index, piece) in taken.iter().enumerate()
+        {
+            // the last piece of a group is shown whole, with a small gap to the next group
+            let width = if index + 1 < taken.len() { SAME_PIECE_STEP * layout.cell } else { size * 1.15 };
+            let at = place(width);
+
+            let sprite = pieces.sprites.get(piece).unwrap();
+            let scale = size / sprite.width() as f32;
+            graphics::draw
+            (
+                ctx,
+                sprite,
+                graphics::DrawParam::default()
+                    .scale([scale, scale])
+                    .dest([at.0, at.1]),
+            )?;
+        }
+    }
+
+    if advantage > 0
+    {
+        let text = graphics::Text::new
+        (
+            graphics::TextFragment::from(format!("+{}", advantage))
+                .font(theme.font)
+                .scale(layout.text_scale(ADVANTAGE_SCALE)),
+        );
+        let dimensions = text.dimensions(ctx);
+        let at = place(dimensions.w);
+        graphics::draw
+        (
+            ctx,
+            &text,
+            graphics::DrawParam::default()
+                .color(theme.text)
+                .dest([at.0, at.1 + (size - dimensions.h) / 2.0]),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Draws the checks a player has given in Three-check at the end of the area, e.g. "Checks 1/3".
+/// The count turns to the check colour once one more check wins.
+pub fn draw_checks(ctx: &mut Context, layout: &Layout, theme: &Theme, area: graphics::Rect, checks: u32) -> GameResult
+{
+    let text = graphics::Text::new
+    (
+        graphics::TextFragment::from(format!("Checks {}/{}", checks, CHECKS_TO_WIN))
+            .font(theme.font)
+            .scale(layout.text_scale(ADVANTAGE_SCALE)),
+    );
+    let dimensions = text.dimensions(ctx);
+    let width = CHECKS_WIDTH * layout.cell;
+    // long names shrink to fit like the settings rows
+    let fit = (width / dimensions.w).min(1.0);
+    graphics::draw
+    (
+        ctx,
+        &text,
+        graphics::DrawParam::default()
+            .color(if checks + 1 >= CHECKS_TO_WIN { theme.check } else { theme.text })
+            .scale([fit, fit])
+            .dest([area.x + area.w - width, area.y + (area.h - dimensions.h * fit) / 2.0]),
+    )
+}
+
+/// Where each kind of piece in a Crazyhouse pocket is drawn, in `ORDER` and only the kinds held.
+/// The pocket holds the pieces in their holder's colour with how many there are.
+fn pocket_slots(layout: &Layout, area: graphics::Rect, pocket: &[(char, u32)]) -> Vec<(char, u32, graphics::Rect)>
+{
+    let size = POCKET_PIECE_SIZE * layout.cell;
+    let mut x = area.x;
+    let mut slots = vec![];
+    for kind in ORDER
+    {
+        if let Some((piece, count)) = pocket.iter().copied().find(|(piece, count)| piece.to_ascii_lowercase() == kind && *count > 0)
+        {
+            slots.push((piece, count, graphics::Rect::new(x, area.y + (area.h - size) / 2.0, size, size)));
+            x += POCKET_SLOT * layout.cell;
+        }
+    }
+    slots
+}
+
+/// Draws the pieces a player holds to drop in Crazyhouse, one of each kind with "xN" after it when there are several.
+pub fn draw_pocket
+(
+    ctx: &mut Context,
+    layout: &Layout,
+    theme: &Theme,
+    pieces: &PieceSet,
+    area: graphics::Rect,
+    pocket: &[(char, u32)],
+) -> GameResult
+{
+    for (piece, count, rect) in pocket_slots(layout, area, pocket)
+    {
+        let sprite = pieces.sprites.get(&piece).unwrap();
+        let scale = rect.w / sprite.width() as f32;
+        graphics::draw
+        (
+            ctx,
+            sprite,
+            graphics::DrawParam::default()
+                .scale([scale, scale])
+                .dest([rect.x, rect.y]),
+        )?;
+
+        if count > 1
+        {
+            let text = graphics::Text::new
+            (
+                graphics::TextFragment::from(format!("x{}", count))
+                    .font(theme.font)
+                    .scale(layout.text_scale(ADVANTAGE_SCALE)),
+            );
+            let dimensions = text.dimensions(ctx);
+            graphics::draw
+            (
+                ctx,
+                &text,
+                graphics::DrawParam::default()
+                    .color(theme.text)
+                    .dest([rect.x + rect.w, rect.y + rect.h - dimensions.h]),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The piece of a Crazyhouse pocket under a window position, None if there is none.
+pub fn pocket_piece_at(layout: &Layout, area: graphics::Rect, pocket: &[(char, u32)], x: f32, y: f32) -> Option<char>
+{
+    pocket_slots(layout, area, pocket)
+        .into_iter()
+        .find(|(_, _, rect)| rect.contains([x, y]))
+        .map(|(piece, _, _)| piece)
+}
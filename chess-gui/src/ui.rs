@@ -0,0 +1,59 @@
+use ggez::graphics;
+
+// Font sizes of the text around the board. Like the rank and file labels they are given for the
+// tile size in `GRID_CELL_SIZE` and scaled to the window with `Layout::text_scale`.
+
+/// The moves in the move list.
+pub const MOVE_SCALE: f32 = 30.0;
+/// The material advantage next to the captured pieces.
+pub const ADVANTAGE_SCALE: f32 = 30.0;
+/// The typed move and the message below it.
+pub const INPUT_SCALE: f32 = 30.0;
+/// The buttons of the board editor.
+pub const EDITOR_SCALE: f32 = 28.0;
+/// The remaining time on the clock.
+pub const CLOCK_SCALE: f32 = 34.0;
+/// The rows of the settings screen.
+pub const SETTING_SCALE: f32 = 28.0;
+/// The offer to resume a saved game.
+pub const OFFER_SCALE: f32 = 28.0;
+/// The labels of the game actions below the move list.
+pub const ACTION_SCALE: f32 = 24.0;
+
+/// Lays out buttons side by side in a row filling `area`, a small gap keeps neighbouring buttons apart.
+pub fn button_row<B: Copy>(buttons: &[B], area: graphics::Rect) -> Vec<(B, graphics::Rect)>
+{
+    let width = area.w / buttons.len() as f32;
+
+    buttons.iter().enumerate().map(|(index, button)|
+    {
+        (*button, graphics::Rect::new(area.x + index as f32 * width + 2.0, area.y + 2.0, width - 4.0, area.h - 4.0))
+    })
+    .collect()
+}
+
+/// The button under a window position, if any.
+pub fn button_at<B: Copy>(buttons: &[(B, graphics::Rect)], x: f32, y: f32) -> Option<B>
+{
+    buttons.iter().find(|(_, rect)| rect.contains([x, y])).map(|(button, _)| *button)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{button_at, button_row};
+    use ggez::graphics::Rect;
+
+    #[test]
+    fn buttons_share_the_row_with_a_gap()
+    {
+        let row = button_row(&['a', 'b'], Rect::new(10.0, 20.0, 100.0, 30.0));
+        assert_eq!(row, vec![('a', Rect::new(12.0, 22.0, 46.0, 26.0)), ('b', Rect::new(62.0, 22.0, 46.0, 26.0))]);
+
+        assert_eq!(button_at(&row, 30.0, 30.0), Some('a'));
+        assert_eq!(button_at(&row, 100.0, 45.0), Some('b'));
+        // between the buttons and outside the row
+        assert_eq!(button_at(&row, 60.0, 30.0), None);
+        assert_eq!(button_at(&row, 30.0, 60.0), None);
+    }
+}
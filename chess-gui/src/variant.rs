@@ -0,0 +1,278 @@
+use super::{Ending, Game};
+use std::fmt;
+
+/// The rules a game is played by. Every variant starts from the standard position and moves the pieces
+/// the standard way unless said otherwise, Chess960 castling works in all of them that castle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Bringing the king to one of the four centre squares wins.
+    KingOfTheHill,
+    /// Checking the opponent for the third time wins, see `Game::get_checks`.
+    ThreeCheck,
+    /// Losing every piece or having no move wins. Captures have to be made, there is no check
+    /// so the king can be taken like any piece, and there is no castling.
+    Antichess,
+    /// Captured pieces go into the capturer's pocket and may be dropped on an empty square instead of moving,
+    /// see `Game::get_pocket`. A promoted piece goes back into the pocket as a pawn.
+    Crazyhouse,
+    /// A capture blows up the capturing piece and every piece around it but the pawns.
+    /// Kings can not capture, and blowing up the other king wins.
+    Atomic,
+    /// White plays 36 pawns and no king against the standard black pieces. White wins by checkmate,
+    /// black by taking every white piece. White pawns on the first rank may step two squares.
+    Horde,
+}
+
+/// Every variant, in the order they are offered.
+pub const VARIANTS: [Variant; 7] = [
+    Variant::Standard,
+    Variant::KingOfTheHill,
+    Variant::ThreeCheck,
+    Variant::Antichess,
+    Variant::Crazyhouse,
+    Variant::Atomic,
+    Variant::Horde,
+];
+
+/// The starting position of Horde.
+const HORDE_START: &str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+
+/// The centre squares of King of the Hill by square index: d4, d5, e4 and e5.
+const HILL: [u32; 4] = [27, 28, 35, 36];
+
+/// Checks that win a Three-check game.
+pub const CHECKS_TO_WIN: u32 = 3;
+
+impl Variant {
+    /// One word for the variant as written in the settings and on the command line, e.g. "kingofthehill".
+    pub fn key(self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::ThreeCheck => "threecheck",
+            Variant::Antichess => "antichess",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::Atomic => "atomic",
+            Variant::Horde => "horde",
+        }
+    }
+
+    /// Reads a variant from its key or its name, ignoring case, spaces and hyphens,
+    /// so the PGN Variant tag "King of the Hill" is read as well as "kingofthehill".
+    pub fn parse(text: &str) -> Option<Variant> {
+        let key: String = text
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        VARIANTS.iter().copied().find(|variant| variant.key() == key)
+    }
+
+    /// True if the king has to be kept out of check and can castle.
+    pub fn has_check(self) -> bool {
+        self != Variant::Antichess
+    }
+
+    /// How many kings white and black have to set up with, None if any number will do.
+    pub fn kings(self) -> Option<[u32; 2]> {
+        match self {
+            Variant::Antichess => None,
+            Variant::Horde => Some([0, 1]),
+            _ => Some([1, 1]),
+        }
+    }
+}
+
+/// The name as written in the PGN Variant tag, e.g. "King of the Hill".
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Variant::Standard => "Standard",
+                Variant::KingOfTheHill => "King of the Hill",
+                Variant::ThreeCheck => "Three-check",
+                Variant::Antichess => "Antichess",
+                Variant::Crazyhouse => "Crazyhouse",
+                Variant::Atomic => "Atomic",
+                Variant::Horde => "Horde",
+            }
+        )
+    }
+}
+
+impl Game {
+    /// Sets up the starting position of a variant.
+    pub fn new_variant(_variant: Variant) -> Game {
+        if _variant == Variant::Horde {
+            return Game::from_variant_fen(_variant, HORDE_START).unwrap();
+        }
+        let mut game = Game::new();
+        game.variant = _variant;
+        if !_variant.has_check() {
+            game.castling = [false; 4];
+        }
+        game
+    }
+
+    /// Returns the rules the game is played by.
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Returns how many times white and black have given check. Only counted in Three-check.
+    pub fn get_checks(&self) -> [u32; 2] {
+        self.checks
+    }
+
+    /// The way the variant's own rules decided the game, None while it goes on or if only checkmate can end it.
+    pub(crate) fn variant_ending(&mut self) -> Option<Ending> {
+        match self.variant {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::KingOfTheHill => (0..2)
+                .find(|colour| HILL.iter().any(|index| self.pieces[5] & self.colour_of_piece[*colour] & 2_u64.pow(*index) != 0))
+                .map(|colour| Ending::Hill { white_wins: colour == 0 }),
+            Variant::ThreeCheck => (0..2)
+                .find(|colour| self.checks[*colour] >= CHECKS_TO_WIN)
+                .map(|colour| Ending::ThreeChecks { white_wins: colour == 0 }),
+            Variant::Atomic => (0..2)
+                .find(|colour| self.pieces[5] & self.colour_of_piece[*colour] == 0)
+                .map(|colour| Ending::Explosion { white_wins: colour == 1 }),
+            Variant::Horde => Some(Ending::HordeCaptured).filter(|_| self.colour_of_piece[0] == 0),
+            Variant::Antichess => {
+                if self.no_valid_moves_for_colour(self.white_turn) {
+                    Some(Ending::NoMovesLeft { white_wins: self.white_turn })
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// True if the piece on `_from` takes something by moving to `_to`, en passant included.
+    fn is_capture(&self, _from: &str, _to: &str) -> bool {
+        let (from_file, from_rank) = self.transform_input(_from);
+        let (to_file, to_rank) = self.transform_input(_to);
+        let colour = if self.is_white(from_file, from_rank) { 0 } else { 1 };
+        let pawn = self.pieces[0] & 2_u64.pow(from_file * 8 + from_rank) != 0;
+        (self.colour_of_piece[1 - colour] & 2_u64.pow(to_file * 8 + to_rank) != 0) | (pawn & (from_file != to_file))
+    }
+
+    /// In Antichess a piece may only move without capturing if no piece of its side can capture.
+    pub(crate) fn forced_captures(&mut self, _is_white: bool, moves: Vec<String>, _from: &str) -> Vec<String> {
+        if self.variant != Variant::Antichess {
+            return moves;
+        }
+        let captures: Vec<String> = moves.iter().filter(|to| self.is_capture(_from, to)).cloned().collect();
+        if !captures.is_empty() {
+            return captures;
+        }
+
+        let own = self.colour_of_piece[if _is_white { 0 } else { 1 }];
+        let any_capture = (0..64).filter(|index| own & 2_u64.pow(*index) != 0).any(|index| {
+            let from = self.transform_back(index / 8, index % 8);
+            self.search_piece_moves(&from)
+                .unwrap_or_default()
+                .iter()
+                .any(|to| self.is_capture(&from, to))
+        });
+        if any_capture {
+            vec![]
+        } else {
+            moves
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Variant, VARIANTS};
+    use crate::{assert_perft, Ending, Game};
+
+    #[test]
+    fn reads_its_names() {
+        for variant in VARIANTS {
+            assert_eq!(Variant::parse(variant.key()), Some(variant));
+            assert_eq!(Variant::parse(&variant.to_string()), Some(variant));
+        }
+        assert_eq!(Variant::parse("3check"), None);
+        assert_eq!(Variant::parse("King-of-the-Hill"), Some(Variant::KingOfTheHill));
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        assert_perft(Variant::KingOfTheHill, "4k3/8/8/8/2K5/8/8/8 w - - 0 1", &[8, 30, 236]);
+        assert_perft(Variant::KingOfTheHill, "rnbq1bnr/ppppkppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR w - - 2 3", &[23, 531, 13337]);
+
+        let mut game = Game::from_variant_fen(Variant::KingOfTheHill, "4k3/8/8/8/2K5/8/8/8 w - - 0 1").unwrap();
+        assert!(game.make_move("C4", "D4"));
+        assert_eq!(game.get_ending(), Some(Ending::Hill { white_wins: true }));
+        // nothing moves once the game is decided
+        assert_eq!(game.get_possible_moves("E8"), Some(vec![]));
+    }
+
+    #[test]
+    fn three_check() {
+        let start = "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1+3 0 3";
+        assert_perft(Variant::ThreeCheck, start, &[30, 1097, 33373]);
+
+        let mut game = Game::from_variant_fen(Variant::ThreeCheck, start).unwrap();
+        assert_eq!(game.get_checks(), [2, 0]);
+        assert_eq!(game.to_fen(), start);
+        assert_eq!(game.move_to_san("F1", "B5").as_deref(), Some("Bb5+"));
+        assert!(game.make_move("F1", "B5"));
+        assert_eq!(game.get_checks(), [3, 0]);
+        assert_eq!(game.get_ending(), Some(Ending::ThreeChecks { white_wins: true }));
+
+        assert!(Game::from_variant_fen(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/4K3 w - - 4+3 0 1").is_err());
+    }
+
+    #[test]
+    fn antichess() {
+        assert_perft(Variant::Antichess, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", &[20, 400, 8067]);
+        assert_perft(Variant::Antichess, "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 2", &[29, 644, 10484]);
+
+        // the capture is forced, and losing the last piece wins
+        let mut game = Game::from_variant_fen(Variant::Antichess, "8/8/8/8/3p4/4P3/8/3K4 w - - 0 1").unwrap();
+        assert_eq!(game.get_possible_moves("D1"), Some(vec![]));
+        assert_eq!(game.get_possible_moves("E3"), Some(vec!["D4".to_string()]));
+        assert!(game.make_move("E3", "D4"));
+        assert_eq!(game.get_ending(), Some(Ending::NoMovesLeft { white_wins: false }));
+
+        // the king is taken like any piece, even by walking into what would be check
+        let mut game = Game::from_variant_fen(Variant::Antichess, "8/8/8/8/8/8/3q4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.get_possible_moves("E1"), Some(vec!["D2".to_string()]));
+        let mut game = Game::from_variant_fen(Variant::Antichess, "8/8/8/8/8/8/3q4/4K3 b - - 0 1").unwrap();
+        assert!(game.make_move("D2", "E1"));
+        assert_eq!(game.get_ending(), Some(Ending::NoMovesLeft { white_wins: true }));
+    }
+
+    #[test]
+    fn horde() {
+        let start = Game::new_variant(Variant::Horde);
+        assert_eq!(start.to_fen(), super::HORDE_START);
+        assert_perft(Variant::Horde, super::HORDE_START, &[8, 128, 1274, 23310]);
+        assert_perft(Variant::Horde, "4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1", &[30, 241, 6633]);
+        assert_perft(Variant::Horde, "rnbqkbnr/6p1/2p1Pp1P/P1PPPP2/Pp4PP/1p2PPPP/1P2PPPP/PP1nPPPP b kq a3 0 18", &[34, 435, 14481]);
+
+        // a pawn stepping two squares from the first rank can not be taken en passant
+        let mut game = Game::from_variant_fen(Variant::Horde, "4k3/8/8/8/8/8/1p6/P7 w - - 0 1").unwrap();
+        assert!(game.make_move("A1", "A3"));
+        assert_eq!(game.get_possible_moves("B2").unwrap().len(), 1);
+        assert!(Game::from_variant_fen(Variant::Horde, "4k3/8/8/8/8/8/8/P3K3 w - - 0 1").is_err());
+
+        // white still mates the black king
+        let mut game = Game::from_variant_fen(Variant::Horde, "k7/2Q5/P7/8/8/8/8/8 w - - 0 1").unwrap();
+        assert!(game.make_move("C7", "B7"));
+        assert_eq!(game.get_ending(), Some(Ending::Checkmate { white_wins: true }));
+
+        // and loses once black has taken every white piece
+        let mut game = Game::from_variant_fen(Variant::Horde, "4k3/8/8/8/8/8/8/P3q3 b - - 0 1").unwrap();
+        assert!(game.make_move("E1", "A1"));
+        assert_eq!(game.get_ending(), Some(Ending::HordeCaptured));
+    }
+}